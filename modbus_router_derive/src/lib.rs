@@ -0,0 +1,222 @@
+//! Derive macros for the modbus router library.
+//!
+//! `#[derive(DeviceConnectors)]` implements the `DeviceConnectors` trait (router & poller
+//! plumbing of a `Device`) for a struct with the following fields:
+//! - `router`: `Option<RouterConnector<Request, Response>>`
+//! - `poller`: `Option<PollerConnector<DeviceRequest, DeviceResponse>>`
+//! - `id`: `ModbusId`, the logical id of the device.
+//! - `slave_id` (optional): `ModbusId`, the physical id of the device on its bus, `id` is used
+//!   if missing.
+//!
+//! Fields can have another name if they are tagged with `#[device(router)]`, `#[device(poller)]`,
+//! `#[device(id)]` or `#[device(slave_id)]`. `Request`/`Response` & `DeviceRequest`/`DeviceResponse`
+//! types are taken from the connectors types.
+//!
+//! The generated code refers to the library through the name it has in the `Cargo.toml` of the
+//! deriving crate (`lib` unless the dependency is renamed). It can be set explicitly with
+//! `#[device(crate = path)]` on the struct, e.g. if the library is re-exported by another crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use proc_macro_crate::{crate_name, FoundCrate};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Field, Fields, GenericArgument, Ident, Path,
+    PathArguments, Type};
+
+/// Name of the library package.
+const PACKAGE: &str = "modbus_router";
+
+/// Name of the library target of `PACKAGE`, used when the dependency is not renamed.
+const LIB: &str = "lib";
+
+#[proc_macro_derive(DeviceConnectors, attributes(device))]
+pub fn derive_device_connectors(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => f.named.iter().collect::<Vec<_>>(),
+            _ => return Err(Error::new_spanned(&input.ident, "DeviceConnectors requires named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "DeviceConnectors can only be derived for structs")),
+    };
+
+    let router = find_field(&fields, "router")?
+        .ok_or_else(|| missing_field(&input, "router", "Option<RouterConnector<Request, Response>>"))?;
+    let poller = find_field(&fields, "poller")?
+        .ok_or_else(|| missing_field(&input, "poller", "Option<PollerConnector<DeviceRequest, DeviceResponse>>"))?;
+    let id = find_field(&fields, "id")?
+        .ok_or_else(|| missing_field(&input, "id", "ModbusId"))?;
+    let slave_id = find_field(&fields, "slave_id")?.unwrap_or(id);
+
+    let (request, response) = connector_types(router, "RouterConnector")?;
+    let (device_request, device_response) = connector_types(poller, "PollerConnector")?;
+
+    let router = router.ident.as_ref().expect("named field");
+    let poller = poller.ident.as_ref().expect("named field");
+    let id = id.ident.as_ref().expect("named field");
+    let slave_id = slave_id.ident.as_ref().expect("named field");
+
+    let krate = crate_path(&input)?;
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::traits::device::DeviceConnectors<#request, #response, #device_request, #device_response>
+            for #name #ty_generics #where_clause
+        {
+            fn set_poller(&mut self, connector: #krate::traits::polling::PollerConnector<#device_request, #device_response>) {
+                self.#poller = ::std::option::Option::Some(connector);
+            }
+
+            fn set_router(&mut self, connector: #krate::traits::routing::RouterConnector<#request, #response>) {
+                self.#router = ::std::option::Option::Some(connector);
+            }
+
+            fn id(&self) -> #krate::modbus::ModbusId {
+                self.#id
+            }
+
+            fn slave_id(&self) -> #krate::modbus::ModbusId {
+                self.#slave_id
+            }
+
+            fn is_external_connected(&self) -> bool {
+                self.#router.is_some()
+            }
+
+            fn is_device_connected(&self) -> bool {
+                self.#poller.is_some()
+            }
+
+            fn read_external_request(&mut self) -> ::std::result::Result<::std::option::Option<#request>, #krate::error::Error> {
+                self.#router.as_mut()
+                    .map(|r| r.receiver.try_recv().ok())
+                    .ok_or(#krate::error::Error::RouterNotConnected)
+            }
+
+            fn send_external_response(&mut self, response: #response) {
+                #krate::log::debug!("{}.send_external_response({:?})", #name_str, response);
+                if let ::std::option::Option::Some(router) = self.#router.as_mut() {
+                    if router.sender.send(response).is_err() {
+                        #krate::log::debug!("Cannot send response: {:?}", response);
+                    }
+                } else {
+                    #krate::log::error!("{}.send_external_response() router not connected!", #name_str);
+                }
+            }
+
+            fn read_device_response(&mut self) -> ::std::result::Result<
+                ::std::option::Option<#krate::traits::polling::PollerMessage<#device_request, #device_response>>,
+                #krate::error::Error
+            > {
+                self.#poller.as_mut()
+                    .map(|p| p.receiver.try_recv().ok())
+                    .ok_or(#krate::error::Error::PollerNotConnected)
+            }
+        }
+    })
+}
+
+/// Return the path of the library: the one given with `#[device(crate = path)]`, or else the
+/// name of the library in the `Cargo.toml` of the deriving crate.
+fn crate_path(input: &DeriveInput) -> Result<Path, Error> {
+    let mut path = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("device")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                path = Some(meta.value()?.parse::<Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `crate = path`"))
+            }
+        })?;
+    }
+    if let Some(path) = path {
+        return Ok(path);
+    }
+    let name = match crate_name(PACKAGE) {
+        // the library itself (`extern crate self as lib`), its tests and binaries
+        Ok(FoundCrate::Itself) => LIB.to_string(),
+        // a dependency renamed with `package = "modbus_router"` takes the new name
+        Ok(FoundCrate::Name(name)) if name != PACKAGE => name,
+        Ok(FoundCrate::Name(_)) => LIB.to_string(),
+        Err(e) => return Err(Error::new(
+            Span::call_site(),
+            format!("{}, or set the library path with `#[device(crate = path)]`", e),
+        )),
+    };
+    let ident = Ident::new(&name, Span::call_site());
+    Ok(syn::parse_quote!(::#ident))
+}
+
+/// Return true if the field is tagged with `#[device(<name>)]`.
+fn has_tag(field: &Field, name: &str) -> Result<bool, Error> {
+    let mut tagged = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("device")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(name) {
+                tagged = true;
+                Ok(())
+            } else if ["router", "poller", "id", "slave_id"].iter().any(|n| meta.path.is_ident(n)) {
+                Ok(())
+            } else {
+                Err(meta.error("expected one of `router`, `poller`, `id`, `slave_id`"))
+            }
+        })?;
+    }
+    Ok(tagged)
+}
+
+/// Find the field tagged with `#[device(<name>)]`, or else the field named `<name>`.
+fn find_field<'a>(fields: &[&'a Field], name: &str) -> Result<Option<&'a Field>, Error> {
+    for field in fields {
+        if has_tag(field, name)? {
+            return Ok(Some(field));
+        }
+    }
+    Ok(fields.iter()
+        .find(|f| f.ident.as_ref().is_some_and(|i| i == name))
+        .copied())
+}
+
+fn missing_field(input: &DeriveInput, name: &str, ty: &str) -> Error {
+    Error::new_spanned(
+        &input.ident,
+        format!("missing field `{}: {}`, or a field tagged with `#[device({})]`", name, ty, name),
+    )
+}
+
+/// Extract the 2 generic types of a `Option<Connector<A, B>>` field.
+fn connector_types<'a>(field: &'a Field, connector: &str) -> Result<(&'a Type, &'a Type), Error> {
+    let error = || Error::new_spanned(&field.ty, format!("expected `Option<{}<_, _>>`", connector));
+    let option = generic_types(&field.ty, "Option").ok_or_else(error)?;
+    let [inner] = option.as_slice() else { return Err(error()) };
+    let types = generic_types(inner, connector).ok_or_else(error)?;
+    match types.as_slice() {
+        [a, b] => Ok((a, b)),
+        _ => Err(error()),
+    }
+}
+
+/// Return the generic types of `ty` if its last path segment is `name`.
+fn generic_types<'a>(ty: &'a Type, name: &str) -> Option<Vec<&'a Type>> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    Some(args.args.iter()
+        .filter_map(|a| match a {
+            GenericArgument::Type(t) => Some(t),
+            _ => None,
+        })
+        .collect())
+}
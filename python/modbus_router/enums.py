@@ -45,6 +45,8 @@ class RequestType(Enum):
     VFD_RESPONSE = 2
     JOYSTICK_REQUEST = 3
     JOYSTICK_RESPONSE = 4
+    REGISTER_MAP_REQUEST = 5
+    REGISTER_MAP_RESPONSE = 6
     
     
 @into_int
@@ -52,6 +54,17 @@ class VfdFnCode(Enum):
     RUN = 1
     STOP = 2
     STATUS = 3
+    FREQUENCY = 4
+    CURRENT = 5
+    VOLTAGE = 6
+    FAULT_CODE = 7
+    RESET = 8
+    READ_PARAMETER = 9
+    WRITE_PARAMETER = 10
+    GROUP_DONE = 11
+    POLLING = 12
+    ERROR = 0x80
+    EVENT = 0x81
 
 
 @into_int
@@ -61,6 +74,28 @@ class JoystickFnCode(Enum):
     BUTTON = 3
     X_THUMB = 4
     Y_THUMB = 5
+    ERROR = 0x80
+    EVENT = 0x81
+
+
+@into_int
+class ErrorKind(Enum):
+    MODBUS_EXCEPTION = 1
+    COMM_FAILURE = 2
+    REJECTED = 3
+
+
+@into_int
+class EventKind(Enum):
+    ONLINE = 1
+    OFFLINE = 2
+    COMM_ERROR_BURST = 3
+    PORT_DOWN = 4
+    PORT_UP = 5
+    CONFIGURED = 6
+    FAULT = 7
+    NOT_FOLLOWING = 8
+    FOLLOWING = 9
 
 
 @into_int
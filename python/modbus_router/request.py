@@ -50,7 +50,7 @@ class Request:
         
         if self.type == RequestType.VFD_REQUEST:
             match self.function:
-                case VfdFnCode.RUN:
+                case VfdFnCode.RUN | VfdFnCode.READ_PARAMETER | VfdFnCode.WRITE_PARAMETER | VfdFnCode.POLLING:
                     frame[3] = self.data1
                     frame[4] = self.data2
                     frame[5] = self.data3
@@ -91,9 +91,23 @@ class Request:
         
     @staticmethod
     def vfd_run(id: int, hertz: float):
-        # convert float to i16
-        ref = round(hertz * 100)
-        ref = max(-32767, min(ref, 32767))
+        """Reference in Hz, sent in hundredths."""
+        return Request._vfd_run(id, round(hertz * 100))
+    
+    @staticmethod
+    def vfd_run_rpm(id: int, rpm: float):
+        """Reference in rpm, for a drive configured with a nameplate in rpm."""
+        return Request._vfd_run(id, round(rpm))
+    
+    @staticmethod
+    def vfd_run_percent(id: int, percent: float):
+        """Reference in percent of the maximum frequency, sent in hundredths."""
+        return Request._vfd_run(id, round(percent * 100))
+    
+    @staticmethod
+    def _vfd_run(id: int, ref: int):
+        if not -32767 <= ref <= 32767:
+            raise ValueError(f"reference {ref} does not fit an i16")
         if ref < 0:
             data1 = 1
         else:
@@ -121,4 +135,49 @@ class Request:
                        RequestType.VFD_REQUEST,
                        VfdFnCode.STATUS)
     
+    @staticmethod
+    def vfd_telemetry(id: int, function: VfdFnCode):
+        """`function` is one of FREQUENCY, CURRENT, VOLTAGE or FAULT_CODE."""
+        return Request(ModbusId(id),
+                       RequestType.VFD_REQUEST,
+                       function)
+    
+    @staticmethod
+    def vfd_reset(id: int):
+        return Request(ModbusId(id),
+                       RequestType.VFD_REQUEST,
+                       VfdFnCode.RESET)
+    
+    @staticmethod
+    def vfd_read_parameter(id: int, address: int, quantity: int = 1):
+        """Read `quantity` registers from `address`, answered with one READ_PARAMETER
+        response per register (`offset` in the block)."""
+        return Request(ModbusId(id),
+                       RequestType.VFD_REQUEST,
+                       VfdFnCode.READ_PARAMETER,
+                       quantity,
+                       (address & 0xff00) >> 8,
+                       address & 0x00ff)
+    
+    @staticmethod
+    def vfd_write_parameter(id: int, offset: int, value: int):
+        """Write the register at `offset` from the address of the last parameter read."""
+        return Request(ModbusId(id),
+                       RequestType.VFD_REQUEST,
+                       VfdFnCode.WRITE_PARAMETER,
+                       offset,
+                       (value & 0xff00) >> 8,
+                       value & 0x00ff)
+    
+    @staticmethod
+    def vfd_polling(id: int, poll_status: bool, auto_update: bool):
+        """Switch the status polling and the pushed status updates of the drive, answered
+        with a POLLING response."""
+        return Request(ModbusId(id),
+                       RequestType.VFD_REQUEST,
+                       VfdFnCode.POLLING,
+                       int(poll_status),
+                       int(auto_update),
+                       0)
+    
     
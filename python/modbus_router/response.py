@@ -8,6 +8,10 @@ class Response:
                  type: RequestType = None,
                  function: VfdFnCode | JoystickFnCode = None,
                  value: int = None,
+                 error: ErrorKind = None,
+                 event: EventKind = None,
+                 offset: int = None,
+                 detail: int = None,
                  ):
         
         if type in [RequestType.VFD_REQUEST, RequestType.JOYSTICK_REQUEST]:
@@ -19,6 +23,10 @@ class Response:
         self.type = type
         self.function = function
         self.value = value
+        self.error = error
+        self.event = event
+        self.offset = offset
+        self.detail = detail
     
     def is_valid(self):
         if not isinstance(self.id, ModbusId):
@@ -82,14 +90,44 @@ class Response:
         
         # deserializing data
         value = 0
+        error = None
+        event = None
+        offset = None
+        detail = None
         match fn_code:
-            case VfdFnCode.RUN:
-                print("RUN response are not expected")
-                return None
+            case VfdFnCode.RUN | VfdFnCode.STOP | VfdFnCode.RESET:
+                # the request is accepted by the drive
+                value = 0
             
-            case VfdFnCode.STOP:
-                print("STOP response are not expected")
-                return None
+            case VfdFnCode.ERROR | JoystickFnCode.ERROR:
+                error = ErrorKind.from_int(frame[3])
+                if error is None:
+                    print("Invalid error kind")
+                    return None
+                value = frame[4]
+                # interlocking device of a rejected request
+                detail = frame[5]
+            
+            case VfdFnCode.EVENT | JoystickFnCode.EVENT:
+                event = EventKind.from_int(frame[3])
+                if event is None:
+                    print("Invalid event kind")
+                    return None
+                value = (frame[4] << 8) + frame[5]
+            
+            case VfdFnCode.READ_PARAMETER | VfdFnCode.WRITE_PARAMETER:
+                offset = frame[3]
+                value = (frame[4] << 8) + frame[5]
+            
+            case VfdFnCode.POLLING:
+                # status polling, and pushed status updates in `detail`
+                value = frame[3]
+                detail = frame[4]
+            
+            case VfdFnCode.GROUP_DONE:
+                # members that did not apply the command, out of `detail` members
+                value = frame[3]
+                detail = frame[4]
             
             case _:
                 if frame[3] not in [0, 1]:
@@ -99,7 +137,7 @@ class Response:
                 if frame[3] == 1:
                     value = -value
         
-        out = Response(id, type, fn_code, value)
+        out = Response(id, type, fn_code, value, error, event, offset, detail)
         if out.is_valid():
             return out
         else:
@@ -57,9 +57,32 @@ def test_request():
     
     assert Request.vfd_run(3, 50.00).to_frame() == [3, 1, 1, 0, 19, 136, 49, 66]
     assert Request.vfd_run(3, -50.00).to_frame() == [3, 1, 1, 1, 19, 136, 96, 130]
+    assert Request.vfd_run_percent(3, 50.00).to_frame() == Request.vfd_run(3, 50.00).to_frame()
+    assert Request.vfd_run_rpm(3, -1500).to_frame()[:6] == [3, 1, 1, 1, 5, 220]
+    try:
+        Request.vfd_run(3, 400.00)
+        assert False, "out of range reference accepted"
+    except ValueError:
+        pass
     
     assert Request.vfd_status(3).to_frame() == [3, 1, 3, 0, 0, 0, 61, 172]
     
+    frame = Request.vfd_telemetry(3, VfdFnCode.CURRENT).to_frame()
+    assert frame[:6] == [3, 1, 5, 0, 0, 0]
+    assert frame[6:] == crc16(frame[:6])
+    
+    frame = Request.vfd_reset(3).to_frame()
+    assert frame[:6] == [3, 1, 8, 0, 0, 0]
+    assert frame[6:] == crc16(frame[:6])
+    
+    frame = Request.vfd_read_parameter(3, 0xF00A, 3).to_frame()
+    assert frame[:6] == [3, 1, 9, 3, 0xF0, 0x0A]
+    assert frame[6:] == crc16(frame[:6])
+    
+    frame = Request.vfd_write_parameter(3, 1, 150).to_frame()
+    assert frame[:6] == [3, 1, 10, 1, 0, 150]
+    assert frame[6:] == crc16(frame[:6])
+    
     assert Request(3,
                    RequestType.VFD_REQUEST,
                    VfdFnCode.STOP).is_valid() is False
@@ -121,6 +144,130 @@ def test_response_negative_value():
     assert response.value == -500  # Assuming correct interpretation for negative values
 
 
+def test_response_error():
+    # Vfd answered with IllegalDataAddress exception
+    frame = frame_response([10, 2, 0x80, 1, 0x02, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.ERROR
+    assert response.error == ErrorKind.MODBUS_EXCEPTION
+    assert response.value == 2
+    
+    # Joystick answered with ServerDeviceBusy exception
+    frame = frame_response([5, 4, 0x80, 1, 0x06, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == JoystickFnCode.ERROR
+    assert response.value == 6
+    
+    # Vfd run request rejected, interlocked by Vfd 12
+    frame = frame_response([20, 2, 0x80, 3, 8, 12])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.error == ErrorKind.REJECTED
+    assert response.value == 8
+    assert response.detail == 12
+    
+    # Vfd did not answer after all retry attempts
+    frame = frame_response([10, 2, 0x80, 2, 0, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.error == ErrorKind.COMM_FAILURE
+    
+    # Unknown error kind
+    frame = frame_response([10, 2, 0x80, 99, 0x02, 0])
+    assert Response.from_frame(frame) is None
+
+
+def test_response_telemetry():
+    # Vfd motor current
+    frame = frame_response([10, 2, 5, 0, 0x01, 0x2c])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.CURRENT
+    assert response.value == 300
+
+
+def test_response_parameter():
+    # third register of a parameter block
+    frame = frame_response([10, 2, 9, 2, 0x13, 0x88])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.READ_PARAMETER
+    assert response.offset == 2
+    assert response.value == 5000
+
+
+def test_response_parameter_written():
+    # second register of the last block read is written
+    frame = frame_response([10, 2, 10, 1, 0x00, 0x96])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.WRITE_PARAMETER
+    assert response.offset == 1
+    assert response.value == 150
+
+
+def test_request_polling():
+    assert Request.vfd_polling(10, True, False).to_frame()[:6] == [10, 1, 12, 1, 0, 0]
+
+
+def test_response_accepted():
+    # run request accepted by the drive
+    frame = frame_response([10, 2, 1, 0, 0, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.RUN
+    assert response.value == 0
+
+
+def test_response_polling():
+    # status polled, updates not pushed
+    frame = frame_response([10, 2, 12, 1, 0, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.POLLING
+    assert response.value == 1
+    assert response.detail == 0
+
+
+def test_response_group_done():
+    # one of the 3 members of group 100 did not apply the command
+    frame = frame_response([100, 2, 11, 1, 3, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.GROUP_DONE
+    assert response.value == 1
+    assert response.detail == 3
+
+
+def test_response_event():
+    # Vfd stopped answering
+    frame = frame_response([10, 2, 0x81, 2, 0, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == VfdFnCode.EVENT
+    assert response.event == EventKind.OFFLINE
+    
+    # Joystick had 3 consecutive failed attempts
+    frame = frame_response([5, 4, 0x81, 3, 0, 3])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.function == JoystickFnCode.EVENT
+    assert response.event == EventKind.COMM_ERROR_BURST
+    assert response.value == 3
+    
+    # Vfd does not follow its command
+    frame = frame_response([10, 2, 0x81, 8, 0, 0])
+    response = Response.from_frame(frame)
+    assert response is not None
+    assert response.event == EventKind.NOT_FOLLOWING
+    
+    # Unknown event kind
+    frame = frame_response([10, 2, 0x81, 99, 0, 0])
+    assert Response.from_frame(frame) is None
+
+
 def test_response_unexpected_function_code():
     # Frame with an unexpected function code
     frame = [3, 2, 99, 0, 0x13, 0x88, 0xA5, 0xB4]  # Invalid function code
@@ -5,6 +5,7 @@ use lib::devices::joystick::requests::{JoystickRequest, JoystickResponse};
 use lib::devices::vfd::device::Vfd;
 use lib::devices::vfd::encoder::{VfdCommands, FRECON, MEGMEET};
 use lib::devices::vfd::requests::{VfdRequest, VfdResponse};
+use lib::error::Error;
 use lib::poller::ModbusPoller;
 use lib::router::StdRouter;
 use lib::soft_request::{SoftRequest, SoftResponse};
@@ -19,12 +20,14 @@ fn vfd(
     router: &mut StdRouter<SoftRequest, SoftResponse>,
     poller: &mut ModbusPoller<VfdRequest, VfdResponse>,
     list: &mut Vec<Vfd>,
-) {
-    let mut vfd_list = Vfd::new(id.into(), vfd, false);
-    vfd_list.connect_poller(poller);
-    vfd_list.connect_router(router);
+) -> Result<(), Error> {
+    // status is polled, it can be switched off at runtime (see `SoftRequest::Polling`)
+    let mut vfd_list = Vfd::new(id.into(), vfd, true);
+    vfd_list.connect_poller(poller)?;
+    vfd_list.connect_router(router)?;
 
     list.push(vfd_list);
+    Ok(())
 }
 
 fn joystick(
@@ -32,16 +35,16 @@ fn joystick(
     joystick_type: JoystickType,
     router: &mut StdRouter<SoftRequest, SoftResponse>,
     poller: &mut ModbusPoller<JoystickRequest, JoystickResponse>,
-) -> Joystick {
+) -> Result<Joystick, Error> {
     let mut joy = Joystick::new(id.into(), joystick_type);
-    joy.connect_poller(poller);
-    joy.connect_router(router);
+    joy.connect_poller(poller)?;
+    joy.connect_router(router)?;
 
-    joy
+    Ok(joy)
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), Error> {
     let port0 = "/dev/ttyXR6";
     let port1 = "/dev/ttyXR7";
     let port2 = "/dev/ttyXR2";
@@ -54,7 +57,7 @@ async fn main() {
     let poller_0 = {
         let mut poller = ModbusPoller::new(port0, Baud115200, Some(1), Some(1), Some(5));
 
-        joystick(0x05, JoystickType::Joystick, &mut router, &mut poller).start();
+        joystick(0x05, JoystickType::Joystick, &mut router, &mut poller)?.start();
         poller
     };
     poller_0.start();
@@ -63,7 +66,7 @@ async fn main() {
     let poller_1 = {
         let mut poller = ModbusPoller::new(port1, Baud115200, Some(1), Some(1), Some(5));
 
-        joystick(0x06, JoystickType::Joystick, &mut router, &mut poller).start();
+        joystick(0x06, JoystickType::Joystick, &mut router, &mut poller)?.start();
         poller
     };
     poller_1.start();
@@ -73,10 +76,10 @@ async fn main() {
         let mut poller = ModbusPoller::new(port2, Baud115200, Some(1), Some(1), Some(5));
         let mut vfd_list: Vec<Vfd> = Vec::new();
 
-        vfd(10, MEGMEET, &mut router, &mut poller, &mut vfd_list);
-        vfd(11, MEGMEET, &mut router, &mut poller, &mut vfd_list);
-        vfd(60, MEGMEET, &mut router, &mut poller, &mut vfd_list);
-        vfd(61, MEGMEET, &mut router, &mut poller, &mut vfd_list);
+        vfd(10, MEGMEET, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(11, MEGMEET, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(60, MEGMEET, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(61, MEGMEET, &mut router, &mut poller, &mut vfd_list)?;
 
         for vfd in vfd_list {
             vfd.start()
@@ -91,11 +94,11 @@ async fn main() {
         let mut poller = ModbusPoller::new(port3, Baud115200, Some(3), Some(6), Some(6));
         let mut vfd_list: Vec<Vfd> = Vec::new();
 
-        vfd(12, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(20, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(21, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(26, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(27, FRECON, &mut router, &mut poller, &mut vfd_list);
+        vfd(12, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(20, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(21, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(26, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(27, FRECON, &mut router, &mut poller, &mut vfd_list)?;
 
         for vfd in vfd_list {
             vfd.start()
@@ -110,12 +113,12 @@ async fn main() {
         let mut poller = ModbusPoller::new(port4, Baud115200, Some(3), Some(6), Some(6));
         let mut vfd_list: Vec<Vfd> = Vec::new();
 
-        vfd(30, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(31, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(40, MEGMEET, &mut router, &mut poller, &mut vfd_list);
-        vfd(43, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(50, FRECON, &mut router, &mut poller, &mut vfd_list);
-        vfd(51, MEGMEET, &mut router, &mut poller, &mut vfd_list);
+        vfd(30, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(31, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(40, MEGMEET, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(43, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(50, FRECON, &mut router, &mut poller, &mut vfd_list)?;
+        vfd(51, MEGMEET, &mut router, &mut poller, &mut vfd_list)?;
 
         for vfd in vfd_list {
             vfd.start()
@@ -128,4 +131,5 @@ async fn main() {
     tokio::time::sleep(Duration::from_millis(100)).await;
     // start router
     router.start().await;
+    Ok(())
 }
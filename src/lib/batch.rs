@@ -1,15 +1,136 @@
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::time::Duration;
 use serial_thread::SerialMessage;
+use tokio::time::Instant;
+use modbus_core::rtu::crc16;
 use crate::modbus::ModbusId;
 use crate::traits::device_encoder::DeviceEncoder;
+use crate::traits::polling::PollerMessage;
 
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Defines when a failed request is sent again.
+///
+/// ## Variants
+/// - `Immediate`: The request is sent again in the same batch, before the next request.
+/// - `NextCycle`: The request is handed back to the device, that will push it in its next batch.
+pub enum RetryMode {
+    Immediate,
+    NextCycle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Retry policy applied by a `Batch` on requests the `DeviceEncoder` flags as retryable.
+///
+/// Fields:
+/// - `max_attempts`: Maximum number of times a request is sent (first attempt included),
+///   `1` means no retry.
+/// - `mode`: Whether the request is retried immediately or on the next poll cycle.
+/// - `backoff`: Optional delay before the first retry, doubled after each failed retry.
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub mode: RetryMode,
+    pub backoff: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    /// Failed requests are not retried.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            mode: RetryMode::NextCycle,
+            backoff: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub const fn new(max_attempts: u32, mode: RetryMode, backoff: Option<Duration>) -> Self {
+        RetryPolicy {
+            max_attempts,
+            mode,
+            backoff,
+        }
+    }
+
+    /// Return the delay to wait before sending again a request that already failed `attempts` times.
+    pub fn backoff(&self, attempts: u32) -> Option<Duration> {
+        self.backoff
+            .map(|b| b.saturating_mul(1 << attempts.saturating_sub(1).min(16)))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A request and the count of times it has already been sent.
+///
+/// Fields:
+/// - `request`: The device request.
+/// - `attempts`: Number of failed attempts for this request.
+/// - `not_before`: Optional instant before which the request should not be sent (backoff).
+pub struct Attempt<DeviceRequest> {
+    pub request: DeviceRequest,
+    pub attempts: u32,
+    pub not_before: Option<Instant>,
+}
+
+impl<DeviceRequest> Attempt<DeviceRequest> {
+    pub fn new(request: DeviceRequest) -> Self {
+        Attempt {
+            request,
+            attempts: 0,
+            not_before: None,
+        }
+    }
+
+    /// Return true if the backoff delay (if any) is elapsed.
+    pub fn is_due(&self) -> bool {
+        self.not_before.is_none_or(|t| Instant::now() >= t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A group of devices whose requests are sent together.
+///
+/// Fields:
+/// - `id`: The id of the group.
+/// - `seq`: The sequence number of the group command the requests belong to.
+/// - `broadcast`: If true, identical requests of the group members are sent in a single
+///   broadcast frame.
+pub struct SyncGroup {
+    pub id: ModbusId,
+    pub seq: u16,
+    pub broadcast: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// An entry of a `Batch`.
+///
+/// Fields:
+/// - `attempt`: The request to send.
+/// - `dependent`: If true, the request is only sent if the previous request of the batch succeed.
+/// - `sync`: The group the request is synchronized with, if any.
+struct Step<DeviceRequest> {
+    attempt: Attempt<DeviceRequest>,
+    dependent: bool,
+    sync: Option<SyncGroup>,
+}
 
 #[derive(Debug)]
+/// An ordered list of requests sent by a device to its poller, requests are sent in push order.
+///
+/// A request pushed with `Batch::then()` depends on the previous one: if the previous request
+/// fails, it is not sent.
+///
+/// Requests pushed with `Batch::sync()` are synchronized with the requests of the other
+/// members of their `SyncGroup`: the poller sends them back-to-back (or in a single broadcast
+/// frame), before the other requests of the batches.
 pub struct Batch<DeviceRequest, DeviceResponse>
 {
     encoder: Box<dyn DeviceEncoder<DeviceRequest, DeviceResponse>>,
-    requests: Vec<DeviceRequest>,
-    current_request: Option<DeviceRequest>,
+    policy: RetryPolicy,
+    requests: VecDeque<Step<DeviceRequest>>,
+    current_request: Option<Step<DeviceRequest>>,
     pub(crate) id: ModbusId,
 }
 
@@ -20,8 +141,9 @@ where
 {
     pub fn new(id: ModbusId, encoder: Box<dyn DeviceEncoder<DeviceRequest, DeviceResponse>>) -> Self {
         Batch {
+            policy: encoder.retry_policy(),
             encoder,
-            requests: vec![],
+            requests: VecDeque::new(),
             current_request: None,
             id,
         }
@@ -31,15 +153,75 @@ where
     /// Yield the next request, return None if no requests remains or if the current request
     /// have not yet been answered.
     pub fn next(&mut self) -> Option<SerialMessage> {
-        if !self.requests.is_empty() && self.current_request.is_none() {
-            let request = self.requests.pop().unwrap();
-            self.current_request = Some(request);
-            self.encoder.request_to_serial(request)
+        if self.current_request.is_none() {
+            let step = self.requests.pop_front()?;
+            self.current_request = Some(step);
+            self.encoder.request_to_serial(step.attempt.request)
         } else {
             None
         }
     }
 
+    /// Return the delay to wait before the next request can be sent, if it is an immediate
+    /// retry with backoff.
+    pub fn backoff(&self) -> Option<Duration> {
+        self.requests.front()
+            .and_then(|s| s.attempt.not_before)
+            .map(|t| t.saturating_duration_since(Instant::now()))
+    }
+
+    /// Return the groups the remaining requests are synchronized with.
+    pub fn sync_groups(&self) -> Vec<SyncGroup> {
+        let mut groups: Vec<SyncGroup> = vec![];
+        for group in self.requests.iter().filter_map(|s| s.sync) {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    /// Return true if a remaining request is synchronized with `group`.
+    pub fn has_sync(&self, group: SyncGroup) -> bool {
+        self.requests.iter().any(|s| s.sync == Some(group))
+    }
+
+    /// Return true if the next request is synchronized with `group`.
+    pub fn next_is_sync(&self, group: SyncGroup) -> bool {
+        self.requests.front().is_some_and(|s| s.sync == Some(group))
+    }
+
+    /// Return the broadcast frame of the next request, `None` if it is not synchronized with a
+    /// broadcast group or if the encoder cannot broadcast it.
+    pub fn broadcast_frame(&self) -> Option<Vec<u8>> {
+        let step = self.requests.front()?;
+        if !step.sync.is_some_and(|g| g.broadcast) || step.attempt.not_before.is_some() {
+            return None;
+        }
+        self.encoder.broadcast_response(step.attempt.request)?;
+        match self.encoder.request_to_serial(step.attempt.request)? {
+            SerialMessage::Send(mut frame) if frame.len() > 3 => {
+                let len = frame.len();
+                frame[0] = ModbusId::Broadcast.into();
+                let crc = crc16(&frame[..len - 2]);
+                frame[len - 2] = ((crc & 0xff00) >> 8) as u8;
+                frame[len - 1] = (crc & 0x00ff) as u8;
+                Some(frame)
+            }
+            _ => None,
+        }
+    }
+
+    /// The next request have been sent in a broadcast frame, that is not answered: return the
+    /// response the device expects.
+    pub fn handle_broadcast(&mut self) -> Vec<PollerMessage<DeviceRequest, DeviceResponse>> {
+        self.requests.pop_front()
+            .and_then(|step| self.encoder.broadcast_response(step.attempt.request))
+            .map(PollerMessage::Response)
+            .into_iter()
+            .collect()
+    }
+
     /// Return true if no request remaining and current request is None.
     pub fn is_empty(&self) -> bool {
         self.requests.is_empty() && self.current_request.is_none()
@@ -50,27 +232,94 @@ where
         self.current_request.is_none()
     }
 
-    /// Try to handle the response, return None if the response is not related to the current 
-    /// request (or if no current request)
-    pub fn handle_response(&mut self, msg: SerialMessage) -> Option<DeviceResponse> {
+    /// Remove the requests depending on a failed request.
+    fn take_dependents(&mut self) -> Vec<Attempt<DeviceRequest>> {
+        let mut dependents = vec![];
+        while let Some(step) = self.requests.front() {
+            if !step.dependent {
+                break;
+            }
+            dependents.push(self.requests.pop_front().expect("not empty").attempt);
+        }
+        dependents
+    }
+
+    /// Try to handle the response, return an empty list if the response is not related to the
+    /// current request (or if no current request), or if the request will be retried immediately.
+    ///
+    /// If the response is a retryable failure, the request is either pushed back in this batch
+    /// (`RetryMode::Immediate`) or handed back to the device as `PollerMessage::Retry`
+    /// (`RetryMode::NextCycle`) with the requests depending on it, until `max_attempts` is
+    /// reached. Then the failure is sent to the device as a regular response, and the
+    /// requests depending on it are dropped and sent back as `PollerMessage::Aborted`.
+    pub fn handle_response(&mut self, msg: SerialMessage) -> Vec<PollerMessage<DeviceRequest, DeviceResponse>> {
         if self.current_request.is_none() {
             log::error!("Batch.handle_response() => cannot decode response, as there is no current request!");
-            None
+            vec![]
         }else if let Some(m) = self.encoder.filter_response(msg) {
-            if let Some(request) = self.current_request.take() {
-                Some(self.encoder.serial_to_response(m, request, self.id))
+            if let Some(mut step) = self.current_request.take() {
+                let response = self.encoder.serial_to_response(m, step.attempt.request, self.id);
+                step.attempt.attempts += 1;
+                if self.encoder.should_retry(&response) && step.attempt.attempts < self.policy.max_attempts {
+                    step.attempt.not_before = self.policy.backoff(step.attempt.attempts).map(|d| Instant::now() + d);
+                    log::debug!("Batch.handle_response() retry {:?}", step.attempt);
+                    match self.policy.mode {
+                        RetryMode::Immediate => {
+                            self.requests.push_front(step);
+                            vec![]
+                        }
+                        RetryMode::NextCycle => {
+                            // dependents are sent back in order, with the same backoff
+                            let not_before = step.attempt.not_before;
+                            let mut out = vec![PollerMessage::Retry(step.attempt)];
+                            out.extend(self.take_dependents().into_iter().map(|mut a| {
+                                a.not_before = not_before;
+                                PollerMessage::Retry(a)
+                            }));
+                            out
+                        }
+                    }
+                } else if self.encoder.is_failure(&response) {
+                    let mut out = vec![PollerMessage::Response(response)];
+                    out.extend(self.take_dependents().into_iter().map(|a| {
+                        log::error!("Batch.handle_response() abort {:?}", a.request);
+                        PollerMessage::Aborted(a.request)
+                    }));
+                    out
+                } else {
+                    vec![PollerMessage::Response(response)]
+                }
             } else {
                 panic!("Cannot handle response if no current request")
             }
-            
+
         } else {
-            None
+            vec![]
         }
     }
-    
+
+    /// Push a request at the end of the batch.
     pub fn push(&mut self, request: DeviceRequest) {
-        log::debug!("Vfd.Batch.push({:?}", &request);
-        self.requests.push(request);
-        
+        log::debug!("Batch.push({:?}", &request);
+        self.push_retry(Attempt::new(request));
     }
-}
\ No newline at end of file
+
+    /// Push a request that already failed, keeping its attempts count.
+    pub fn push_retry(&mut self, attempt: Attempt<DeviceRequest>) {
+        log::debug!("Batch.push_retry({:?}", &attempt);
+        self.requests.push_back(Step { attempt, dependent: false, sync: None });
+    }
+
+    /// Push a request that is only sent if the previous one succeed.
+    pub fn then(&mut self, attempt: Attempt<DeviceRequest>) {
+        log::debug!("Batch.then({:?}", &attempt);
+        self.requests.push_back(Step { attempt, dependent: true, sync: None });
+    }
+
+    /// Push a request synchronized with `group`, it is only sent if the previous one succeed
+    /// if `dependent` is true.
+    pub fn sync(&mut self, attempt: Attempt<DeviceRequest>, group: SyncGroup, dependent: bool) {
+        log::debug!("Batch.sync({:?}, {:?})", &attempt, group);
+        self.requests.push_back(Step { attempt, dependent, sync: Some(group) });
+    }
+}
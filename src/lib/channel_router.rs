@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::time::sleep;
+use crate::error::Error;
+use crate::group::Groups;
+use crate::modbus::{FunctionType, ModbusId};
+use crate::soft_request::{SoftRequest, SoftResponse};
+use crate::traits::request::RequestFn;
+use crate::traits::routing::{RouterConnector, Routing};
+
+/// Default delay a `RouterHandle` waits for a device response.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Capacity of the updates channel, slow subscribers miss older updates.
+const UPDATES_CAPACITY: usize = 256;
+
+type Reply = oneshot::Sender<Result<SoftResponse, Error>>;
+
+#[derive(Debug)]
+/// Routes typed requests from in-process `RouterHandle`s to devices.
+///
+/// A request to a device is resolved by the next matching response of the device (`Run`,
+/// `Stop` and `Reset` by their acknowledgement or rejection), every other response is pushed to
+/// the updates channel. A parameter read is resolved by the whole block. A request to a group
+/// (see `Routing::add_group()`) is fanned out to its members, the group completion is pushed to
+/// the updates channel.
+pub struct ChannelRouter {
+    requests: mpsc::UnboundedReceiver<(SoftRequest, Reply)>,
+    handle: RouterHandle,
+    receiver: Receiver<SoftResponse>,
+    connector: Sender<SoftResponse>,
+    senders: HashMap<ModbusId, Sender<SoftRequest>>,
+    pending: Vec<(SoftRequest, Reply)>,
+    groups: Groups,
+}
+
+#[derive(Debug, Clone)]
+/// In-process client of a `ChannelRouter`.
+pub struct RouterHandle {
+    sender: mpsc::UnboundedSender<(SoftRequest, Reply)>,
+    updates: broadcast::Sender<SoftResponse>,
+    timeout: Duration,
+}
+
+impl Default for ChannelRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChannelRouter {
+    pub fn new() -> Self {
+        let (sender, requests) = mpsc::unbounded_channel();
+        let (updates, _) = broadcast::channel(UPDATES_CAPACITY);
+        let (connector, receiver) = channel();
+        ChannelRouter {
+            requests,
+            handle: RouterHandle {
+                sender,
+                updates,
+                timeout: DEFAULT_TIMEOUT,
+            },
+            receiver,
+            connector,
+            senders: Default::default(),
+            pending: vec![],
+            groups: Default::default(),
+        }
+    }
+
+    /// Return a new handle to this router.
+    pub fn handle(&self) -> RouterHandle {
+        self.handle.clone()
+    }
+
+    /// Starts the run loop of the `Router` in a new thread.
+    pub fn start(mut self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    /// Return true if `response` answers `request`.
+    fn answers(request: &SoftRequest, response: &SoftResponse) -> bool {
+        match (request, response) {
+            (SoftRequest::Status(id), SoftResponse::Status(rid, _)) => id == rid,
+            (SoftRequest::ReadPoint(id, index), SoftResponse::Point(rid, rindex, _))
+            | (SoftRequest::WritePoint(id, index, _), SoftResponse::Point(rid, rindex, _)) => {
+                id == rid && index == rindex
+            }
+            (SoftRequest::Telemetry(id, telemetry), SoftResponse::Telemetry(rid, rtelemetry, _)) => {
+                id == rid && telemetry == rtelemetry
+            }
+            (SoftRequest::ReadParameter(id, address, quantity), SoftResponse::Parameters(rid, block)) => {
+                id == rid && *address == block.address && *quantity == block.quantity
+            }
+            (SoftRequest::WriteParameter(id, offset, value), SoftResponse::ParameterWritten(rid, _, roffset, rvalue)) => {
+                id == rid && offset == roffset && value == rvalue
+            }
+            (SoftRequest::Polling(id, _, _), SoftResponse::Polling(rid, _, _)) => id == rid,
+            (SoftRequest::Run(id, _), SoftResponse::Accepted(rid, FunctionType::Run))
+            | (SoftRequest::Stop(id), SoftResponse::Accepted(rid, FunctionType::Stop))
+            | (SoftRequest::Reset(id), SoftResponse::Accepted(rid, FunctionType::Reset)) => id == rid,
+            (SoftRequest::Run(id, _), SoftResponse::Error(rid, _, _))
+            | (SoftRequest::Reset(id), SoftResponse::Error(rid, _, _))
+            | (SoftRequest::ReadPoint(id, _), SoftResponse::Error(rid, _, _))
+            | (SoftRequest::WritePoint(id, _, _), SoftResponse::Error(rid, _, _))
+            | (SoftRequest::Telemetry(id, _), SoftResponse::Error(rid, _, _))
+            | (SoftRequest::ReadParameter(id, _, _), SoftResponse::Error(rid, _, _))
+            | (SoftRequest::WriteParameter(id, _, _), SoftResponse::Error(rid, _, _)) => id == rid,
+            _ => false,
+        }
+    }
+
+    fn handle_request(&mut self, request: SoftRequest, reply: Reply) {
+        log::debug!("ChannelRouter.handle_request({:?})", request);
+        match self.groups.fan_out(&request) {
+            Some(Ok(requests)) => {
+                for request in requests {
+                    self.transmit_request(request);
+                }
+                let _ = reply.send(Ok(SoftResponse::None));
+                return;
+            }
+            Some(Err(e)) => {
+                let _ = reply.send(Err(e));
+                return;
+            }
+            None => {}
+        }
+        match request.id() {
+            ModbusId::Id(_) => {
+                if !self.senders.contains_key(&request.id()) {
+                    let _ = reply.send(Err(Error::UnknownDevice(request.id())));
+                    return;
+                }
+                self.transmit_request(request);
+                self.pending.push((request, reply));
+            }
+            ModbusId::Broadcast => {
+                for id in self.devices_ids() {
+                    self.transmit_request(*request.new_id(id));
+                }
+                let _ = reply.send(Ok(SoftResponse::None));
+            }
+            ModbusId::Reserved => {
+                let _ = reply.send(Err(Error::WrongModbusId));
+            }
+        }
+    }
+}
+
+impl RouterHandle {
+    /// Set the delay to wait for a device response.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a request to a device.
+    ///
+    /// Broadcast & group requests resolve to `SoftResponse::None` once routed, other requests
+    /// resolve to the device response.
+    pub async fn request(&self, request: SoftRequest) -> Result<SoftResponse, Error> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send((request, reply)).map_err(|_| Error::RouterStopped)?;
+        match tokio::time::timeout(self.timeout, response).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => Err(Error::RouterStopped),
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+
+    /// Subscribe to the responses pushed by devices that do not answer a request.
+    pub fn updates(&self) -> broadcast::Receiver<SoftResponse> {
+        self.updates.subscribe()
+    }
+}
+
+impl Routing<SoftRequest, SoftResponse> for ChannelRouter {
+    fn get_connector(&mut self, id: ModbusId) -> Option<RouterConnector<SoftRequest, SoftResponse>> {
+        if self.groups.contains(id) {
+            return None;
+        }
+        if let std::collections::hash_map::Entry::Vacant(e) = self.senders.entry(id) {
+            let (sender, receiver) = channel();
+            e.insert(sender);
+
+            Some(RouterConnector {
+                sender: self.connector.clone(),
+                receiver,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn transmit_request(&mut self, request: SoftRequest) {
+        if let Some(sender) = self.senders.get_mut(&request.id()) {
+            log::debug!("ChannelRouter.transmit_request({:?}) to {:?}", request, request.id());
+            let _ = sender.send(request);
+        } else {
+            log::error!("ChannelRouter.transmit_request() no receiver for id {:?}", &request.id())
+        }
+    }
+
+    /// Responses are not serialized.
+    fn transmit_response(&mut self, _raw: Vec<u8>) {}
+
+    fn devices_count(&self) -> usize {
+        self.senders.len()
+    }
+
+    fn devices_ids(&self) -> Vec<ModbusId> {
+        self.senders.keys().cloned().collect()
+    }
+
+    /// Requests are not serialized.
+    fn try_receive_request(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn try_receive_response(&mut self) -> Option<SoftResponse> {
+        self.receiver.try_recv().ok()
+    }
+
+    fn groups(&mut self) -> &mut Groups {
+        &mut self.groups
+    }
+
+    async fn run(&mut self) {
+        log::info!("ChannelRouter Started, {} devices.", self.devices_count());
+        loop {
+            while let Ok((request, reply)) = self.requests.try_recv() {
+                self.handle_request(request, reply);
+            }
+
+            while let Some(response) = self.try_receive_response() {
+                self.handle_response(response);
+            }
+
+            sleep(Duration::from_nanos(10)).await;
+        }
+    }
+
+    /// Resolve the oldest pending request answered by `response`, or push it to updates.
+    fn handle_response(&mut self, response: SoftResponse) {
+        log::debug!("ChannelRouter.handle_response({:?}) ", response);
+        let Some(response) = self.groups.filter_response(response) else {
+            return;
+        };
+        // drop requests whose handle timed out
+        self.pending.retain(|(_, reply)| !reply.is_closed());
+        if let Some(i) = self.pending.iter().position(|(r, _)| Self::answers(r, &response)) {
+            let (_, reply) = self.pending.remove(i);
+            let _ = reply.send(Ok(response));
+        } else if !matches!(response, SoftResponse::None) {
+            // no subscriber is not an error
+            let _ = self.handle.updates.send(response);
+        }
+    }
+}
@@ -1,11 +1,12 @@
-use crate::batch::Batch;
-use crate::device_template;
+use tokio::sync::watch;
+use crate::batch::{Attempt, Batch, RetryPolicy};
 use crate::devices::joystick::encoder::JoystickEncoder;
 use crate::devices::joystick::requests::{JoystickRequest, JoystickResponse, JoystickStatus};
-use crate::modbus::ModbusId;
-use crate::soft_request::{SoftRequest, SoftResponse};
-use crate::traits::device::Device;
-use crate::traits::polling::{PollerConnector, PollerMessage};
+use crate::modbus::{ModbusException, ModbusId};
+use crate::soft_request::{DeviceEvent, DeviceType, SoftError, SoftRequest, SoftResponse};
+use crate::state::{CommHealth, DeviceState, LinkMonitor, StatePublisher};
+use crate::traits::device::{Device, DeviceConnectors};
+use crate::traits::polling::PollerConnector;
 use crate::traits::routing::RouterConnector;
 
 
@@ -15,11 +16,16 @@ pub enum JoystickType {
     JoystickWithThumb,
 }
 
-#[derive(Debug)]
+#[derive(Debug, DeviceConnectors)]
 pub struct Joystick {
     id: ModbusId,
+    slave_id: ModbusId,
     joystick_type: JoystickType,
     status: JoystickStatus,
+    state: StatePublisher<JoystickStatus>,
+    link: LinkMonitor,
+    error: Option<SoftError>,
+    retry_policy: RetryPolicy,
     router: Option<RouterConnector<SoftRequest, SoftResponse>>,
     poller: Option<PollerConnector<JoystickRequest, JoystickResponse>>,
 }
@@ -30,14 +36,20 @@ impl Joystick {
     pub fn new(id: ModbusId, joystick_type: JoystickType) -> Self {
         Joystick {
             id,
+            slave_id: id,
             joystick_type,
             status: JoystickStatus::None,
+            state: StatePublisher::new(JoystickStatus::None),
+            link: LinkMonitor::new(),
+            error: None,
+            retry_policy: RetryPolicy::default(),
             router: None,
             poller: None,
         }
     }
     
     fn update_status(&mut self, status: JoystickStatus) {
+        self.state.update(status);
         // TODO
         if status != self.status {
             println!("Joystick {:?}: {:?}", self.id, status);
@@ -45,10 +57,35 @@ impl Joystick {
         }
     }
 
+    /// Set the retry policy applied on failed requests.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Subscribe to the device state snapshots, should be called before `start()`.
+    pub fn subscribe(&self) -> watch::Receiver<DeviceState<JoystickStatus>> {
+        self.state.subscribe()
+    }
+
+    /// Report an error to the client, only if it differs from the last one reported.
+    fn report_error(&mut self, error: SoftError) {
+        self.state.set_health(CommHealth::Error(error));
+        if self.error != Some(error) {
+            self.error = Some(error);
+            self.send_external_response(SoftResponse::Error(self.id, DeviceType::Joystick, error));
+            if error == SoftError::Exception(ModbusException::ServerDeviceFailure) {
+                self.report_event(DeviceEvent::Fault(0));
+            }
+        }
+    }
+
     /// Starts the Vfd run loop in a new thread.
     pub fn start(mut self) {
         tokio::spawn(async move {
-            self.run().await;
+            if let Err(e) = self.run().await {
+                log::error!("Joystick {} stopped: {}", {let id: u8 = self.id.into(); id}, e);
+            }
         });
     }
 }
@@ -57,13 +94,16 @@ impl Device<SoftRequest, SoftResponse, JoystickRequest, JoystickResponse> for Jo
 {
     type Encoder = JoystickEncoder;
 
-    device_template!(JoystickRequest, JoystickResponse);
+
+    fn set_slave_id(&mut self, slave_id: ModbusId) {
+        self.slave_id = slave_id;
+    }
 
     fn send_batch(&mut self) {
         if self.is_device_connected() {
             let mut batch: Batch<JoystickRequest, JoystickResponse> =
-                Batch::new(self.id(), Box::new(JoystickEncoder::new(self.joystick_type)));
-            batch.push(JoystickRequest::Status(self.id(), self.joystick_type));
+                Batch::new(self.slave_id, Box::new(JoystickEncoder::new(self.joystick_type, self.retry_policy)));
+            batch.push(JoystickRequest::Status(self.slave_id, self.joystick_type));
             
             if self.poller.as_mut().unwrap().sender.send(batch).is_err() {
                 log::debug!("Joystick: cannot send batch");
@@ -77,8 +117,30 @@ impl Device<SoftRequest, SoftResponse, JoystickRequest, JoystickResponse> for Jo
 
     fn handle_device_response(&mut self, response: JoystickResponse) {
         match response {
-            JoystickResponse::Status(status) => {self.update_status(status)}
-            JoystickResponse::Fail(_) => {/* TODO: handle lost request counting */}
+            JoystickResponse::Status(status) => {
+                if let Some(event) = self.link.success() { self.report_event(event) }
+                self.error = None;
+                self.update_status(status)
+            }
+            JoystickResponse::Fail(_) => {
+                if let Some(event) = self.link.failure() { self.report_event(event) }
+                self.report_error(SoftError::CommFailure)
+            }
+            JoystickResponse::Exception(_, exception) => {
+                if let Some(event) = self.link.answered() { self.report_event(event) }
+                self.report_error(SoftError::Exception(exception))
+            }
         }
     }
+
+    fn handle_retry(&mut self, attempt: Attempt<JoystickRequest>) {
+        // status is requested again on next poll
+        log::debug!("Joystick.handle_retry({:?})", attempt);
+        if let Some(event) = self.link.retry() { self.report_event(event) }
+    }
+
+    fn report_event(&mut self, event: DeviceEvent) {
+        log::info!("Joystick {} event: {:?}", {let id: u8 = self.id.into(); id}, event);
+        self.send_external_response(SoftResponse::Event(self.id, DeviceType::Joystick, event));
+    }
 }
\ No newline at end of file
@@ -2,20 +2,23 @@ use modbus_core::codec::Encode;
 use modbus_core::{Request, Response};
 use modbus_core::rtu::crc16;
 use serial_thread::SerialMessage;
+use crate::batch::RetryPolicy;
 use crate::devices::joystick::device::JoystickType;
 use crate::devices::joystick::requests::{JoystickRequest, JoystickResponse, JoystickStatus};
-use crate::modbus::ModbusId;
+use crate::modbus::{ModbusException, ModbusId};
 use crate::traits::device_encoder::DeviceEncoder;
 
 #[derive(Debug, Clone, Copy)]
 pub struct JoystickEncoder {
     joystick_type: JoystickType,
+    retry_policy: RetryPolicy,
 }
 
 impl JoystickEncoder {
-    pub fn new(joystick_type: JoystickType) -> Self {
+    pub fn new(joystick_type: JoystickType, retry_policy: RetryPolicy) -> Self {
         JoystickEncoder {
             joystick_type,
+            retry_policy,
         }
     }
     
@@ -61,8 +64,8 @@ impl DeviceEncoder<JoystickRequest, JoystickResponse> for JoystickEncoder {
 
         let mut frame: Vec<u8> = vec![(*id).into()];
         let bytes = &mut [0; 6];
-        request.encode(bytes).expect("fixed frame size");
-        frame.append(&mut bytes.to_vec());
+        let len = request.encode(bytes).expect("fixed frame size");
+        frame.extend_from_slice(&bytes[..len]);
         let crc = crc16(&frame.to_vec());
         frame.append(&mut vec![((crc & 0xff00) >> 8) as u8, (crc & 0x00ff) as u8]);
 
@@ -72,7 +75,11 @@ impl DeviceEncoder<JoystickRequest, JoystickResponse> for JoystickEncoder {
     fn serial_to_response(&self, msg: SerialMessage, request: JoystickRequest, id: ModbusId) -> JoystickResponse {
         match msg.clone() {
             SerialMessage::Receive(data) => {
-                if data[0] == id.into() {
+                if data[0] == {let i: u8 = id.into(); i} {
+                    if let Some(exception) = ModbusException::from_frame(&data) {
+                        log::error!("JoystickEncoder.serial_to_response() {:?} answered with {:?}!", request, exception);
+                        return JoystickResponse::Exception(request, exception);
+                    }
                     if let Some(response) = self.decode_response(data) {
                         return JoystickResponse::Status(response);
                     }
@@ -87,4 +94,20 @@ impl DeviceEncoder<JoystickRequest, JoystickResponse> for JoystickEncoder {
             }
         }
     }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    fn is_failure(&self, response: &JoystickResponse) -> bool {
+        matches!(response, JoystickResponse::Fail(_) | JoystickResponse::Exception(_, _))
+    }
+
+    fn should_retry(&self, response: &JoystickResponse) -> bool {
+        match response {
+            JoystickResponse::Fail(_) => true,
+            JoystickResponse::Exception(_, exception) => exception.is_retryable(),
+            JoystickResponse::Status(_) => false,
+        }
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use crate::devices::joystick::device::JoystickType;
-use crate::modbus::ModbusId;
+use crate::modbus::{ModbusException, ModbusId};
 
 #[allow(unused)]
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
@@ -12,6 +12,7 @@ pub enum JoystickRequest {
 #[derive(Debug, Clone, Copy)]
 pub enum JoystickResponse {
     Fail(JoystickRequest),
+    Exception(JoystickRequest, ModbusException),
     Status(JoystickStatus),
 }
 
@@ -1,3 +1,3 @@
 pub mod vfd;
 pub mod joystick;
-mod template;
+pub mod register_map;
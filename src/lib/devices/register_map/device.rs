@@ -0,0 +1,250 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use crate::batch::{Attempt, Batch, RetryPolicy};
+use crate::devices::register_map::encoder::RegisterEncoder;
+use crate::devices::register_map::map::RegisterMap;
+use crate::devices::register_map::requests::{RegisterRequest, RegisterResponse};
+use crate::modbus::{ModbusException, ModbusId};
+use crate::soft_request::{DeviceEvent, DeviceType, RejectReason, SoftError, SoftRequest, SoftResponse};
+use crate::state::{CommHealth, DeviceState, LinkMonitor, StatePublisher};
+use crate::traits::device::{Device, DeviceConnectors};
+use crate::traits::polling::PollerConnector;
+use crate::traits::routing::RouterConnector;
+
+/// A generic Modbus device, described by a `RegisterMap`.
+///
+/// Points are read periodically (or on every poll if they have no read period), and can be
+/// read and written by the client through the soft protocol by their index in the map.
+#[derive(Debug, DeviceConnectors)]
+pub struct RegisterDevice {
+    id: ModbusId,
+    slave_id: ModbusId,
+    map: Arc<RegisterMap>,
+    values: Vec<Option<u32>>,
+    state: StatePublisher<Vec<Option<i32>>>,
+    last_read: Vec<Option<Instant>>,
+    reads: Vec<Option<Attempt<RegisterRequest>>>,
+    writes: Vec<Option<Attempt<RegisterRequest>>>,
+    awaiting: Vec<bool>,
+    link: LinkMonitor,
+    error: Option<SoftError>,
+    retry_policy: RetryPolicy,
+    router: Option<RouterConnector<SoftRequest, SoftResponse>>,
+    poller: Option<PollerConnector<RegisterRequest, RegisterResponse>>,
+}
+
+impl RegisterDevice {
+    pub fn new(id: ModbusId, map: RegisterMap) -> Self {
+        let len = map.points.len();
+        RegisterDevice {
+            id,
+            slave_id: id,
+            map: Arc::new(map),
+            values: vec![None; len],
+            state: StatePublisher::new(vec![None; len]),
+            last_read: vec![None; len],
+            reads: vec![None; len],
+            writes: vec![None; len],
+            awaiting: vec![false; len],
+            link: LinkMonitor::new(),
+            error: None,
+            retry_policy: RetryPolicy::default(),
+            router: None,
+            poller: None,
+        }
+    }
+
+    /// Set the retry policy applied on failed requests.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Starts the device run loop in a new thread.
+    pub fn start(mut self) {
+        tokio::spawn(async move {
+            if let Err(e) = self.run().await {
+                log::error!("RegisterDevice {} stopped: {}", {let id: u8 = self.id.into(); id}, e);
+            }
+        });
+    }
+
+    /// Subscribe to the device state snapshots, should be called before `start()`.
+    pub fn subscribe(&self) -> watch::Receiver<DeviceState<Vec<Option<i32>>>> {
+        self.state.subscribe()
+    }
+
+    /// Report an error to the client, only if it differs from the last one reported.
+    fn report_error(&mut self, error: SoftError) {
+        self.state.set_health(CommHealth::Error(error));
+        if self.error != Some(error) {
+            self.error = Some(error);
+            self.send_external_response(SoftResponse::Error(self.id, DeviceType::RegisterMap, error));
+            if error == SoftError::Exception(ModbusException::ServerDeviceFailure) {
+                self.report_event(DeviceEvent::Fault(0));
+            }
+        }
+    }
+
+    fn reject(&mut self, reason: RejectReason) {
+        self.send_external_response(
+            SoftResponse::Error(self.id, DeviceType::RegisterMap, SoftError::Rejected(reason)));
+    }
+
+    /// Publish the (scaled) values of the points.
+    fn update_state(&self) {
+        let values = self.values.iter()
+            .zip(self.map.points.iter())
+            .map(|(v, p)| v.map(|raw| p.to_soft(raw)))
+            .collect();
+        self.state.update(values);
+    }
+
+    fn send_value(&mut self, index: u8, raw: u32) {
+        if let Some(point) = self.map.point(index) {
+            let value = point.to_soft(raw);
+            self.send_external_response(SoftResponse::Point(self.id, index, value));
+        }
+    }
+
+    /// Return true if the periodic read of the point at `index` is due.
+    fn read_due(&self, index: usize, now: Instant) -> bool {
+        match (self.last_read[index], self.map.points[index].period()) {
+            (Some(last), Some(period)) => now.duration_since(last) >= period,
+            _ => true,
+        }
+    }
+}
+
+impl Device<SoftRequest, SoftResponse, RegisterRequest, RegisterResponse> for RegisterDevice
+{
+    type Encoder = RegisterEncoder;
+
+
+    fn set_slave_id(&mut self, slave_id: ModbusId) {
+        self.slave_id = slave_id;
+    }
+
+    fn send_batch(&mut self) {
+        if self.is_device_connected() {
+            let encoder = RegisterEncoder::new(self.map.clone(), self.retry_policy);
+            let mut batch = Batch::new(self.slave_id, Box::new(encoder));
+            // writes first, then reads
+            for write in self.writes.iter_mut() {
+                if let Some(attempt) = write.take_if(|a| a.is_due()) {
+                    batch.push_retry(attempt);
+                }
+            }
+            let now = Instant::now();
+            for index in 0..self.map.points.len() {
+                if let Some(attempt) = self.reads[index].take_if(|a| a.is_due()) {
+                    batch.push_retry(attempt);
+                    self.last_read[index] = Some(now);
+                } else if self.reads[index].is_none() && self.read_due(index, now) {
+                    batch.push(RegisterRequest::Read(self.slave_id, index as u8));
+                    self.last_read[index] = Some(now);
+                }
+            }
+
+            if self.poller.as_mut().unwrap().sender.send(batch).is_err() {
+                log::error!("RegisterDevice: cannot send batch");
+            }
+        }
+    }
+
+    fn handle_external_request(&mut self, request: SoftRequest) {
+        log::debug!("RegisterDevice.handle_external_request({:?}) ", request);
+        match request {
+            SoftRequest::ReadPoint(_, index) => {
+                if self.map.point(index).is_none() {
+                    self.reject(RejectReason::UnknownPoint);
+                } else if let Some(raw) = self.values[index as usize] {
+                    self.send_value(index, raw);
+                } else {
+                    // answered once the point have been read
+                    self.awaiting[index as usize] = true;
+                }
+            }
+            SoftRequest::WritePoint(_, index, value) => {
+                let raw = match self.map.point(index) {
+                    None => {
+                        self.reject(RejectReason::UnknownPoint);
+                        return;
+                    }
+                    Some(point) if !point.writable => {
+                        self.reject(RejectReason::NotWritable);
+                        return;
+                    }
+                    Some(point) => point.from_soft(value),
+                };
+                if let Some(raw) = raw {
+                    // latest write wins
+                    self.writes[index as usize] = Some(Attempt::new(RegisterRequest::Write(self.slave_id, index, raw)));
+                } else {
+                    self.reject(RejectReason::OutOfRange);
+                }
+            }
+            _ => {
+                log::error!("RegisterDevice.handle_external_request() unsupported request {:?}", request);
+            }
+        }
+    }
+
+    fn handle_device_response(&mut self, response: RegisterResponse) {
+        log::debug!("RegisterDevice.handle_device_response({:?})", response);
+        let event = match response {
+            RegisterResponse::Fail(_) => self.link.failure(),
+            RegisterResponse::Exception(_, _) => self.link.answered(),
+            _ => self.link.success(),
+        };
+        if let Some(event) = event { self.report_event(event) }
+        match response {
+            RegisterResponse::Value(index, raw) => {
+                self.error = None;
+                self.values[index as usize] = Some(raw);
+                self.update_state();
+                if self.awaiting[index as usize] {
+                    self.awaiting[index as usize] = false;
+                    self.send_value(index, raw);
+                }
+            }
+            RegisterResponse::OK(RegisterRequest::Write(_, index, raw)) => {
+                self.error = None;
+                self.values[index as usize] = Some(raw);
+                self.update_state();
+                self.send_value(index, raw);
+            }
+            RegisterResponse::OK(_) => {
+                self.error = None;
+                self.state.set_health(CommHealth::Ok);
+            }
+            RegisterResponse::Fail(_) => {
+                self.report_error(SoftError::CommFailure);
+            }
+            RegisterResponse::Exception(_, exception) => {
+                self.report_error(SoftError::Exception(exception));
+            }
+        }
+    }
+
+    fn handle_retry(&mut self, attempt: Attempt<RegisterRequest>) {
+        if let Some(event) = self.link.retry() { self.report_event(event) }
+        match attempt.request {
+            RegisterRequest::Read(_, index) => {
+                self.reads[index as usize] = Some(attempt);
+            }
+            RegisterRequest::Write(_, index, _) => {
+                // dropped if a newer write is pending
+                if self.writes[index as usize].is_none() {
+                    self.writes[index as usize] = Some(attempt);
+                }
+            }
+        }
+    }
+
+    fn report_event(&mut self, event: DeviceEvent) {
+        log::info!("RegisterDevice {} event: {:?}", {let id: u8 = self.id.into(); id}, event);
+        self.send_external_response(SoftResponse::Event(self.id, DeviceType::RegisterMap, event));
+    }
+}
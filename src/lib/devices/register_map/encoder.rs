@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use modbus_core::{Data, Request, Response};
+use serial_thread::SerialMessage;
+use crate::batch::RetryPolicy;
+use crate::devices::register_map::map::{RegisterKind, RegisterMap};
+use crate::devices::register_map::requests::{RegisterRequest, RegisterResponse};
+use crate::modbus::{rtu_frame, ModbusException, ModbusId};
+use crate::traits::device_encoder::DeviceEncoder;
+
+#[derive(Debug, Clone)]
+pub struct RegisterEncoder {
+    map: Arc<RegisterMap>,
+    retry_policy: RetryPolicy,
+}
+
+impl RegisterEncoder {
+    pub fn new(map: Arc<RegisterMap>, retry_policy: RetryPolicy) -> Self {
+        RegisterEncoder {
+            map,
+            retry_policy,
+        }
+    }
+
+    /// Decodes a raw Modbus response message into a `RegisterResponse`, return `None` if the
+    /// response does not match the request.
+    fn decode_response(&self, msg: Vec<u8>, request: RegisterRequest) -> Option<RegisterResponse> {
+        // modbus id is dropped
+        let raw_response = &msg[1..];
+        log::debug!("RegisterEncoder.decode_response({:?}) ", raw_response);
+        let response = modbus_core::Response::try_from(raw_response).ok()?;
+        match (request, response) {
+            (RegisterRequest::Read(_, index), Response::ReadHoldingRegisters(data))
+            | (RegisterRequest::Read(_, index), Response::ReadInputRegisters(data)) => {
+                let point = self.map.point(index)?;
+                if data.len() == point.data_type.words() as usize {
+                    let raw = data.into_iter().fold(0u32, |raw, word| (raw << 16) | word as u32);
+                    Some(RegisterResponse::Value(index, raw))
+                } else {
+                    None
+                }
+            }
+            (RegisterRequest::Write(_, index, raw), Response::WriteSingleRegister(addr, value)) => {
+                let point = self.map.point(index)?;
+                (addr == point.address && value == raw as u16).then_some(RegisterResponse::OK(request))
+            }
+            (RegisterRequest::Write(_, index, _), Response::WriteMultipleRegisters(addr, quantity)) => {
+                let point = self.map.point(index)?;
+                (addr == point.address && quantity == 2).then_some(RegisterResponse::OK(request))
+            }
+            (a, b) => {
+                log::debug!("RegisterEncoder.decode_response() unrecognized pattern! {:?} / {:?}", a, b);
+                None
+            }
+        }
+    }
+}
+
+impl DeviceEncoder<RegisterRequest, RegisterResponse> for RegisterEncoder {
+    fn request_to_serial(&self, request: RegisterRequest) -> Option<SerialMessage> {
+        let mut data_buffer = [0u8; 4];
+        let (id, request) = match request {
+            RegisterRequest::Read(id, index) => {
+                let point = self.map.point(index)?;
+                let words = point.data_type.words();
+                match point.kind {
+                    RegisterKind::Holding => (id, Request::ReadHoldingRegisters(point.address, words)),
+                    RegisterKind::Input => (id, Request::ReadInputRegisters(point.address, words)),
+                }
+            }
+            RegisterRequest::Write(id, index, raw) => {
+                let point = self.map.point(index)?;
+                if point.data_type.words() == 1 {
+                    (id, Request::WriteSingleRegister(point.address, raw as u16))
+                } else {
+                    let words = [(raw >> 16) as u16, raw as u16];
+                    let data = Data::from_words(&words, &mut data_buffer).expect("2 words fit in buffer");
+                    (id, Request::WriteMultipleRegisters(point.address, data))
+                }
+            }
+        };
+        Some(SerialMessage::Send(rtu_frame(id, request)))
+    }
+
+    fn serial_to_response(&self, msg: SerialMessage, request: RegisterRequest, id: ModbusId) -> RegisterResponse {
+        log::debug!("RegisterEncoder.serial_to_response({:?})", msg);
+        match msg.clone() {
+            SerialMessage::Receive(data) => {
+                if data[0] != {let i: u8 = id.into(); i} {
+                    log::error!("RegisterEncoder.serial_to_response() id not match! ({} vs {})", &data[0], {let i: u8 = id.into(); i});
+                    RegisterResponse::Fail(request)
+                } else if let Some(exception) = ModbusException::from_frame(&data) {
+                    log::error!("RegisterEncoder.serial_to_response() {:?} answered with {:?}!", request, exception);
+                    RegisterResponse::Exception(request, exception)
+                } else if let Some(response) = self.decode_response(data, request) {
+                    response
+                } else {
+                    log::error!("RegisterEncoder.serial_to_response({:?}) fail decoding response!", msg);
+                    RegisterResponse::Fail(request)
+                }
+            }
+            SerialMessage::NoResponse => {
+                log::error!("RegisterEncoder.serial_to_response() no response to {:?}!", request);
+                RegisterResponse::Fail(request)
+            }
+            _ => { panic!("We should have drop this message in DeviceEncoder::filter_response()")}
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    fn is_failure(&self, response: &RegisterResponse) -> bool {
+        matches!(response, RegisterResponse::Fail(_) | RegisterResponse::Exception(_, _))
+    }
+
+    fn should_retry(&self, response: &RegisterResponse) -> bool {
+        match response {
+            RegisterResponse::Fail(_) => true,
+            RegisterResponse::Exception(_, exception) => exception.is_retryable(),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::time::Duration;
+use serde::Deserialize;
+use crate::error::Error;
+
+/// Maximum number of points of a register map, the point index is encoded on 7 bits in
+/// soft protocol frames.
+pub const MAX_POINTS: usize = 128;
+
+/// Maximum absolute value of a point in soft protocol frames (sign + 16 bits magnitude).
+pub const MAX_SOFT_VALUE: i32 = u16::MAX as i32;
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Modbus register table a point is read from.
+///
+/// ## Variants
+/// - `Holding`: Holding register, read with function 3, can be written.
+/// - `Input`: Input register, read with function 4, read only.
+pub enum RegisterKind {
+    #[default]
+    Holding,
+    Input,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Data type of a point, 32 bits types are stored in 2 registers, most significant word first.
+pub enum DataType {
+    #[default]
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl DataType {
+    /// Number of registers used by this type.
+    pub fn words(&self) -> u16 {
+        match self {
+            DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+        }
+    }
+
+    /// Decode raw registers value.
+    fn decode(&self, raw: u32) -> f64 {
+        match self {
+            DataType::U16 => (raw as u16) as f64,
+            DataType::I16 => (raw as u16 as i16) as f64,
+            DataType::U32 => raw as f64,
+            DataType::I32 => (raw as i32) as f64,
+            DataType::F32 => f32::from_bits(raw) as f64,
+        }
+    }
+
+    /// Encode a value into raw registers value, return `None` if out of range.
+    fn encode(&self, value: f64) -> Option<u32> {
+        let int = value.round();
+        match self {
+            DataType::U16 => (0.0..=u16::MAX as f64).contains(&int).then_some(int as u32),
+            DataType::I16 => (i16::MIN as f64..=i16::MAX as f64).contains(&int).then_some(int as i16 as u16 as u32),
+            DataType::U32 => (0.0..=u32::MAX as f64).contains(&int).then_some(int as u32),
+            DataType::I32 => (i32::MIN as f64..=i32::MAX as f64).contains(&int).then_some(int as i32 as u32),
+            DataType::F32 => Some((value as f32).to_bits()),
+        }
+    }
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// A named value of a device, stored in one or two registers.
+///
+/// The value exchanged through the soft protocol is `raw * scale + offset`, rounded.
+///
+/// Fields:
+/// - `name`: Name of the point, must be unique in the map.
+/// - `address`: Address of the (first) register.
+/// - `kind`: Register table, default to `holding`.
+/// - `data_type`: Data type of the register(s), default to `u16`.
+/// - `scale`: Scale factor applied to the raw value, default to `1.0`.
+/// - `offset`: Offset added to the scaled value, default to `0.0`.
+/// - `period_ms`: Optional read period, the point is read on every poll if not set.
+/// - `writable`: If true, the point can be written by the client, default to false.
+pub struct Point {
+    pub name: String,
+    pub address: u16,
+    #[serde(default)]
+    pub kind: RegisterKind,
+    #[serde(default)]
+    pub data_type: DataType,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default)]
+    pub period_ms: Option<u64>,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl Point {
+    pub fn period(&self) -> Option<Duration> {
+        self.period_ms.map(Duration::from_millis)
+    }
+
+    /// Convert a raw registers value into a soft protocol value, saturated to `MAX_SOFT_VALUE`.
+    pub fn to_soft(&self, raw: u32) -> i32 {
+        let value = (self.data_type.decode(raw) * self.scale + self.offset).round();
+        value.clamp(-MAX_SOFT_VALUE as f64, MAX_SOFT_VALUE as f64) as i32
+    }
+
+    /// Convert a soft protocol value into a raw registers value, return `None` if the value
+    /// is out of the data type range.
+    pub fn from_soft(&self, value: i32) -> Option<u32> {
+        self.data_type.encode((value as f64 - self.offset) / self.scale)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// Description of a generic Modbus device as a list of points.
+///
+/// Points are addressed in the soft protocol by their index in the list. A map can be
+/// loaded from a TOML description:
+///
+/// ```toml
+/// [[points]]
+/// name = "temperature"
+/// address = 0x0010
+/// kind = "input"
+/// data_type = "i16"
+/// scale = 0.1
+/// period_ms = 1000
+///
+/// [[points]]
+/// name = "setpoint"
+/// address = 0x0020
+/// writable = true
+/// ```
+pub struct RegisterMap {
+    pub points: Vec<Point>,
+}
+
+impl RegisterMap {
+    pub fn new(points: Vec<Point>) -> Result<Self, Error> {
+        let map = RegisterMap { points };
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Parse and validate a register map from a TOML description.
+    pub fn from_toml(description: &str) -> Result<Self, Error> {
+        let map: RegisterMap = toml::from_str(description)
+            .map_err(|e| Error::InvalidRegisterMap(e.to_string()))?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.points.is_empty() || self.points.len() > MAX_POINTS {
+            return Err(Error::InvalidRegisterMap(
+                format!("map should contain 1 to {} points", MAX_POINTS)));
+        }
+        let mut names = HashSet::new();
+        for point in &self.points {
+            if !names.insert(point.name.as_str()) {
+                return Err(Error::InvalidRegisterMap(format!("duplicate point name {}", point.name)));
+            }
+            if point.writable && point.kind == RegisterKind::Input {
+                return Err(Error::InvalidRegisterMap(format!("input register {} cannot be writable", point.name)));
+            }
+            if point.scale == 0.0 || !point.scale.is_finite() || !point.offset.is_finite() {
+                return Err(Error::InvalidRegisterMap(format!("invalid scaling for {}", point.name)));
+            }
+            if point.period_ms == Some(0) {
+                return Err(Error::InvalidRegisterMap(format!("invalid read period for {}", point.name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the index of the point named `name`, the index the client addresses the point
+    /// with (see `SoftRequest::ReadPoint`).
+    pub fn index_of(&self, name: &str) -> Option<u8> {
+        self.points.iter().position(|p| p.name == name).map(|i| i as u8)
+    }
+
+    /// Return the point at `index`, `None` if the map has no such point.
+    pub fn point(&self, index: u8) -> Option<&Point> {
+        self.points.get(index as usize)
+    }
+}
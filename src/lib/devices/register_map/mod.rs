@@ -0,0 +1,4 @@
+pub mod device;
+pub mod encoder;
+pub mod map;
+pub mod requests;
@@ -0,0 +1,29 @@
+use crate::modbus::{ModbusException, ModbusId};
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+/// Represents a request to be sent to the register map device Poller.
+///
+/// ## Variants
+/// - `Read(u8)`: Read the point at this index.
+/// - `Write(u8, u32)`: Write a raw value to the point at this index.
+pub enum RegisterRequest {
+    Read(ModbusId, u8),
+    Write(ModbusId, u8, u32),
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy)]
+/// Represents a response from the register map device Poller.
+///
+/// ## Variants
+/// - `OK(RegisterRequest)`: Successful acknowledgment of a write request.
+/// - `Fail(RegisterRequest)`: Indicates a failure in processing a `RegisterRequest`.
+/// - `Exception(RegisterRequest, ModbusException)`: The device answered with a Modbus exception.
+/// - `Value(u8, u32)`: Raw value read from the point at this index.
+pub enum RegisterResponse {
+    OK(RegisterRequest),
+    Fail(RegisterRequest),
+    Exception(RegisterRequest, ModbusException),
+    Value(u8, u32),
+}
@@ -1,94 +1,280 @@
-use crate::batch::Batch;
-use crate::device_template;
+use std::ops::RangeInclusive;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use crate::batch::{Attempt, Batch, RetryMode, RetryPolicy, SyncGroup};
 use crate::devices::vfd::encoder::{VfdCommands, VfdEncoder};
-use crate::devices::vfd::requests::{Dir, VfdRequest, VfdResponse, VfdStatus};
-use crate::modbus::ModbusId;
-use crate::soft_request::{SoftRequest, SoftResponse};
-use crate::traits::device::Device;
-use crate::traits::polling::{PollerConnector, PollerMessage};
+use crate::devices::vfd::encoding::Unit;
+use crate::devices::vfd::limits::VfdLimits;
+use crate::devices::vfd::nameplate::Nameplate;
+use crate::devices::vfd::ramp::{Ramp, RampConfig};
+use crate::devices::vfd::reconcile::{Reconcile, ReconcileConfig, Reconciler};
+use crate::devices::vfd::requests::{Dir, VfdRequest, VfdResponse, VfdState, VfdStatus, VfdTelemetry, MAX_PARAMETER_BLOCK};
+use crate::error::Error;
+use crate::modbus::{FunctionType, ModbusException, ModbusId};
+use crate::soft_request::{DeviceEvent, DeviceType, RejectReason, SoftError, SoftRequest, SoftResponse};
+use crate::state::{CommHealth, DeviceState, LinkMonitor, StatePublisher};
+use crate::traits::device::{Device, DeviceConnectors};
+use crate::traits::polling::PollerConnector;
 use crate::traits::routing::RouterConnector;
 
+/// Default retry policy of a `Vfd`: failed requests are sent again in the next 2 batches.
+pub const DEFAULT_RETRY_POLICY: RetryPolicy = RetryPolicy::new(3, RetryMode::NextCycle, None);
+
 #[derive(Debug, Clone)]
+/// Pending requests of a `Vfd`, with latest-wins semantics: a new setpoint replaces the
+/// pending one, and a value already queued (or written) is not queued again.
+///
+/// A pending stop is never replaced by a run command, the run command is queued and sent
+/// in the batch following the stop.
+///
+/// A fault reset is refused while a run command is queued or written, and it is sent first in
+/// its batch, once the pending stop (if any) have been sent.
+///
+/// Parameter requests are one-off entries, sent once in the next batch (and retried).
 pub struct VfdBatch {
-    cmd: Option<VfdRequest>,
-    reference: Option<VfdRequest>,
-    status: VfdRequest
+    cmd: Option<Attempt<VfdRequest>>,
+    reference: Option<Attempt<VfdRequest>>,
+    status: Attempt<VfdRequest>,
+    telemetry: Vec<Attempt<VfdRequest>>,
+    reset: Option<Attempt<VfdRequest>>,
+    parameters: Vec<Attempt<VfdRequest>>,
+    queued_run: Option<(VfdRequest, u16)>,
+    last_cmd: Option<VfdRequest>,
+    last_ref: Option<u16>,
 }
 
 impl VfdBatch {
-    fn new(id: ModbusId) -> Self {
+    fn new(slave_id: ModbusId, commands: &VfdCommands) -> Self {
         VfdBatch {
             cmd: None,
             reference: None,
-            status: VfdRequest::Status(id),
+            status: Attempt::new(VfdRequest::Status(slave_id)),
+            // the first block is read with the status
+            telemetry: commands.read_blocks()
+                .into_iter()
+                .skip(1)
+                .map(|(start, quantity)| Attempt::new(VfdRequest::Telemetry(slave_id, start, quantity)))
+                .collect(),
+            reset: None,
+            parameters: vec![],
+            queued_run: None,
+            last_cmd: None,
+            last_ref: None,
         }
     }
     
-    fn take(&mut self) -> Self {
-        let out = self.clone();
-        self.cmd = None;
-        self.reference = None;
+    /// Take the (cmd, reference, status) requests to send in the next batch, requests waiting
+    /// for a retry backoff are kept for a later batch.
+    #[allow(clippy::type_complexity)]
+    fn take(&mut self) -> (Option<Attempt<VfdRequest>>, Option<Attempt<VfdRequest>>, Attempt<VfdRequest>) {
+        let out = (
+            self.cmd.take_if(|a| a.is_due()),
+            self.reference.take_if(|a| a.is_due()),
+            self.status,
+        );
+        if self.status.is_due() {
+            self.status = Attempt::new(self.status.request);
+        }
+        // the stop have been sent, queued run command can be sent on next batch
+        if self.cmd.is_none() {
+            if let Some((cmd, reference)) = self.queued_run.take() {
+                self.set_cmd(cmd);
+                self.set_reference(reference);
+            }
+        }
         out
     }
+
+    /// Take the telemetry requests to send in the next batch, requests waiting for a retry
+    /// backoff are kept for a later batch.
+    fn take_telemetry(&mut self) -> Vec<Attempt<VfdRequest>> {
+        self.telemetry.iter_mut()
+            .filter(|a| a.is_due())
+            .map(|a| std::mem::replace(a, Attempt::new(a.request)))
+            .collect()
+    }
+
+    /// Take the fault reset to send first in the next batch, kept for a later batch while a stop
+    /// is pending or a retry backoff is not elapsed.
+    fn take_reset(&mut self) -> Option<Attempt<VfdRequest>> {
+        let pending_stop = matches!(self.cmd.map(|a| a.request), Some(VfdRequest::Stop(_)));
+        if pending_stop {
+            return None;
+        }
+        self.reset.take_if(|a| a.is_due())
+    }
+
+    /// Take the parameter requests to send in the next batch, requests waiting for a retry
+    /// backoff are kept for a later batch.
+    fn take_parameters(&mut self) -> Vec<Attempt<VfdRequest>> {
+        let (due, later) = self.parameters.drain(..).partition(|a| a.is_due());
+        self.parameters = later;
+        due
+    }
+
+    /// Return true if a command or a reference is waiting to be written.
+    fn is_writing(&self) -> bool {
+        self.cmd.is_some() || self.reference.is_some() || self.queued_run.is_some()
+    }
+
+    /// Queue a parameter request.
+    fn push_parameter(&mut self, request: VfdRequest) {
+        self.parameters.push(Attempt::new(request));
+    }
+
+    /// Queue a fault reset, rejected if a run command is queued or written (the drive must be
+    /// stopped first).
+    fn reset(&mut self) -> Result<(), RejectReason> {
+        let run = self.queued_run.is_some() || matches!(self.last_cmd, Some(VfdRequest::Cmd(_, _)));
+        if run {
+            return Err(RejectReason::RunPending);
+        }
+        if self.reset.is_none() {
+            self.reset = Some(Attempt::new(VfdRequest::Reset(self.status.request.id())));
+        }
+        Ok(())
+    }
+
+    /// Replace the pending command, unless the same command is already queued or written.
+    fn set_cmd(&mut self, cmd: VfdRequest) {
+        // stop is always sent
+        if self.last_cmd != Some(cmd) || matches!(cmd, VfdRequest::Stop(_)) {
+            self.cmd = Some(Attempt::new(cmd));
+            self.last_cmd = Some(cmd);
+        }
+    }
+
+    /// Replace the pending reference, unless the same reference is already queued or written.
+    fn set_reference(&mut self, reference: u16) {
+        if self.last_ref != Some(reference) {
+            self.reference = Some(Attempt::new(VfdRequest::Ref(self.status.request.id(), reference)));
+            self.last_ref = Some(reference);
+        }
+    }
     
-    fn handle_request(&mut self, request: SoftRequest, device_id: ModbusId) {
-        match request {
-            SoftRequest::Run(id, r) => {
-                let cmd = if r != 0 && id == device_id {
-                    if r > 0 {
-                        VfdRequest::Cmd(device_id, Dir::Fw)
-                    } else {
-                        VfdRequest::Cmd(device_id, Dir::Rv)
-                    }
-                } else {
-                    VfdRequest::Stop(device_id)
-                };
-                let ref_value = match &cmd {
-                    VfdRequest::Cmd(_, _) => { (r.abs() & i16::MAX) as u16 }
-                    VfdRequest::Stop(_) => { 0 }
-                    _ => { panic!("impossible")}
-                };
-                let reference = VfdRequest::Ref(device_id, ref_value);
-                
-                if self.cmd.is_none() && self.reference.is_none() {
-                    self.reference = Some(reference);
-                    self.cmd = Some(cmd);
-                }
+    /// Queue a command and its (raw) reference, a run command is queued after a pending stop.
+    fn set_setpoint(&mut self, cmd: VfdRequest, ref_value: u16) {
+        let pending_stop = matches!(self.cmd.map(|a| a.request), Some(VfdRequest::Stop(_)));
+        match cmd {
+            VfdRequest::Stop(_) => {
+                self.queued_run = None;
             }
-            SoftRequest::Stop(id) => {
-                if id == device_id  && (self.cmd.is_none() && self.reference.is_none()){
-                    self.cmd = Some(VfdRequest::Stop(device_id));
-                    self.reference = Some(VfdRequest::Ref(device_id, 0));
-                }
+            _ if pending_stop => {
+                self.queued_run = Some((cmd, ref_value));
+                return;
             }
-            _ => { panic!("this request should have been filtered out before!")}
+            _ => {}
         }
+        self.set_cmd(cmd);
+        self.set_reference(ref_value);
     }
     
-    fn retry_request(&mut self, request: VfdRequest) {
-        match request {
-            VfdRequest::Status(_) => {}
+    /// Merge a command and a reference request into a single `CmdRef` request.
+    fn merge(cmd: VfdRequest, reference: VfdRequest) -> Option<VfdRequest> {
+        match (cmd, reference) {
+            (VfdRequest::Cmd(id, dir), VfdRequest::Ref(_, r)) => Some(VfdRequest::CmdRef(id, Some(dir), r)),
+            (VfdRequest::Stop(id), VfdRequest::Ref(_, r)) => Some(VfdRequest::CmdRef(id, None, r)),
+            _ => None,
+        }
+    }
+
+    /// Push back a failed request, a command or reference is dropped if a newer one is pending.
+    fn retry_request(&mut self, attempt: Attempt<VfdRequest>) {
+        match attempt.request {
+            VfdRequest::CmdRef(id, dir, r) => {
+                if self.cmd.is_none() && self.reference.is_none() {
+                    let cmd = dir.map_or(VfdRequest::Stop(id), |d| VfdRequest::Cmd(id, d));
+                    self.cmd = Some(Attempt { request: cmd, ..attempt });
+                    self.reference = Some(Attempt { request: VfdRequest::Ref(id, r), ..attempt });
+                }
+            }
+            VfdRequest::Status(_) => { self.status = attempt }
+            VfdRequest::Telemetry(_, start, _) => {
+                if let Some(a) = self.telemetry.iter_mut()
+                    .find(|a| matches!(a.request, VfdRequest::Telemetry(_, s, _) if s == start)) {
+                    *a = attempt;
+                }
+            }
             VfdRequest::Cmd(_, _) |
             VfdRequest::Stop(_) => { 
                 if self.cmd.is_none() {
-                    self.cmd = Some(request)
+                    self.cmd = Some(attempt)
                 }
             }
             VfdRequest::Ref(_, _) => { 
                 if self.reference.is_none() {
-                    self.reference = Some(request)
+                    self.reference = Some(attempt)
+                }
+            }
+            VfdRequest::Reset(_) => {
+                if self.reset.is_none() {
+                    self.reset = Some(attempt)
                 }
             }
+            VfdRequest::ReadParameter(_, _, _) | VfdRequest::WriteParameter(_, _, _) => {
+                self.parameters.push(attempt)
+            }
+        }
+    }
+
+    /// Drop the run command and its reference waiting to be written, a pending stop is kept.
+    fn drop_run(&mut self) {
+        if matches!(self.cmd.map(|a| a.request), Some(VfdRequest::Cmd(_, _))) {
+            self.cmd = None;
+            self.reference = None;
+            self.invalidate_written();
+        }
+        self.queued_run = None;
+    }
+
+    /// Forget the command and reference written, the next setpoint will be written even if
+    /// unchanged.
+    fn invalidate_written(&mut self) {
+        self.last_cmd = None;
+        self.last_ref = None;
+    }
+
+    /// Forget the last value of a failed (or aborted) write, the drive state is unknown so
+    /// the next setpoint will be written even if unchanged.
+    fn forget(&mut self, request: VfdRequest) {
+        match request {
+            VfdRequest::Cmd(_, _) | VfdRequest::Stop(_) => { self.last_cmd = None }
+            VfdRequest::Ref(_, _) => { self.last_ref = None }
+            VfdRequest::CmdRef(_, _, _) => self.invalidate_written(),
+            VfdRequest::Status(_)
+            | VfdRequest::Telemetry(_, _, _)
+            | VfdRequest::Reset(_)
+            | VfdRequest::ReadParameter(_, _, _)
+            | VfdRequest::WriteParameter(_, _, _) => {}
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, DeviceConnectors)]
 pub struct Vfd {
     id: ModbusId,
+    slave_id: ModbusId,
     commands: VfdCommands,
     status: VfdStatus,
+    telemetry: VfdTelemetry,
+    state: StatePublisher<VfdState>,
+    link: LinkMonitor,
+    error: Option<SoftError>,
     batch: VfdBatch,
+    ramp: Option<Ramp>,
+    nameplate: Option<(Nameplate, Unit)>,
+    desired: Option<(VfdRequest, u16)>,
+    reconciler: Option<Reconciler>,
+    limits: VfdLimits,
+    interlocks: Vec<(ModbusId, watch::Receiver<DeviceState<VfdState>>)>,
+    last_dir: Option<Dir>,
+    stopped_since: Option<Instant>,
+    writable_parameters: Vec<RangeInclusive<u16>>,
+    parameter_address: Option<u16>,
+    parameter_writes: Vec<(u16, u8)>,
+    group: Option<SyncGroup>,
+    group_writes: usize,
+    retry_policy: RetryPolicy,
     router: Option<RouterConnector<SoftRequest, SoftResponse>>,
     poller: Option<PollerConnector<VfdRequest, VfdResponse>>,
     auto_update: bool,
@@ -98,12 +284,38 @@ pub struct Vfd {
 unsafe impl Send for Vfd{}
 
 impl Vfd {
+    /// `poll_status` is the initial status polling, see `SoftRequest::Polling` to change it at
+    /// runtime.
     pub fn new(id: ModbusId, commands: VfdCommands, poll_status: bool) -> Self {
         Vfd {
             id,
+            slave_id: id,
             commands,
             status: VfdStatus::None,
-            batch: VfdBatch::new(id),
+            telemetry: VfdTelemetry::default(),
+            state: StatePublisher::new(VfdState {
+                status: VfdStatus::None,
+                telemetry: VfdTelemetry::default(),
+                ramp: None,
+                not_following: false,
+            }),
+            link: LinkMonitor::new(),
+            error: None,
+            batch: VfdBatch::new(id, &commands),
+            ramp: None,
+            nameplate: None,
+            desired: None,
+            reconciler: None,
+            limits: VfdLimits::new(),
+            interlocks: vec![],
+            last_dir: None,
+            stopped_since: None,
+            writable_parameters: vec![],
+            parameter_address: None,
+            parameter_writes: vec![],
+            group: None,
+            group_writes: 0,
+            retry_policy: DEFAULT_RETRY_POLICY,
             router: None,
             poller: None,
             auto_update: false,
@@ -111,14 +323,344 @@ impl Vfd {
         }
     }
 
+    /// Push a status update to the client after each status read, default to `false` (see
+    /// `SoftRequest::Polling` to change it at runtime).
+    pub fn auto_update(mut self, auto_update: bool) -> Self {
+        self.auto_update = auto_update;
+        self
+    }
+
     /// Starts the Vfd run loop in a new thread.
     pub fn start(mut self) {
         log::debug!("Vfd.start()");
         tokio::spawn(async move {
-            self.run().await;
+            if let Err(e) = self.run().await {
+                log::error!("Vfd {} stopped: {}", {let id: u8 = self.id.into(); id}, e);
+            }
         });
         log::debug!("Vfd.start() started!");
     }
+
+    /// Set the retry policy applied on failed requests.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Exchange references and statuses with the client in `unit` (Hz and percent in
+    /// hundredths), converted with the motor `nameplate`. References above the maximum
+    /// frequency are rejected. The unit is set per device, a client cannot choose it in a
+    /// request.
+    ///
+    /// Without nameplate, the client values are drive values (see `ValueEncoding`).
+    pub fn nameplate(mut self, nameplate: Nameplate, unit: Unit) -> Result<Self, Error> {
+        nameplate.validate()?;
+        self.nameplate = Some((nameplate, unit));
+        Ok(self)
+    }
+
+    /// Compare the command and the reference written to the drive with its polled status, and
+    /// write them again while the drive does not follow (needs `poll_status`).
+    pub fn reconcile(mut self, config: ReconcileConfig) -> Self {
+        self.reconciler = Some(Reconciler::new(config));
+        self
+    }
+
+    /// Set the safety limits checked on run requests.
+    pub fn limits(mut self, limits: VfdLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Accept run requests only while the drive `id` is running, `state` is its state (see
+    /// `subscribe()`). The drive is stopped if `id` is not running anymore, reported as an
+    /// `Interlocked` rejection.
+    pub fn interlock(mut self, id: ModbusId, state: watch::Receiver<DeviceState<VfdState>>) -> Self {
+        self.interlocks.push((id, state));
+        self
+    }
+
+    /// Allow the client to write the parameter registers in `addresses` (see
+    /// `SoftRequest::WriteParameter`), no parameter is writable by default.
+    pub fn writable_parameters(mut self, addresses: impl IntoIterator<Item = RangeInclusive<u16>>) -> Self {
+        self.writable_parameters.extend(addresses);
+        self
+    }
+
+    /// Ramp the reference written to the drive, the client reference is reached over successive
+    /// poll cycles (limits are in client units). A run request to `0` ramps down under the
+    /// deceleration limit, then the stop is written; a stop request is written at once.
+    pub fn ramp(mut self, config: RampConfig) -> Self {
+        self.ramp = Some(Ramp::new(config));
+        self.state.set_status(self.vfd_state());
+        self
+    }
+
+    /// Subscribe to the device state snapshots, should be called before `start()`.
+    pub fn subscribe(&self) -> watch::Receiver<DeviceState<VfdState>> {
+        self.state.subscribe()
+    }
+
+    /// Report an error to the client, only if it differs from the last one reported.
+    fn report_error(&mut self, error: SoftError) {
+        self.state.set_health(CommHealth::Error(error));
+        if self.error != Some(error) {
+            self.error = Some(error);
+            self.send_external_response(SoftResponse::Error(self.id, DeviceType::Vfd, error));
+            if error == SoftError::Exception(ModbusException::ServerDeviceFailure) {
+                self.report_event(DeviceEvent::Fault(0));
+            }
+        }
+    }
+
+    /// Merge the telemetry just read and publish the state, a fault is reported when the
+    /// fault code changes to a non zero value.
+    fn update_telemetry(&mut self, telemetry: VfdTelemetry) {
+        let fault = self.telemetry.fault;
+        self.telemetry.merge(telemetry);
+        match self.telemetry.fault {
+            Some(code) if code != 0 && fault != Some(code) => self.report_event(DeviceEvent::Fault(code)),
+            _ => {}
+        }
+        self.state.update(self.vfd_state());
+    }
+
+    fn vfd_state(&self) -> VfdState {
+        VfdState {
+            status: self.status,
+            telemetry: self.telemetry,
+            ramp: self.ramp.map(|r| r.progress()),
+            not_following: self.reconciler.is_some_and(|r| r.alarm()),
+        }
+    }
+
+    /// Convert a client reference magnitude into a reference register value, return `None` if
+    /// out of range.
+    fn reference_register(&self, reference: u16) -> Option<u16> {
+        let value = match self.nameplate {
+            Some((nameplate, unit)) => nameplate.from_client(reference as f32, unit)?,
+            None => reference as f32,
+        };
+        self.commands.encoding.encode(value)
+    }
+
+    /// Convert a speed of the drive into a client value.
+    fn client_value(&self, speed: f32) -> i16 {
+        let value = match self.nameplate {
+            Some((nameplate, unit)) => nameplate.to_client(speed, unit),
+            None => speed,
+        };
+        value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    /// Convert a speed read from the drive into a client status.
+    fn client_status(&self, speed: f32) -> VfdStatus {
+        match self.client_value(speed) {
+            0 => VfdStatus::Stop,
+            value => VfdStatus::Run(value),
+        }
+    }
+
+    /// Queue a command and its reference register value, and keep them as the desired state.
+    fn set_setpoint(&mut self, cmd: VfdRequest, raw: u16) {
+        self.batch.set_setpoint(cmd, raw);
+        if self.desired != Some((cmd, raw)) {
+            self.desired = Some((cmd, raw));
+            if let Some(reconciler) = self.reconciler.as_mut() {
+                reconciler.desired_changed();
+            }
+        }
+    }
+
+    /// Compare the desired state with the status just read, write command and reference again
+    /// if the drive does not follow.
+    fn check_following(&mut self) {
+        let Some((cmd, raw)) = self.desired else {
+            return;
+        };
+        let magnitude = self.client_value(self.commands.encoding.decode(raw).abs());
+        let desired = match cmd {
+            VfdRequest::Cmd(_, Dir::Fw) => magnitude,
+            VfdRequest::Cmd(_, Dir::Rv) => -magnitude,
+            _ => 0,
+        };
+        let actual = match self.status {
+            VfdStatus::Run(value) => value,
+            _ => 0,
+        };
+        let Some(reconciler) = self.reconciler.as_mut() else {
+            return;
+        };
+        match reconciler.check(desired, actual, Instant::now()) {
+            Some(Reconcile::Rewrite) => {
+                log::warn!("Vfd {} does not follow {:?}, written again", {let id: u8 = self.id.into(); id}, cmd);
+                // the drive state is unknown, the setpoint is written even if unchanged
+                self.batch.invalidate_written();
+                self.batch.set_setpoint(cmd, raw);
+            }
+            Some(Reconcile::NotFollowing) => self.report_event(DeviceEvent::NotFollowing),
+            Some(Reconcile::Following) => self.report_event(DeviceEvent::Following),
+            None => {}
+        }
+    }
+
+    /// Queue a run command with the client reference `reference`, references are checked
+    /// (see `handle_external_request()`) before being queued.
+    fn run_at(&mut self, dir: Dir, reference: u16) {
+        if let Some(raw) = self.reference_register(reference) {
+            self.set_setpoint(VfdRequest::Cmd(self.slave_id, dir), raw);
+        }
+    }
+
+    /// Queue a stop command, the ramp (if any) is dropped.
+    fn stop(&mut self) {
+        // the dwell is counted from a stop read after the stop command
+        self.stopped_since = None;
+        let raw = self.commands.encoding.encode(0.0).unwrap_or(0);
+        self.set_setpoint(VfdRequest::Stop(self.slave_id), raw);
+        if let Some(ramp) = self.ramp.as_mut() {
+            ramp.reset();
+            self.state.set_status(self.vfd_state());
+        }
+    }
+
+    /// The fault reset failed, the drive is still faulted: the run requested after the reset is
+    /// dropped (a run written in the same batch is aborted by the poller).
+    fn reset_failed(&mut self) {
+        if !matches!(self.desired, Some((VfdRequest::Cmd(_, _), _))) {
+            return;
+        }
+        log::error!("Vfd {} reset failed, run dropped", {let id: u8 = self.id.into(); id});
+        self.batch.drop_run();
+        self.desired = None;
+        if let Some(ramp) = self.ramp.as_mut() {
+            ramp.reset();
+            self.state.set_status(self.vfd_state());
+        }
+    }
+
+    /// Send the error of a parameter request to the client, a parameter request is always
+    /// answered.
+    fn parameter_error(&mut self, request: VfdRequest, error: SoftError) {
+        log::error!("Vfd {} {:?} failed: {:?}", {let id: u8 = self.id.into(); id}, request, error);
+        if let VfdRequest::WriteParameter(_, address, _) = request {
+            self.take_parameter_write(address);
+        }
+        self.send_external_response(SoftResponse::Error(self.id, DeviceType::Vfd, error));
+    }
+
+    /// Return the client offset of the oldest pending write of the parameter `address`.
+    fn take_parameter_write(&mut self, address: u16) -> Option<u8> {
+        let i = self.parameter_writes.iter().position(|(a, _)| *a == address)?;
+        Some(self.parameter_writes.remove(i).1)
+    }
+
+    /// Check a run request against the limits, the reversal dwell and the interlocks.
+    fn check_run(&self, dir: Dir, reference: u16) -> Result<(), RejectReason> {
+        self.limits.check(dir, reference)?;
+        if self.reference_register(reference).is_none() {
+            return Err(RejectReason::OutOfRange);
+        }
+        let stopped_for = self.stopped_since.map(|since| since.elapsed());
+        self.limits.check_reversal(self.last_dir, dir, stopped_for)?;
+        match self.interlocked() {
+            Some(id) => Err(RejectReason::Interlocked(id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Return the first interlocking device that is not running, `None` if all are running.
+    fn interlocked(&self) -> Option<ModbusId> {
+        self.interlocks.iter()
+            .find(|(_, state)| {
+                let state = state.borrow();
+                !(matches!(state.status.status, VfdStatus::Run(_)) && state.health == CommHealth::Ok)
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// Stop the drive if it runs while an interlocking device is not running.
+    fn check_interlocks(&mut self) {
+        let running = matches!(self.desired, Some((VfdRequest::Cmd(_, _), _)));
+        if !running {
+            return;
+        }
+        if let Some(id) = self.interlocked() {
+            self.stop();
+            self.reject(RejectReason::Interlocked(id));
+        }
+    }
+
+    /// Check and queue a run request, a reference of `0` stops the drive (once ramped down to
+    /// zero if a ramp is set).
+    fn run_request(&mut self, r: i16) -> Result<(), RejectReason> {
+        let dir = if r > 0 { Dir::Fw } else { Dir::Rv };
+        if r == 0 {
+            match self.ramp.as_mut() {
+                Some(ramp) if ramp.progress().setpoint != 0 => {
+                    ramp.set_target(0, Instant::now());
+                    self.stopped_since = None;
+                    self.step_ramp();
+                }
+                _ => self.stop(),
+            }
+            return Ok(());
+        }
+        self.check_run(dir, r.unsigned_abs())?;
+        self.last_dir = Some(dir);
+        self.stopped_since = None;
+        if let Some(ramp) = self.ramp.as_mut() {
+            ramp.set_target(r, Instant::now());
+            self.step_ramp();
+        } else {
+            self.run_at(dir, r.unsigned_abs());
+        }
+        Ok(())
+    }
+
+    /// The setpoint of a group command have been queued, it is written synchronized with the
+    /// other members of `group` (reported at once if already written).
+    fn group_setpoint(&mut self, group: SyncGroup) {
+        self.group = Some(group);
+        self.group_writes = 0;
+        if !self.batch.is_writing() {
+            self.group_applied(true);
+        }
+    }
+
+    /// Report to the group of the last group command whether it is applied.
+    fn group_applied(&mut self, applied: bool) {
+        if let Some(group) = self.group.take() {
+            if !applied {
+                log::error!("Vfd {} group {:?} command not applied", {let id: u8 = self.id.into(); id}, group.id);
+            }
+            self.send_external_response(SoftResponse::GroupApplied(group.id, group.seq, self.id, applied));
+        }
+    }
+
+    /// Send a `Rejected` error to the client.
+    fn reject(&mut self, reason: RejectReason) {
+        log::error!("Vfd {} request rejected: {:?}", {let id: u8 = self.id.into(); id}, reason);
+        self.send_external_response(SoftResponse::Error(self.id, DeviceType::Vfd, SoftError::Rejected(reason)));
+    }
+
+    /// Advance the ramp, queue the setpoint reached and publish the ramp progress.
+    fn step_ramp(&mut self) {
+        let Some(ramp) = self.ramp.as_mut() else {
+            return;
+        };
+        let setpoint = ramp.advance(Instant::now());
+        let target = ramp.progress().target;
+        if target != 0 || setpoint != 0 {
+            // the direction changes at zero
+            let dir = if setpoint > 0 || (setpoint == 0 && target > 0) { Dir::Fw } else { Dir::Rv };
+            self.run_at(dir, setpoint.unsigned_abs());
+        } else if matches!(self.desired, Some((VfdRequest::Cmd(_, _), _))) {
+            // ramped down to zero, the drive is stopped
+            self.stop();
+        }
+        self.state.set_status(self.vfd_state());
+    }
 }
 
 impl Device<SoftRequest, SoftResponse, VfdRequest, VfdResponse> for Vfd
@@ -126,21 +668,80 @@ impl Device<SoftRequest, SoftResponse, VfdRequest, VfdResponse> for Vfd
 
     type Encoder = VfdEncoder;
 
-    device_template!(VfdRequest, VfdResponse);
+
+    fn set_slave_id(&mut self, slave_id: ModbusId) {
+        self.slave_id = slave_id;
+        self.batch = VfdBatch::new(slave_id, &self.commands);
+    }
 
     fn send_batch(&mut self) {
         log::debug!("Vfd.send_batch()");
         if self.is_device_connected() {
-            let vfd_batch = self.batch.take();
-            let mut batch = Batch::new(self.id, Box::new(VfdEncoder::new(self.commands)));
-            if let Some(req) = vfd_batch.cmd {
-                batch.push(req);
+            self.check_interlocks();
+            self.step_ramp();
+            let reset = self.batch.take_reset();
+            let (cmd, reference, status) = self.batch.take();
+            let mut batch = Batch::new(self.slave_id, Box::new(VfdEncoder::new(self.commands, self.retry_policy)));
+            let mut after_reset = reset.is_some();
+            if let Some(reset) = reset {
+                batch.push_retry(reset);
+            }
+            // the setpoint of a group command is written synchronized with the other members
+            let group = self.group;
+            let mut writes = 0;
+            let mut push = |batch: &mut Batch<VfdRequest, VfdResponse>, attempt: Attempt<VfdRequest>, dependent| {
+                writes += 1;
+                // a run setpoint is not written if the reset fails, a stop is always written
+                let stop = matches!(attempt.request, VfdRequest::Stop(_) | VfdRequest::CmdRef(_, None, _));
+                let dependent = dependent || (std::mem::take(&mut after_reset) && !stop);
+                match group {
+                    Some(group) => batch.sync(attempt, group, dependent),
+                    None if dependent => batch.then(attempt),
+                    None => batch.push_retry(attempt),
+                }
+            };
+            match (reference, cmd) {
+                // command and reference in a single frame
+                (Some(reference), Some(cmd)) if self.commands.can_write_cmd_ref() => {
+                    if let Some(request) = VfdBatch::merge(cmd.request, reference.request) {
+                        push(&mut batch, Attempt {
+                            request,
+                            attempts: cmd.attempts.max(reference.attempts),
+                            not_before: None,
+                        }, false);
+                    }
+                }
+                // stop is sent first and does not depend on the reference write
+                (reference, Some(stop)) if matches!(stop.request, VfdRequest::Stop(_)) => {
+                    push(&mut batch, stop, false);
+                    if let Some(reference) = reference {
+                        push(&mut batch, reference, false);
+                    }
+                }
+                // run command is not sent if the reference write fails
+                (reference, cmd) => {
+                    if let Some(reference) = reference {
+                        push(&mut batch, reference, false);
+                    }
+                    if let Some(cmd) = cmd {
+                        push(&mut batch, cmd, true);
+                    }
+                }
+            }
+            if writes > 0 {
+                self.group_writes = writes;
+            }
+            for parameter in self.batch.take_parameters() {
+                batch.push_retry(parameter);
             }
-            if let Some(req) = vfd_batch.reference {
-                batch.push(req);
+            // status is read back after writes
+            if self.poll_status && status.is_due() {
+                batch.push_retry(status);
             }
             if self.poll_status {
-                batch.push(vfd_batch.status);
+                for telemetry in self.batch.take_telemetry() {
+                    batch.push_retry(telemetry);
+                }
             }
             log::debug!("Vfd.send_batch() batch: {:?}", batch);
             if self.poller.as_mut().unwrap().sender.send(batch).is_err() {
@@ -148,7 +749,7 @@ impl Device<SoftRequest, SoftResponse, VfdRequest, VfdResponse> for Vfd
             }
             
         } else {
-            panic!("Router not connected!");
+            log::error!("Vfd.send_batch() poller not connected!");
         }
     }
 
@@ -164,30 +765,192 @@ impl Device<SoftRequest, SoftResponse, VfdRequest, VfdResponse> for Vfd
                     log::error!("Device.handle_external_request() id {:?} and {:?} does not matches!", id, self.id);
                 }
             }
-            _ => { self.batch.handle_request(request, self.id,)}
+            SoftRequest::Telemetry(id, telemetry) => {
+                if id == self.id {
+                    let response = match self.telemetry.get(telemetry) {
+                        Some(value) => SoftResponse::Telemetry(self.id, telemetry, value),
+                        None => SoftResponse::Error(self.id, DeviceType::Vfd, SoftError::Rejected(RejectReason::NotAvailable)),
+                    };
+                    self.send_external_response(response);
+                }
+            }
+            SoftRequest::Reset(id) => {
+                if id == self.id {
+                    let result = match self.commands.reset_register() {
+                        Some(_) => self.batch.reset(),
+                        None => Err(RejectReason::NotAvailable),
+                    };
+                    match result {
+                        Ok(()) => self.send_external_response(SoftResponse::Accepted(self.id, FunctionType::Reset)),
+                        Err(reason) => self.reject(reason),
+                    }
+                }
+            }
+            SoftRequest::Run(id, r) if id == self.id => {
+                // a command of the device supersedes the group command
+                self.group_applied(false);
+                match self.run_request(r) {
+                    Ok(()) => self.send_external_response(SoftResponse::Accepted(self.id, FunctionType::Run)),
+                    Err(reason) => self.reject(reason),
+                }
+            }
+            SoftRequest::Stop(id) if id == self.id => {
+                self.group_applied(false);
+                self.stop();
+                self.send_external_response(SoftResponse::Accepted(self.id, FunctionType::Stop));
+            }
+            SoftRequest::Polling(id, poll_status, auto_update) if id == self.id => {
+                log::info!("Vfd {} poll status: {}, auto update: {}", {let id: u8 = self.id.into(); id}, poll_status, auto_update);
+                self.poll_status = poll_status;
+                self.auto_update = auto_update;
+                self.send_external_response(SoftResponse::Polling(self.id, poll_status, auto_update));
+            }
+            SoftRequest::GroupRun(id, group, r) if id == self.id => {
+                self.group_applied(false);
+                match self.run_request(r) {
+                    Ok(()) => self.group_setpoint(group),
+                    Err(reason) => {
+                        self.reject(reason);
+                        self.group = Some(group);
+                        self.group_applied(false);
+                    }
+                }
+            }
+            SoftRequest::GroupStop(id, group) if id == self.id => {
+                self.group_applied(false);
+                self.stop();
+                self.group_setpoint(group);
+            }
+            SoftRequest::ReadParameter(id, address, quantity) if id == self.id => {
+                let in_range = (1..=MAX_PARAMETER_BLOCK).contains(&quantity)
+                    && address.checked_add(quantity as u16 - 1).is_some();
+                if in_range {
+                    self.batch.push_parameter(VfdRequest::ReadParameter(self.slave_id, address, quantity));
+                } else {
+                    self.reject(RejectReason::OutOfRange);
+                }
+            }
+            SoftRequest::WriteParameter(id, offset, value) if id == self.id => {
+                let Some(base) = self.parameter_address else {
+                    return self.reject(RejectReason::NotAvailable);
+                };
+                match base.checked_add(offset as u16) {
+                    Some(address) if self.writable_parameters.iter().any(|r| r.contains(&address)) => {
+                        self.parameter_writes.push((address, offset));
+                        self.batch.push_parameter(VfdRequest::WriteParameter(self.slave_id, address, value));
+                    }
+                    _ => self.reject(RejectReason::NotWritable),
+                }
+            }
+            _ => {
+                log::error!("Vfd.handle_external_request() unsupported request {:?}", request);
+            }
         }
     }
 
     fn handle_device_response(&mut self, response: VfdResponse) {
         log::debug!("Vfd.handle_device_response({:?})", response);
         match response {
-            // if command or ref fail, re-send on next batch
+            // retries are handled by the batch, failures reaching here are permanent
+            VfdResponse::Fail(r) if r.is_parameter() => {
+                if let Some(event) = self.link.failure() { self.report_event(event) }
+                self.parameter_error(r, SoftError::CommFailure);
+            }
+            VfdResponse::Exception(r, exception) if r.is_parameter() => {
+                if let Some(event) = self.link.answered() { self.report_event(event) }
+                self.parameter_error(r, SoftError::Exception(exception));
+            }
             VfdResponse::Fail(r) => {
-                match r {
-                    VfdRequest::Status(_) => {}
-                    _ => {self.batch.retry_request(r)}
+                log::error!("Vfd.handle_device_response() {:?} failed after all attempts!", r);
+                if let Some(event) = self.link.failure() { self.report_event(event) }
+                self.batch.forget(r);
+                if matches!(r, VfdRequest::Reset(_)) {
+                    self.reset_failed();
                 }
+                if r.is_write() {
+                    self.group_applied(false);
+                }
+                self.report_error(SoftError::CommFailure);
+            }
+            VfdResponse::Exception(r, exception) => {
+                if let Some(event) = self.link.answered() { self.report_event(event) }
+                self.batch.forget(r);
+                if matches!(r, VfdRequest::Reset(_)) {
+                    self.reset_failed();
+                }
+                if r.is_write() {
+                    self.group_applied(false);
+                }
+                self.report_error(SoftError::Exception(exception));
             }
             // update status
-            VfdResponse::Status(status) => {
+            VfdResponse::Status(speed, telemetry) => {
+                if let Some(event) = self.link.success() { self.report_event(event) }
+                self.error = None;
+                let status = self.client_status(speed);
                 self.status = status;
+                // a status read before the last command was written does not count
+                if status == VfdStatus::Stop && !self.batch.is_writing() {
+                    self.stopped_since.get_or_insert(Instant::now());
+                } else {
+                    self.stopped_since = None;
+                }
+                self.check_following();
+                self.update_telemetry(telemetry);
                 if self.auto_update {
                     self.send_external_response(SoftResponse::Status(self.id, status));
                 }
             }
-            _ => {}
+            VfdResponse::Telemetry(telemetry) => {
+                if let Some(event) = self.link.success() { self.report_event(event) }
+                self.error = None;
+                self.update_telemetry(telemetry);
+            }
+            VfdResponse::Parameters(block) => {
+                if let Some(event) = self.link.success() { self.report_event(event) }
+                self.error = None;
+                // the next writes are relative to the last block read
+                self.parameter_address = Some(block.address);
+                self.send_external_response(SoftResponse::Parameters(self.id, block));
+            }
+            VfdResponse::OK(r) => {
+                if let Some(event) = self.link.success() { self.report_event(event) }
+                self.error = None;
+                self.state.set_health(CommHealth::Ok);
+                if let VfdRequest::WriteParameter(_, address, value) = r {
+                    if let Some(offset) = self.take_parameter_write(address) {
+                        self.send_external_response(SoftResponse::ParameterWritten(self.id, address, offset, value));
+                    }
+                }
+                // the group setpoint is applied once all its writes succeed
+                if r.is_write() && self.group.is_some() {
+                    self.group_writes = self.group_writes.saturating_sub(1);
+                    if self.group_writes == 0 && !self.batch.is_writing() {
+                        self.group_applied(true);
+                    }
+                }
+            }
         }
         
         // auto update
     }
+
+    fn handle_retry(&mut self, attempt: Attempt<VfdRequest>) {
+        log::debug!("Vfd.handle_retry({:?})", attempt);
+        if let Some(event) = self.link.retry() { self.report_event(event) }
+        self.batch.retry_request(attempt);
+    }
+
+    fn handle_aborted(&mut self, request: VfdRequest) {
+        log::error!("Vfd.handle_aborted({:?})", request);
+        self.batch.forget(request);
+        if request.is_write() {
+            self.group_applied(false);
+        }
+    }
+
+    fn report_event(&mut self, event: DeviceEvent) {
+        log::info!("Vfd {} event: {:?}", {let id: u8 = self.id.into(); id}, event);
+        self.send_external_response(SoftResponse::Event(self.id, DeviceType::Vfd, event));
+    }
 }
\ No newline at end of file
@@ -1,12 +1,32 @@
-use modbus_core::{Request, Response};
+use modbus_core::{Data, Request, Response};
 use modbus_core::codec::Encode;
 use modbus_core::rtu::crc16;
+use serde::Deserialize;
 use serial_thread::SerialMessage;
-use crate::devices::vfd::requests::{VfdRequest, VfdResponse, VfdStatus};
-use crate::modbus::ModbusId;
+use crate::batch::RetryPolicy;
+use crate::devices::vfd::encoding::ValueEncoding;
+use crate::devices::vfd::requests::{ParameterBlock, Telemetry, VfdRequest, VfdResponse, VfdTelemetry};
+use crate::modbus::{ModbusException, ModbusId};
 use crate::traits::device_encoder::DeviceEncoder;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Registers and command values of a drive vendor, built-in (`FRECON`, `MEGMEET`) or loaded
+/// from a profile (see `VfdProfiles`).
+///
+/// Telemetry registers (`frequency_address`, `current_address`, `voltage_address` and
+/// `fault_address`) are optional, they are read with the status when set.
+///
+/// The command and reference are written in a single Write Multiple Registers frame (function
+/// 16) if `write_multiple` is set and their registers are adjacent. It should only be set for
+/// drives documented to accept function 16 on these registers, the built-in profiles write them
+/// with single register writes.
+///
+/// A fault reset writes `reset_value` to `reset_address`, or to `cmd_address` if the drive
+/// takes the reset as a command value. Drives without `reset_value` cannot be reset remotely.
+///
+/// The status and reference registers are converted with `encoding`, references and statuses
+/// exchanged with the client are engineering values.
 pub struct VfdCommands {
     pub cmd_address: u16,
     pub ref_address: u16,
@@ -14,6 +34,100 @@ pub struct VfdCommands {
     pub fw_value: u16,
     pub rv_value: u16,
     pub stop_value: u16,
+    #[serde(default)]
+    pub write_multiple: bool,
+    #[serde(default)]
+    pub encoding: ValueEncoding,
+    pub frequency_address: Option<u16>,
+    pub current_address: Option<u16>,
+    pub voltage_address: Option<u16>,
+    pub fault_address: Option<u16>,
+    pub reset_address: Option<u16>,
+    pub reset_value: Option<u16>,
+}
+
+impl VfdCommands {
+    /// Return the start address and the registers values to write command and reference in a
+    /// single Write Multiple Registers frame, or `None` if the profile does not allow it (drive
+    /// does not support function 16, or addresses are not adjacent).
+    pub fn cmd_ref_registers(&self, cmd: u16, reference: u16) -> Option<(u16, [u16; 2])> {
+        if !self.write_multiple {
+            None
+        } else if self.ref_address == self.cmd_address.wrapping_add(1) {
+            Some((self.cmd_address, [cmd, reference]))
+        } else if self.cmd_address == self.ref_address.wrapping_add(1) {
+            Some((self.ref_address, [reference, cmd]))
+        } else {
+            None
+        }
+    }
+
+    /// Return true if command and reference can be written in a single frame.
+    pub fn can_write_cmd_ref(&self) -> bool {
+        self.cmd_ref_registers(0, 0).is_some()
+    }
+
+    /// Return the address of a telemetry register, `None` if not in the profile.
+    pub fn telemetry_address(&self, telemetry: Telemetry) -> Option<u16> {
+        match telemetry {
+            Telemetry::Frequency => self.frequency_address,
+            Telemetry::Current => self.current_address,
+            Telemetry::Voltage => self.voltage_address,
+            Telemetry::Fault => self.fault_address,
+        }
+    }
+
+    /// Return the blocks of registers (start address, quantity) to read the status and the
+    /// telemetry, contiguous registers are read in a single block. The first block holds the
+    /// status register.
+    pub fn read_blocks(&self) -> Vec<(u16, u16)> {
+        let mut addresses: Vec<u16> = Telemetry::ALL.iter()
+            .filter_map(|t| self.telemetry_address(*t))
+            .chain([self.status_address])
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+
+        let mut blocks: Vec<(u16, u16)> = vec![];
+        for address in addresses {
+            match blocks.last_mut() {
+                Some((start, quantity)) if *start as u32 + *quantity as u32 == address as u32 => {
+                    *quantity += 1;
+                }
+                _ => blocks.push((address, 1)),
+            }
+        }
+        let status = blocks.iter()
+            .position(|(start, quantity)| Self::in_block(self.status_address, *start, *quantity))
+            .expect("status address is in a block");
+        let status = blocks.remove(status);
+        blocks.insert(0, status);
+        blocks
+    }
+
+    /// Return the telemetry values in a block of registers read from `start`.
+    pub fn telemetry(&self, start: u16, words: &[u16]) -> VfdTelemetry {
+        let mut telemetry = VfdTelemetry::default();
+        for t in Telemetry::ALL {
+            let value = self.telemetry_address(t)
+                .and_then(|a| a.checked_sub(start))
+                .and_then(|offset| words.get(offset as usize));
+            if let Some(value) = value {
+                telemetry.set(t, *value);
+            }
+        }
+        telemetry
+    }
+
+    /// Return the register and the value to write to reset a fault, `None` if the drive cannot
+    /// be reset remotely.
+    pub fn reset_register(&self) -> Option<(u16, u16)> {
+        self.reset_value.map(|value| (self.reset_address.unwrap_or(self.cmd_address), value))
+    }
+
+    fn in_block(address: u16, start: u16, quantity: u16) -> bool {
+        address >= start && (address as u32) < start as u32 + quantity as u32
+    }
 }
 
 pub const FRECON: VfdCommands = VfdCommands {
@@ -23,6 +137,14 @@ pub const FRECON: VfdCommands = VfdCommands {
     fw_value: 0x0001,
     rv_value: 0x0002,
     stop_value: 0x0005,
+    write_multiple: false,
+    encoding: ValueEncoding::RAW,
+    frequency_address: None,
+    current_address: None,
+    voltage_address: None,
+    fault_address: None,
+    reset_address: None,
+    reset_value: Some(0x0007),
 };
 
 pub const MEGMEET: VfdCommands = VfdCommands {
@@ -32,17 +154,27 @@ pub const MEGMEET: VfdCommands = VfdCommands {
     fw_value: 0x0034,
     rv_value: 0x003c,
     stop_value: 0x0035,
+    write_multiple: false,
+    encoding: ValueEncoding::RAW,
+    frequency_address: None,
+    current_address: None,
+    voltage_address: None,
+    fault_address: None,
+    reset_address: None,
+    reset_value: None,
 };
 
 #[derive(Debug, Clone, Copy)]
 pub struct VfdEncoder {
     commands: VfdCommands,
+    retry_policy: RetryPolicy,
 }
 
 impl VfdEncoder {
-    pub fn new(commands: VfdCommands) -> Self {
+    pub fn new(commands: VfdCommands, retry_policy: RetryPolicy) -> Self {
         VfdEncoder {
             commands,
+            retry_policy,
         }
     }
 
@@ -58,13 +190,6 @@ impl VfdEncoder {
     /// Returns an `Option<VfdResponse>` which is `Some` with the decoded response if successful,
     /// or `None` if the response cannot be decoded or is not valid for the request.
     fn decode_response(&self, msg: Vec<u8>, request: VfdRequest, vfd: VfdCommands) -> Option<VfdResponse> {
-        fn u16_to_i16(u: u16) -> i16 {
-            let mut v = (u & 0x7fff) as i16;
-            if ((u & 0x8000) >> 8) == 1 {
-                v = -v;
-            }
-            v
-        }
         // modbus id is dropped
         let raw_response = &msg[1..];
 
@@ -72,18 +197,39 @@ impl VfdEncoder {
         if let Ok(response) = modbus_core::Response::try_from(raw_response) {
             match (request, response) {
                 (VfdRequest::Status(s), Response::ReadHoldingRegisters(data)) => {
-                    if data.len() == 1 {
-                        let reference = u16_to_i16(data.get(0).expect("at least one word"));
-                        if reference == 0 {
-                            Some(VfdResponse::Status(VfdStatus::Stop))
-                        } else {
-                            Some(VfdResponse::Status(VfdStatus::Run(reference)))
-                        }
+                    let (start, quantity) = vfd.read_blocks()[0];
+                    if data.len() == quantity as usize {
+                        let words: Vec<u16> = data.into_iter().collect();
+                        let speed = vfd.encoding.decode(words[(vfd.status_address - start) as usize]);
+                        Some(VfdResponse::Status(speed, vfd.telemetry(start, &words)))
                     } else {
                         log::debug!("VfdEncoder.decode_response() status not match: {:?} / {:?}", s, response);
                         None
                     }
                 }
+                (VfdRequest::Telemetry(_, start, quantity), Response::ReadHoldingRegisters(data)) => {
+                    if data.len() == quantity as usize {
+                        let words: Vec<u16> = data.into_iter().collect();
+                        Some(VfdResponse::Telemetry(vfd.telemetry(start, &words)))
+                    } else {
+                        None
+                    }
+                }
+                (VfdRequest::ReadParameter(_, start, quantity), Response::ReadHoldingRegisters(data)) => {
+                    if data.len() == quantity as usize {
+                        let words: Vec<u16> = data.into_iter().collect();
+                        ParameterBlock::new(start, &words).map(VfdResponse::Parameters)
+                    } else {
+                        None
+                    }
+                }
+                (VfdRequest::WriteParameter(_, address, value), Response::WriteSingleRegister(addr, v)) => {
+                    if addr == address && v == value {
+                        Some(VfdResponse::OK(request))
+                    } else {
+                        None
+                    }
+                }
                 (VfdRequest::Cmd(_, dir), Response::WriteSingleRegister(addr, value)) => {
                     if addr == vfd.cmd_address && value == dir.into_u16(vfd) {
                         Some(VfdResponse::OK(request))
@@ -105,6 +251,20 @@ impl VfdEncoder {
                         None
                     }
                 }
+                (VfdRequest::Reset(_), Response::WriteSingleRegister(addr, value)) => {
+                    if vfd.reset_register() == Some((addr, value)) {
+                        Some(VfdResponse::OK(request))
+                    } else {
+                        None
+                    }
+                }
+                (VfdRequest::CmdRef(_, dir, reference), Response::WriteMultipleRegisters(addr, quantity)) => {
+                    let cmd = dir.map_or(vfd.stop_value, |d| d.into_u16(vfd));
+                    match vfd.cmd_ref_registers(cmd, reference) {
+                        Some((start, _)) if start == addr && quantity == 2 => Some(VfdResponse::OK(request)),
+                        _ => None,
+                    }
+                }
                 (a, b) => {
                     log::debug!("VfdEncoder.decode_response() unrecognized pattern! {:?} / {:?}", a, b);
                     None
@@ -120,6 +280,7 @@ impl VfdEncoder {
 impl DeviceEncoder<VfdRequest, VfdResponse> for VfdEncoder {
     fn request_to_serial(&self, request: VfdRequest) -> Option<SerialMessage> {
         let vfd = self.commands;
+        let mut data_buffer = [0u8; 4];
         let (id, request) = match request {
             VfdRequest::Cmd(id, dir) => (id, Request::WriteSingleRegister(
                 vfd.cmd_address, dir.into_u16(vfd))),
@@ -127,12 +288,36 @@ impl DeviceEncoder<VfdRequest, VfdResponse> for VfdEncoder {
                 (id, Request::WriteSingleRegister(vfd.ref_address, reference))
             }
             VfdRequest::Stop(id) => (id, Request::WriteSingleRegister(vfd.cmd_address, vfd.stop_value)),
-            VfdRequest::Status(id) => (id, Request::ReadHoldingRegisters(vfd.status_address, 1)),
+            VfdRequest::Status(id) => {
+                let (start, quantity) = vfd.read_blocks()[0];
+                (id, Request::ReadHoldingRegisters(start, quantity))
+            }
+            VfdRequest::Telemetry(id, start, quantity) => (id, Request::ReadHoldingRegisters(start, quantity)),
+            VfdRequest::ReadParameter(id, start, quantity) => {
+                (id, Request::ReadHoldingRegisters(start, quantity as u16))
+            }
+            VfdRequest::WriteParameter(id, address, value) => (id, Request::WriteSingleRegister(address, value)),
+            VfdRequest::Reset(id) => {
+                let Some((address, value)) = vfd.reset_register() else {
+                    log::error!("VfdEncoder.request_to_serial() profile does not allow {:?}", request);
+                    return None;
+                };
+                (id, Request::WriteSingleRegister(address, value))
+            }
+            VfdRequest::CmdRef(id, dir, reference) => {
+                let cmd = dir.map_or(vfd.stop_value, |d| d.into_u16(vfd));
+                let Some((address, words)) = vfd.cmd_ref_registers(cmd, reference) else {
+                    log::error!("VfdEncoder.request_to_serial() profile does not allow {:?}", request);
+                    return None;
+                };
+                let data = Data::from_words(&words, &mut data_buffer).expect("2 words fit in buffer");
+                (id, Request::WriteMultipleRegisters(address, data))
+            }
         };
         let mut frame: Vec<u8> = vec![id.into()];
-        let bytes = &mut [0; 5];
-        request.encode(bytes).expect("fixed frame size");
-        frame.append(&mut bytes.to_vec());
+        let bytes = &mut [0; 16];
+        let len = request.encode(bytes).expect("frame fit in buffer");
+        frame.extend_from_slice(&bytes[..len]);
         let crc = crc16(&frame.to_vec());
         frame.append(&mut vec![((crc & 0xff00) >> 8) as u8, (crc & 0x00ff) as u8]);
 
@@ -143,9 +328,12 @@ impl DeviceEncoder<VfdRequest, VfdResponse> for VfdEncoder {
         log::debug!("VfdEncoder.serial_to_response({:?})", msg);
         match msg.clone() {
             SerialMessage::Receive(data) => {
-                if data[0] != id.into() {
+                if data[0] != {let i: u8 = id.into(); i} {
                     log::error!("VfdEncoder.serial_to_response() id not match! ({} vs {})", &data[0], {let i: u8 = id.into(); i});
                     VfdResponse::Fail(request)
+                } else if let Some(exception) = ModbusException::from_frame(&data) {
+                    log::error!("VfdEncoder.serial_to_response() {:?} answered with {:?}!", request, exception);
+                    VfdResponse::Exception(request, exception)
                 } else if let Some(response) = self.decode_response(data, request, self.commands) {
                     response
                 } else {
@@ -161,4 +349,31 @@ impl DeviceEncoder<VfdRequest, VfdResponse> for VfdEncoder {
         }
     }
 
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Writes of command and reference can be broadcast.
+    fn broadcast_response(&self, request: VfdRequest) -> Option<VfdResponse> {
+        match request {
+            VfdRequest::Cmd(_, _)
+            | VfdRequest::Ref(_, _)
+            | VfdRequest::Stop(_)
+            | VfdRequest::CmdRef(_, _, _) => Some(VfdResponse::OK(request)),
+            _ => None,
+        }
+    }
+
+    fn is_failure(&self, response: &VfdResponse) -> bool {
+        matches!(response, VfdResponse::Fail(_) | VfdResponse::Exception(_, _))
+    }
+
+    fn should_retry(&self, response: &VfdResponse) -> bool {
+        match response {
+            VfdResponse::Fail(_) => true,
+            VfdResponse::Exception(_, exception) => exception.is_retryable(),
+            _ => false,
+        }
+    }
+
 }
\ No newline at end of file
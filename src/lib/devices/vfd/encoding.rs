@@ -0,0 +1,142 @@
+use serde::Deserialize;
+use crate::error::Error;
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How a drive encodes the sign of a speed register.
+///
+/// ## Variants
+/// - `Unsigned`: The register holds a magnitude only (16 bits).
+/// - `SignMagnitude`: Magnitude on bits 0-14, sign on bit 15.
+/// - `TwosComplement`: Signed 16 bits integer.
+/// - `DirectionBit(u8)`: Magnitude on the other bits, the given bit is set in reverse.
+pub enum SignScheme {
+    Unsigned,
+    #[default]
+    SignMagnitude,
+    TwosComplement,
+    DirectionBit(u8),
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Engineering unit of a decoded speed value.
+///
+/// ## Variants
+/// - `Raw`: Drive specific unit (default).
+/// - `Hz`: Output frequency, in Hz.
+/// - `Rpm`: Motor speed, in rpm.
+/// - `Percent`: Percent of the maximum speed.
+pub enum Unit {
+    #[default]
+    Raw,
+    Hz,
+    Rpm,
+    Percent,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Encoding of the status and reference registers of a drive.
+///
+/// The engineering value of a register is `signed * scale + offset`, where `signed` is the
+/// register value decoded with the `sign` scheme. References are encoded back with the same
+/// conversion.
+///
+/// Fields:
+/// - `sign`: Sign scheme, default to `sign_magnitude`.
+/// - `scale`: Scale factor applied to the register value, default to `1.0`.
+/// - `offset`: Offset added to the scaled value, default to `0.0`.
+/// - `unit`: Engineering unit of the value, default to `raw`.
+pub struct ValueEncoding {
+    #[serde(default)]
+    pub sign: SignScheme,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub offset: f32,
+    #[serde(default)]
+    pub unit: Unit,
+}
+
+impl Default for ValueEncoding {
+    fn default() -> Self {
+        ValueEncoding::RAW
+    }
+}
+
+impl ValueEncoding {
+    /// Sign-magnitude registers in drive units, without scaling.
+    pub const RAW: ValueEncoding = ValueEncoding {
+        sign: SignScheme::SignMagnitude,
+        scale: 1.0,
+        offset: 0.0,
+        unit: Unit::Raw,
+    };
+
+    /// Decode a register value into an engineering value.
+    pub fn decode(&self, raw: u16) -> f32 {
+        let signed = match self.sign {
+            SignScheme::Unsigned => raw as f32,
+            SignScheme::SignMagnitude => Self::signed(raw & 0x7fff, raw & 0x8000 != 0),
+            SignScheme::TwosComplement => raw as i16 as f32,
+            SignScheme::DirectionBit(bit) => {
+                let mask = Self::direction_mask(bit);
+                Self::signed(raw & !mask, raw & mask != 0)
+            }
+        };
+        signed * self.scale + self.offset
+    }
+
+    /// Encode an engineering value into a register value, return `None` if it does not fit
+    /// the register.
+    pub fn encode(&self, value: f32) -> Option<u16> {
+        let signed = ((value - self.offset) / self.scale).round();
+        if !signed.is_finite() {
+            return None;
+        }
+        let magnitude = signed.abs();
+        let negative = signed < 0.0;
+        match self.sign {
+            SignScheme::Unsigned => (!negative && magnitude <= u16::MAX as f32).then_some(magnitude as u16),
+            SignScheme::SignMagnitude => (magnitude <= 0x7fff as f32)
+                .then_some(magnitude as u16 | if negative { 0x8000 } else { 0 }),
+            SignScheme::TwosComplement => (i16::MIN as f32..=i16::MAX as f32).contains(&signed)
+                .then_some(signed as i16 as u16),
+            SignScheme::DirectionBit(bit) => {
+                let mask = Self::direction_mask(bit);
+                (magnitude <= u16::MAX as f32 && magnitude as u16 & mask == 0)
+                    .then_some(magnitude as u16 | if negative { mask } else { 0 })
+            }
+        }
+    }
+
+    /// Check the scaling and the sign scheme.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.scale == 0.0 || !self.scale.is_finite() || !self.offset.is_finite() {
+            return Err(Error::InvalidVfdProfile("invalid encoding scaling".to_string()));
+        }
+        if matches!(self.sign, SignScheme::DirectionBit(bit) if bit > 15) {
+            return Err(Error::InvalidVfdProfile("direction bit should be 0 to 15".to_string()));
+        }
+        Ok(())
+    }
+
+    fn direction_mask(bit: u8) -> u16 {
+        1u16.checked_shl(bit as u32).unwrap_or(0)
+    }
+
+    fn signed(magnitude: u16, negative: bool) -> f32 {
+        if negative {
+            -(magnitude as f32)
+        } else {
+            magnitude as f32
+        }
+    }
+}
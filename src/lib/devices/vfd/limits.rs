@@ -0,0 +1,78 @@
+use std::time::Duration;
+use crate::devices::vfd::requests::Dir;
+use crate::soft_request::RejectReason;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Safety limits of a `Vfd`, checked on every run request (a stop is always accepted).
+///
+/// Fields:
+/// - `min_reference`, `max_reference`: Allowed reference magnitude, in client units (see
+///   `Vfd::nameplate()`).
+/// - `forbidden`: Direction the drive must never run in.
+/// - `reversal_dwell`: If set, the direction only changes once the drive have been read stopped
+///   for this duration (needs `poll_status`).
+pub struct VfdLimits {
+    pub min_reference: u16,
+    pub max_reference: u16,
+    pub forbidden: Option<Dir>,
+    pub reversal_dwell: Option<Duration>,
+}
+
+impl Default for VfdLimits {
+    fn default() -> Self {
+        VfdLimits::new()
+    }
+}
+
+impl VfdLimits {
+    /// No limit.
+    pub fn new() -> Self {
+        VfdLimits {
+            min_reference: 0,
+            max_reference: u16::MAX,
+            forbidden: None,
+            reversal_dwell: None,
+        }
+    }
+
+    /// Set the allowed reference magnitude.
+    pub fn reference(mut self, min: u16, max: u16) -> Self {
+        self.min_reference = min;
+        self.max_reference = max;
+        self
+    }
+
+    /// Forbid a direction.
+    pub fn forbid(mut self, dir: Dir) -> Self {
+        self.forbidden = Some(dir);
+        self
+    }
+
+    /// Require a stop of `dwell` before a direction change.
+    pub fn stop_before_reversal(mut self, dwell: Duration) -> Self {
+        self.reversal_dwell = Some(dwell);
+        self
+    }
+
+    /// Check the direction and the reference magnitude of a run request.
+    pub fn check(&self, dir: Dir, reference: u16) -> Result<(), RejectReason> {
+        if self.forbidden == Some(dir) {
+            Err(RejectReason::DirectionForbidden)
+        } else if !(self.min_reference..=self.max_reference).contains(&reference) {
+            Err(RejectReason::OutOfRange)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check a direction change from `last` to `dir`, `stopped_for` is how long the drive have
+    /// been read stopped.
+    pub fn check_reversal(&self, last: Option<Dir>, dir: Dir, stopped_for: Option<Duration>) -> Result<(), RejectReason> {
+        match (self.reversal_dwell, last) {
+            (Some(dwell), Some(last)) if last != dir && stopped_for.is_none_or(|d| d < dwell) => {
+                Err(RejectReason::StopRequired)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,3 +1,9 @@
 pub mod device;
 pub mod encoder;
+pub mod encoding;
+pub mod limits;
+pub mod nameplate;
+pub mod profile;
+pub mod ramp;
+pub mod reconcile;
 pub mod requests;
\ No newline at end of file
@@ -0,0 +1,73 @@
+use crate::devices::vfd::encoding::Unit;
+use crate::error::Error;
+
+/// Hz and percent values exchanged with the client are in hundredths (e.g. `5000` = 50 Hz).
+pub const CENTI: f32 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Motor nameplate data of a `Vfd`, to exchange references and statuses with the client in
+/// engineering units (see `Vfd::nameplate()`).
+///
+/// Fields:
+/// - `max_frequency`: Maximum output frequency, in Hz.
+/// - `pole_pairs`: Number of pole pairs of the motor, to convert frequency into rpm.
+/// - `full_scale`: Drive value (see `ValueEncoding`) at `max_frequency`, default to
+///   `max_frequency` (drive values in Hz).
+pub struct Nameplate {
+    pub max_frequency: f32,
+    pub pole_pairs: u8,
+    pub full_scale: f32,
+}
+
+impl Nameplate {
+    pub fn new(max_frequency: f32, pole_pairs: u8) -> Self {
+        Nameplate {
+            max_frequency,
+            pole_pairs,
+            full_scale: max_frequency,
+        }
+    }
+
+    /// Set the drive value at the maximum frequency.
+    pub fn full_scale(mut self, full_scale: f32) -> Self {
+        self.full_scale = full_scale;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(self.max_frequency > 0.0 && self.max_frequency.is_finite()) {
+            return Err(Error::InvalidNameplate("max frequency should be positive".to_string()));
+        }
+        if self.pole_pairs == 0 {
+            return Err(Error::InvalidNameplate("pole pairs should be positive".to_string()));
+        }
+        if self.full_scale == 0.0 || !self.full_scale.is_finite() {
+            return Err(Error::InvalidNameplate("invalid full scale".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Convert a client value in `unit` into a drive value, return `None` if the frequency is
+    /// above the maximum frequency.
+    pub fn from_client(&self, value: f32, unit: Unit) -> Option<f32> {
+        let frequency = match unit {
+            Unit::Raw => value / self.full_scale * self.max_frequency,
+            Unit::Hz => value / CENTI,
+            Unit::Rpm => value * self.pole_pairs as f32 / 60.0,
+            Unit::Percent => value / CENTI / 100.0 * self.max_frequency,
+        };
+        (frequency.abs() <= self.max_frequency)
+            .then_some(frequency / self.max_frequency * self.full_scale)
+    }
+
+    /// Convert a drive value into a client value in `unit`.
+    pub fn to_client(&self, value: f32, unit: Unit) -> f32 {
+        let frequency = value / self.full_scale * self.max_frequency;
+        match unit {
+            Unit::Raw => value,
+            Unit::Hz => frequency * CENTI,
+            Unit::Rpm => frequency * 60.0 / self.pole_pairs as f32,
+            Unit::Percent => frequency / self.max_frequency * 100.0 * CENTI,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::devices::vfd::encoder::VfdCommands;
+use crate::devices::vfd::requests::Telemetry;
+use crate::error::Error;
+
+/// Profiles shipped with the library, as (name, TOML description).
+const BUNDLED: [(&str, &str); 2] = [
+    ("frecon", include_str!("profiles/frecon.toml")),
+    ("megmeet", include_str!("profiles/megmeet.toml")),
+];
+
+impl VfdCommands {
+    /// Parse and validate a profile from a TOML description.
+    pub fn from_toml(description: &str) -> Result<Self, Error> {
+        let commands: VfdCommands = toml::from_str(description)
+            .map_err(|e| Error::InvalidVfdProfile(e.to_string()))?;
+        commands.validate()?;
+        Ok(commands)
+    }
+
+    /// Parse and validate a profile from a JSON description.
+    pub fn from_json(description: &str) -> Result<Self, Error> {
+        let commands: VfdCommands = serde_json::from_str(description)
+            .map_err(|e| Error::InvalidVfdProfile(e.to_string()))?;
+        commands.validate()?;
+        Ok(commands)
+    }
+
+    /// Check that command values and registers do not overlap.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.encoding.validate()?;
+        let invalid = |msg: &str| Err(Error::InvalidVfdProfile(msg.to_string()));
+        let mut values = vec![self.fw_value, self.rv_value, self.stop_value];
+        // a reset written to the command register is a command value
+        if let (None, Some(reset)) = (self.reset_address, self.reset_value) {
+            values.push(reset);
+        }
+        if !distinct(&values) {
+            return invalid("command values should be distinct");
+        }
+        if self.cmd_address == self.ref_address {
+            return invalid("command and reference registers should be distinct");
+        }
+        let mut reads: Vec<u16> = Telemetry::ALL.iter()
+            .filter_map(|t| self.telemetry_address(*t))
+            .collect();
+        reads.push(self.status_address);
+        if !distinct(&reads) {
+            return invalid("status and telemetry registers should be distinct");
+        }
+        if self.reset_address.is_some() && self.reset_value.is_none() {
+            return invalid("reset register without reset value");
+        }
+        Ok(())
+    }
+}
+
+fn distinct(values: &[u16]) -> bool {
+    values.iter().enumerate().all(|(i, v)| !values[..i].contains(v))
+}
+
+#[derive(Debug, Clone)]
+/// Vfd profiles, selectable by name.
+///
+/// The bundled profiles (`frecon` & `megmeet`) are always available. A profile directory holds
+/// one `<name>.toml` or `<name>.json` file per profile, a profile of the directory replaces the
+/// bundled profile of the same name:
+///
+/// ```toml
+/// cmd_address = 0x2000
+/// ref_address = 0x2001
+/// status_address = 0x3000
+/// fw_value = 0x0001
+/// rv_value = 0x0002
+/// stop_value = 0x0005
+/// # only if the drive accepts function 16 on the command and reference registers
+/// write_multiple = true
+/// # optional extended registers
+/// fault_address = 0x8000
+/// reset_value = 0x0007
+///
+/// # status and reference registers, in 0.01 Hz, two's complement
+/// [encoding]
+/// sign = "twos_complement"
+/// scale = 0.01
+/// unit = "hz"
+/// ```
+///
+/// A direction bit is set with `sign = { direction_bit = 15 }`.
+pub struct VfdProfiles {
+    profiles: HashMap<String, VfdCommands>,
+}
+
+impl Default for VfdProfiles {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+impl VfdProfiles {
+    /// Return the bundled profiles.
+    pub fn bundled() -> Self {
+        let profiles = BUNDLED.iter()
+            .map(|(name, description)| {
+                let commands = VfdCommands::from_toml(description).expect("bundled profile is valid");
+                (name.to_string(), commands)
+            })
+            .collect();
+        VfdProfiles { profiles }
+    }
+
+    /// Return the bundled profiles and the profiles of the directory `path`, every profile is
+    /// validated.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let read_error = |e: std::io::Error| Error::InvalidVfdProfile(format!("{}: {}", path.display(), e));
+        let mut profiles = Self::bundled();
+        let mut loaded = HashMap::new();
+        let mut entries = fs::read_dir(path).map_err(read_error)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error)?;
+        entries.sort();
+        for file in entries {
+            let (Some(name), Some(extension)) = (file.file_stem(), file.extension()) else {
+                continue;
+            };
+            let parse: fn(&str) -> Result<VfdCommands, Error> = match extension.to_str() {
+                Some("toml") => VfdCommands::from_toml,
+                Some("json") => VfdCommands::from_json,
+                _ => continue,
+            };
+            let name = name.to_string_lossy().to_string();
+            let in_file = |e: Error| match e {
+                Error::InvalidVfdProfile(e) => Error::InvalidVfdProfile(format!("{}: {}", file.display(), e)),
+                e => e,
+            };
+            let description = fs::read_to_string(&file)
+                .map_err(|e| in_file(Error::InvalidVfdProfile(e.to_string())))?;
+            let commands = parse(&description).map_err(in_file)?;
+            if loaded.insert(name.clone(), commands).is_some() {
+                return Err(Error::InvalidVfdProfile(format!("duplicate profile {}", name)));
+            }
+        }
+        profiles.profiles.extend(loaded);
+        Ok(profiles)
+    }
+
+    /// Add (or replace) a profile, after validating it.
+    pub fn insert(&mut self, name: &str, commands: VfdCommands) -> Result<(), Error> {
+        commands.validate()?;
+        self.profiles.insert(name.to_string(), commands);
+        Ok(())
+    }
+
+    /// Return the profile named `name`.
+    pub fn get(&self, name: &str) -> Result<VfdCommands, Error> {
+        self.profiles.get(name)
+            .copied()
+            .ok_or_else(|| Error::UnknownVfdProfile(name.to_string()))
+    }
+
+    /// Return the names of the available profiles, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profiles.keys().map(|n| n.as_str()).collect();
+        names.sort();
+        names
+    }
+}
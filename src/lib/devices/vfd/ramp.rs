@@ -0,0 +1,168 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Ratio between the peak and the mean rate of an S-curve segment (smoothstep).
+const S_CURVE_PEAK: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Reference ramp limits of a `Vfd`, in client units per second (see `Vfd::nameplate()`).
+///
+/// `acceleration` applies while the reference magnitude increases, `deceleration` while it
+/// decreases, a reversal decelerates to zero first. With `s_curve`, the rate of each segment
+/// starts and ends at zero and peaks at the limit, instead of being constant. A limit of `0.0`
+/// does not limit the rate.
+pub struct RampConfig {
+    pub acceleration: f32,
+    pub deceleration: f32,
+    pub s_curve: bool,
+}
+
+impl RampConfig {
+    pub fn new(acceleration: f32, deceleration: f32) -> Self {
+        RampConfig {
+            acceleration,
+            deceleration,
+            s_curve: false,
+        }
+    }
+
+    /// Smooth the start and the end of each segment.
+    pub fn s_curve(mut self, s_curve: bool) -> Self {
+        self.s_curve = s_curve;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Progress of a ramp: the reference written to the drive (`setpoint`) and the reference
+/// requested by the client (`target`).
+pub struct RampProgress {
+    pub setpoint: i16,
+    pub target: i16,
+}
+
+impl RampProgress {
+    /// Return true if the setpoint reached the target.
+    pub fn is_done(&self) -> bool {
+        self.setpoint == self.target
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A part of the ramp that does not cross zero, run at a single rate limit.
+struct Segment {
+    from: f32,
+    to: f32,
+    start: Instant,
+    duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Generate the intermediate references from the current setpoint to the target, under the
+/// limits of a `RampConfig`.
+pub struct Ramp {
+    config: RampConfig,
+    setpoint: f32,
+    target: i16,
+    segment: Option<Segment>,
+}
+
+impl Ramp {
+    pub fn new(config: RampConfig) -> Self {
+        Ramp {
+            config,
+            setpoint: 0.0,
+            target: 0,
+            segment: None,
+        }
+    }
+
+    /// Return the current progress of the ramp.
+    pub fn progress(&self) -> RampProgress {
+        RampProgress {
+            setpoint: self.setpoint.round() as i16,
+            target: self.target,
+        }
+    }
+
+    /// Set a new target, the ramp restarts from the setpoint reached at `now`.
+    pub fn set_target(&mut self, target: i16, now: Instant) {
+        if target != self.target {
+            self.advance(now);
+            self.target = target;
+            self.segment = Some(self.next_segment(now));
+        }
+    }
+
+    /// Drop the ramp, setpoint and target are back to zero (the drive is stopped).
+    pub fn reset(&mut self) {
+        self.setpoint = 0.0;
+        self.target = 0;
+        self.segment = None;
+    }
+
+    /// Advance the ramp up to `now`, return the setpoint reached.
+    pub fn advance(&mut self, now: Instant) -> i16 {
+        let mut start = now;
+        loop {
+            let segment = match self.segment {
+                Some(segment) => segment,
+                None if self.setpoint == self.target as f32 => break,
+                None => {
+                    let segment = self.next_segment(start);
+                    self.segment = Some(segment);
+                    segment
+                }
+            };
+            let elapsed = now.saturating_duration_since(segment.start);
+            if elapsed < segment.duration {
+                let progress = elapsed.as_secs_f32() / segment.duration.as_secs_f32();
+                self.setpoint = segment.from + (segment.to - segment.from) * self.shape(progress);
+                break;
+            }
+            // the next segment starts when this one ended
+            self.setpoint = segment.to;
+            self.segment = None;
+            start = segment.start + segment.duration;
+            // the direction changes: zero is returned (and written) first, the next segment
+            // starts now
+            if segment.to == 0.0 && self.target != 0 {
+                self.segment = Some(self.next_segment(now));
+                break;
+            }
+        }
+        self.progress().setpoint
+    }
+
+    /// Return the next segment from the setpoint toward the target, stopping at zero if the
+    /// direction changes.
+    fn next_segment(&self, start: Instant) -> Segment {
+        let from = self.setpoint;
+        let target = self.target as f32;
+        let to = if from * target < 0.0 { 0.0 } else { target };
+        let rate = if to.abs() > from.abs() {
+            self.config.acceleration
+        } else {
+            self.config.deceleration
+        };
+        let mut seconds = if rate > 0.0 { (to - from).abs() / rate } else { 0.0 };
+        if self.config.s_curve {
+            seconds *= S_CURVE_PEAK;
+        }
+        Segment {
+            from,
+            to,
+            start,
+            duration: Duration::try_from_secs_f32(seconds).unwrap_or(Duration::ZERO),
+        }
+    }
+
+    /// Return the fraction of the segment covered at `progress` (0..1) of its duration.
+    fn shape(&self, progress: f32) -> f32 {
+        if self.config.s_curve {
+            progress * progress * (3.0 - 2.0 * progress)
+        } else {
+            progress
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Reconciliation of a `Vfd` desired state with the status polled from the drive.
+///
+/// The drive follows while its status is within `tolerance` (client units) of the desired
+/// state. A drive that does not follow for `settle_time` gets its command and reference written
+/// again, up to `max_attempts` times, then the `Vfd` reports that it does not follow.
+/// `settle_time` should cover the acceleration time of the drive.
+pub struct ReconcileConfig {
+    pub tolerance: u16,
+    pub settle_time: Duration,
+    pub max_attempts: u32,
+}
+
+impl ReconcileConfig {
+    pub fn new(tolerance: u16, settle_time: Duration, max_attempts: u32) -> Self {
+        ReconcileConfig {
+            tolerance,
+            settle_time,
+            max_attempts,
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Action to take after comparing the desired state with the drive status.
+///
+/// ## Variants
+/// - `Rewrite`: Write the command and the reference again.
+/// - `NotFollowing`: The drive did not converge after all attempts, report the alarm.
+/// - `Following`: The drive follows again after an alarm, clear it.
+pub enum Reconcile {
+    Rewrite,
+    NotFollowing,
+    Following,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Compare the desired state of a `Vfd` with the statuses polled from the drive.
+pub struct Reconciler {
+    config: ReconcileConfig,
+    diverging_since: Option<Instant>,
+    attempts: u32,
+    alarm: bool,
+}
+
+impl Reconciler {
+    pub fn new(config: ReconcileConfig) -> Self {
+        Reconciler {
+            config,
+            diverging_since: None,
+            attempts: 0,
+            alarm: false,
+        }
+    }
+
+    /// Return true while the "not following" alarm is active.
+    pub fn alarm(&self) -> bool {
+        self.alarm
+    }
+
+    /// The desired state changed, the drive is given `settle_time` and every attempt again.
+    pub fn desired_changed(&mut self) {
+        self.diverging_since = None;
+        self.attempts = 0;
+    }
+
+    /// Compare the `desired` and `actual` statuses (client units) read at `now`.
+    pub fn check(&mut self, desired: i16, actual: i16, now: Instant) -> Option<Reconcile> {
+        if (desired as i32 - actual as i32).unsigned_abs() <= self.config.tolerance as u32 {
+            self.desired_changed();
+            let cleared = std::mem::replace(&mut self.alarm, false);
+            return cleared.then_some(Reconcile::Following);
+        }
+        let since = *self.diverging_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < self.config.settle_time {
+            None
+        } else if self.attempts < self.config.max_attempts {
+            // the drive is given settle time after each attempt
+            self.attempts += 1;
+            self.diverging_since = Some(now);
+            Some(Reconcile::Rewrite)
+        } else if !self.alarm {
+            self.alarm = true;
+            Some(Reconcile::NotFollowing)
+        } else {
+            None
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use crate::devices::vfd::encoder::VfdCommands;
+use crate::devices::vfd::ramp::RampProgress;
 use crate::devices::vfd::requests::Dir::Fw;
-use crate::modbus::ModbusId;
+use crate::modbus::{ModbusException, ModbusId};
 
+/// Maximum quantity of registers read by a single parameter request.
+pub const MAX_PARAMETER_BLOCK: u8 = 8;
 
 #[allow(unused)]
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
@@ -34,12 +37,51 @@ impl Dir {
 /// - `Cmd(Dir)`: Command with a direction (`Fw` or `Rv`).
 /// - `Ref(u16)`: A reference value.
 /// - `Stop`: Command to stop the VFD.
-/// - `Status`: Request for the current status of the VFD.
+/// - `Status`: Request for the current status of the VFD, with the telemetry registers
+///   contiguous to the status register.
+/// - `CmdRef(Option<Dir>, u16)`: Command (`None` for stop) and reference written in a single
+///   Write Multiple Registers frame.
+/// - `Telemetry(u16, u16)`: Read a block of telemetry registers (start address, quantity), see
+///   `VfdCommands::read_blocks()`.
+/// - `Reset`: Reset a drive fault, see `VfdCommands::reset_register()`.
+/// - `ReadParameter(u16, u8)`: Read a block of parameter registers (start address, quantity).
+/// - `WriteParameter(u16, u16)`: Write a parameter register (address, value).
 pub enum VfdRequest {
     Cmd(ModbusId, Dir),
     Ref(ModbusId, u16),
     Stop(ModbusId),
     Status(ModbusId),
+    CmdRef(ModbusId, Option<Dir>, u16),
+    Telemetry(ModbusId, u16, u16),
+    Reset(ModbusId),
+    ReadParameter(ModbusId, u16, u8),
+    WriteParameter(ModbusId, u16, u16),
+}
+
+impl VfdRequest {
+    pub fn id(&self) -> ModbusId {
+        match self {
+            VfdRequest::Cmd(id, _)
+            | VfdRequest::Ref(id, _)
+            | VfdRequest::Stop(id)
+            | VfdRequest::Status(id)
+            | VfdRequest::Reset(id)
+            | VfdRequest::CmdRef(id, _, _)
+            | VfdRequest::Telemetry(id, _, _)
+            | VfdRequest::ReadParameter(id, _, _)
+            | VfdRequest::WriteParameter(id, _, _) => *id,
+        }
+    }
+
+    /// Return true for a one-off parameter request, answered to the client whatever its result.
+    pub fn is_parameter(&self) -> bool {
+        matches!(self, VfdRequest::ReadParameter(_, _, _) | VfdRequest::WriteParameter(_, _, _))
+    }
+
+    /// Return true for a write of the command or the reference.
+    pub fn is_write(&self) -> bool {
+        matches!(self, VfdRequest::Cmd(_, _) | VfdRequest::Ref(_, _) | VfdRequest::Stop(_) | VfdRequest::CmdRef(_, _, _))
+    }
 }
 
 
@@ -50,16 +92,52 @@ pub enum VfdRequest {
 /// ## Variants
 /// - `OK(VfdRequest)`: Successful acknowledgment of a `VfdRequest`.
 /// - `Fail(VfdRequest)`: Indicates a failure in processing a `VfdRequest`.
-/// - `Status(VfdStatus)`: Provides the status of the VFD.
+/// - `Exception(VfdRequest, ModbusException)`: The VFD answered a `VfdRequest` with a Modbus exception.
+/// - `Status(f32, VfdTelemetry)`: Provides the speed read from the status register (drive
+///   value, see `ValueEncoding`), and the telemetry
+///   registers read with it.
+/// - `Telemetry(VfdTelemetry)`: Provides the telemetry registers of a `Telemetry` request.
+/// - `Parameters(ParameterBlock)`: Provides the registers of a `ReadParameter` request.
 /// - `Poll`: Indicates a polling request in order to VfdAxis send a Batch to VfdPoller.
 pub enum VfdResponse {
     OK(VfdRequest),
     Fail(VfdRequest),
-    Status(VfdStatus),
+    Exception(VfdRequest, ModbusException),
+    Status(f32, VfdTelemetry),
+    Telemetry(VfdTelemetry),
+    Parameters(ParameterBlock),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Parameter registers read from `address`, the first `quantity` values are valid.
+pub struct ParameterBlock {
+    pub address: u16,
+    pub quantity: u8,
+    pub values: [u16; MAX_PARAMETER_BLOCK as usize],
+}
+
+impl ParameterBlock {
+    /// Return a block of the registers `words` read from `address`, `None` if too long.
+    pub fn new(address: u16, words: &[u16]) -> Option<Self> {
+        if words.len() > MAX_PARAMETER_BLOCK as usize {
+            return None;
+        }
+        let mut values = [0; MAX_PARAMETER_BLOCK as usize];
+        values[..words.len()].copy_from_slice(words);
+        Some(ParameterBlock {
+            address,
+            quantity: words.len() as u8,
+            values,
+        })
+    }
+
+    pub fn values(&self) -> &[u16] {
+        &self.values[..self.quantity as usize]
+    }
 }
 
 #[allow(unused)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Indicates the current status of a Vfd.
 ///
 /// ## Variants
@@ -70,4 +148,88 @@ pub enum VfdStatus {
     Run(i16),
     Stop,
     None,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A telemetry value of a Vfd, also selects the soft protocol function code to read it.
+///
+/// ## Variants
+/// - `Frequency`: Output frequency (function code `4`).
+/// - `Current`: Motor current (function code `5`).
+/// - `Voltage`: DC bus voltage (function code `6`).
+/// - `Fault`: Active fault/alarm code, `0` if none (function code `7`).
+pub enum Telemetry {
+    Frequency,
+    Current,
+    Voltage,
+    Fault,
+}
+
+impl Telemetry {
+    pub const ALL: [Telemetry; 4] = [Telemetry::Frequency, Telemetry::Current, Telemetry::Voltage, Telemetry::Fault];
+
+    pub fn fn_code(&self) -> u8 {
+        match self {
+            Telemetry::Frequency => 4,
+            Telemetry::Current => 5,
+            Telemetry::Voltage => 6,
+            Telemetry::Fault => 7,
+        }
+    }
+
+    pub fn from_fn_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.fn_code() == code)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Telemetry of a Vfd, as raw register values (units depend on the drive).
+///
+/// A value is `None` if its register is not in the `VfdCommands` profile, or not read yet.
+pub struct VfdTelemetry {
+    pub frequency: Option<u16>,
+    pub current: Option<u16>,
+    pub voltage: Option<u16>,
+    pub fault: Option<u16>,
+}
+
+impl VfdTelemetry {
+    pub fn get(&self, telemetry: Telemetry) -> Option<u16> {
+        match telemetry {
+            Telemetry::Frequency => self.frequency,
+            Telemetry::Current => self.current,
+            Telemetry::Voltage => self.voltage,
+            Telemetry::Fault => self.fault,
+        }
+    }
+
+    pub fn set(&mut self, telemetry: Telemetry, value: u16) {
+        let field = match telemetry {
+            Telemetry::Frequency => &mut self.frequency,
+            Telemetry::Current => &mut self.current,
+            Telemetry::Voltage => &mut self.voltage,
+            Telemetry::Fault => &mut self.fault,
+        };
+        *field = Some(value);
+    }
+
+    /// Update with the values read in `other`.
+    pub fn merge(&mut self, other: VfdTelemetry) {
+        for t in Telemetry::ALL {
+            if let Some(value) = other.get(t) {
+                self.set(t, value);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// State of a Vfd published to subscribers: its status and telemetry, the progress of its
+/// reference ramp (`None` if the Vfd has no ramp), and whether the drive does not follow its
+/// command (see `ReconcileConfig`).
+pub struct VfdState {
+    pub status: VfdStatus,
+    pub telemetry: VfdTelemetry,
+    pub ramp: Option<RampProgress>,
+    pub not_following: bool,
+}
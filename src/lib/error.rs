@@ -1,7 +1,35 @@
+use std::fmt::{Display, Formatter};
+use crate::modbus::ModbusId;
+
 #[allow(unused)]
-#[derive(Debug)]
-pub enum VfdError {
-    ChannelAllReadyConnected,
+#[derive(Debug, Clone, PartialEq)]
+/// Errors of the modbus router library.
+///
+/// ## Variants
+/// - `PollerAlreadyConnected`: The device is already connected to a poller.
+/// - `RouterAlreadyConnected`: The device is already connected to a router.
+/// - `IdAlreadyRegistered(ModbusId)`: A device with the same id is already registered
+///   on the poller or router.
+/// - `PollerNotConnected`: The device is not connected to a poller.
+/// - `RouterNotConnected`: The device is not connected to a router.
+/// - `UnknownDevice(ModbusId)`: No device with this id is connected to the router.
+/// - `Timeout`: The device did not answer in time.
+/// - `RouterStopped`: The router is not running anymore.
+/// - `Wrong*`: An external request frame cannot be decoded.
+/// - `InvalidRegisterMap(String)`: A register map description is invalid.
+/// - `InvalidVfdProfile(String)`: A Vfd profile description is invalid, or cannot be read.
+/// - `UnknownVfdProfile(String)`: No Vfd profile with this name.
+/// - `InvalidNameplate(String)`: A Vfd nameplate is invalid.
+/// - `InvalidGroup(String)`: A drive group is invalid.
+pub enum Error {
+    PollerAlreadyConnected,
+    RouterAlreadyConnected,
+    IdAlreadyRegistered(ModbusId),
+    PollerNotConnected,
+    RouterNotConnected,
+    UnknownDevice(ModbusId),
+    Timeout,
+    RouterStopped,
     WrongFrameLength,
     WrongCrc,
     WrongFrameType,
@@ -9,5 +37,48 @@ pub enum VfdError {
     WrongRefValue,
     WrongRefSign,
     WrongModbusId,
+    WrongFlag,
     NotImplemented,
+    InvalidRegisterMap(String),
+    InvalidVfdProfile(String),
+    UnknownVfdProfile(String),
+    InvalidNameplate(String),
+    InvalidGroup(String),
 }
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::PollerAlreadyConnected => write!(f, "device already connected to a poller"),
+            Error::RouterAlreadyConnected => write!(f, "device already connected to a router"),
+            Error::IdAlreadyRegistered(id) => {
+                let id: u8 = (*id).into();
+                write!(f, "a device with id {} is already registered", id)
+            }
+            Error::PollerNotConnected => write!(f, "device not connected to a poller"),
+            Error::RouterNotConnected => write!(f, "device not connected to a router"),
+            Error::UnknownDevice(id) => {
+                let id: u8 = (*id).into();
+                write!(f, "no device with id {}", id)
+            }
+            Error::Timeout => write!(f, "device response timeout"),
+            Error::RouterStopped => write!(f, "router stopped"),
+            Error::WrongFrameLength => write!(f, "wrong frame length"),
+            Error::WrongCrc => write!(f, "wrong crc"),
+            Error::WrongFrameType => write!(f, "wrong frame type"),
+            Error::WrongFunctionType => write!(f, "wrong function type"),
+            Error::WrongRefValue => write!(f, "wrong reference value"),
+            Error::WrongRefSign => write!(f, "wrong reference sign"),
+            Error::WrongModbusId => write!(f, "wrong modbus id"),
+            Error::WrongFlag => write!(f, "wrong flag value"),
+            Error::NotImplemented => write!(f, "not implemented"),
+            Error::InvalidRegisterMap(e) => write!(f, "invalid register map: {}", e),
+            Error::InvalidVfdProfile(e) => write!(f, "invalid vfd profile: {}", e),
+            Error::UnknownVfdProfile(name) => write!(f, "no vfd profile named {}", name),
+            Error::InvalidNameplate(e) => write!(f, "invalid nameplate: {}", e),
+            Error::InvalidGroup(e) => write!(f, "invalid group: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use crate::batch::SyncGroup;
+use crate::error::Error;
+use crate::modbus::ModbusId;
+use crate::traits::request::{RequestFn, ResponseFn};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A named group of drives that start and change speed together.
+///
+/// A request to the group `id` is fanned out by the router to every member, the reference of
+/// a run request is scaled by the ratio of the member. The members on a same port write their
+/// setpoints back-to-back (see `SyncGroup`), or in a single broadcast frame if `broadcast` is
+/// set and every device of the port is a member with the same setpoint.
+///
+/// Fields:
+/// - `id`: The id the group is addressed with, it must not be the id of a device.
+/// - `name`: The name of the group, for logs.
+/// - `members`: The id of each member, and the ratio applied to its reference.
+/// - `broadcast`: Allow the members to be written in a broadcast frame.
+pub struct DriveGroup {
+    pub id: ModbusId,
+    pub name: String,
+    pub members: Vec<(ModbusId, f32)>,
+    pub broadcast: bool,
+}
+
+impl DriveGroup {
+    pub fn new(id: ModbusId, name: &str) -> Self {
+        DriveGroup {
+            id,
+            name: name.to_string(),
+            members: vec![],
+            broadcast: false,
+        }
+    }
+
+    /// Add a member, its reference is the group reference times `ratio`.
+    pub fn member(mut self, id: ModbusId, ratio: f32) -> Self {
+        self.members.push((id, ratio));
+        self
+    }
+
+    /// Allow the members to be written in a broadcast frame.
+    pub fn broadcast(mut self, broadcast: bool) -> Self {
+        self.broadcast = broadcast;
+        self
+    }
+
+    /// Return the `SyncGroup` the members requests of the command `seq` are sent with.
+    pub fn sync(&self, seq: u16) -> SyncGroup {
+        SyncGroup {
+            id: self.id,
+            seq,
+            broadcast: self.broadcast,
+        }
+    }
+
+    /// Check the group against the ids of the devices of the router.
+    fn validate(&self, devices: &[ModbusId]) -> Result<(), Error> {
+        if !matches!(self.id, ModbusId::Id(_)) {
+            return Err(Error::WrongModbusId);
+        }
+        if devices.contains(&self.id) {
+            return Err(Error::IdAlreadyRegistered(self.id));
+        }
+        if self.members.is_empty() {
+            return Err(Error::InvalidGroup(format!("{} has no member", self.name)));
+        }
+        for (i, (id, ratio)) in self.members.iter().enumerate() {
+            if !devices.contains(id) {
+                let id: u8 = (*id).into();
+                return Err(Error::InvalidGroup(format!("{}: no device with id {}", self.name, id)));
+            }
+            if self.members[..i].iter().any(|(other, _)| other == id) {
+                let id: u8 = (*id).into();
+                return Err(Error::InvalidGroup(format!("{}: device {} is a member twice", self.name, id)));
+            }
+            if !ratio.is_finite() {
+                return Err(Error::InvalidGroup(format!("{}: invalid ratio {}", self.name, ratio)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Report of a group member: whether it applied the group command `seq`.
+pub struct GroupReport {
+    pub group: ModbusId,
+    pub seq: u16,
+    pub member: ModbusId,
+    pub applied: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+/// Progress of the last command `seq` of a group: the members that did not report yet, and the
+/// count of members that did not apply it.
+struct Completion {
+    seq: u16,
+    pending: Vec<ModbusId>,
+    failed: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+/// The `DriveGroup`s of a router: fan out the group requests to the members, and gather the
+/// members reports into a single group completion response.
+pub struct Groups {
+    groups: Vec<DriveGroup>,
+    completions: HashMap<ModbusId, Completion>,
+    seq: u16,
+}
+
+impl Groups {
+    /// Add a group, `devices` are the ids of the devices of the router.
+    pub fn add(&mut self, group: DriveGroup, devices: &[ModbusId]) -> Result<(), Error> {
+        group.validate(devices)?;
+        if self.contains(group.id) {
+            return Err(Error::IdAlreadyRegistered(group.id));
+        }
+        log::info!("Group {} ({:?}) members: {:?}", group.name, group.id, group.members);
+        self.groups.push(group);
+        Ok(())
+    }
+
+    /// Return true if `id` is the id of a group.
+    pub fn contains(&self, id: ModbusId) -> bool {
+        self.groups.iter().any(|g| g.id == id)
+    }
+
+    /// Return the requests to send to the members if `request` is sent to a group, `None` if
+    /// it is not. A request the group does not support fails with `WrongFunctionType`, a
+    /// request that fails for a member (e.g. its scaled reference is out of range) is sent to
+    /// no member. The previous command of the group is not reported anymore.
+    pub fn fan_out<R: RequestFn>(&mut self, request: &R) -> Option<Result<Vec<R>, Error>> {
+        let group = self.groups.iter().find(|g| g.id == request.id())?;
+        let seq = self.seq.wrapping_add(1);
+        let requests: Result<Vec<R>, Error> = group.members.iter()
+            .filter_map(|(id, ratio)| request.for_member(*id, group.sync(seq), *ratio))
+            .map(|r| r.map(|r| *r))
+            .collect();
+        let requests = match requests {
+            Ok(requests) => requests,
+            Err(e) => {
+                log::error!("Group {} rejects {:?}: {}", group.name, request, e);
+                return Some(Err(e));
+            }
+        };
+        if requests.is_empty() {
+            log::error!("Group {} does not support {:?}", group.name, request);
+            return Some(Err(Error::WrongFunctionType));
+        }
+        self.seq = seq;
+        let pending = requests.iter().map(|r| r.id()).collect();
+        self.completions.insert(group.id, Completion { seq, pending, failed: 0 });
+        Some(Ok(requests))
+    }
+
+    /// Return the response to send to the client: a member report is consumed, and the group
+    /// completion is returned once every member reported. Reports of a previous command of the
+    /// group are ignored.
+    pub fn filter_response<S: ResponseFn>(&mut self, response: S) -> Option<S> {
+        let Some(GroupReport { group, seq, member, applied }) = response.group_report() else {
+            return Some(response);
+        };
+        let completion = self.completions.get_mut(&group).filter(|c| c.seq == seq)?;
+        let i = completion.pending.iter().position(|id| *id == member)?;
+        completion.pending.remove(i);
+        if !applied {
+            completion.failed = completion.failed.saturating_add(1);
+        }
+        if !completion.pending.is_empty() {
+            return None;
+        }
+        let failed = completion.failed;
+        self.completions.remove(&group);
+        let members = self.groups.iter()
+            .find(|g| g.id == group)
+            .map_or(0, |g| g.members.len().min(u8::MAX as usize) as u8);
+        S::group_done(group, failed, members)
+    }
+}
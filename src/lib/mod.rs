@@ -1,13 +1,21 @@
+// allow `#[derive(DeviceConnectors)]` to refer to this crate as `lib` from inside it
+extern crate self as lib;
+
 pub mod error;
 pub mod poller;
 pub mod router;
+pub mod channel_router;
 pub mod batch;
+pub mod group;
 pub mod traits;
 pub mod devices;
 pub mod modbus;
 pub mod soft_request;
+pub mod state;
+#[cfg(feature = "simulator")]
+pub mod simulator;
 
 pub mod async_stdin;
 
-
-
+#[doc(hidden)]
+pub use log;
@@ -1,3 +1,10 @@
+use modbus_core::codec::Encode;
+use modbus_core::rtu::crc16;
+use modbus_core::Request;
+
+/// Bit set on the function code of a response frame when the slave answers with an exception.
+pub const EXCEPTION_FLAG: u8 = 0x80;
+
 
 #[allow(unused)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,11 +28,19 @@ pub enum FrameType {
 /// - `Run`: Represents a command to run or execute an operation.
 /// - `Stop`: Represents a command to stop an operation.
 /// - `Status`: Represents a request or response pertaining to the status.
+/// - `Reset`: Represents a command to reset a fault.
+/// - `ReadParameter`: Represents a request to read drive parameters.
+/// - `WriteParameter`: Represents a request to write a drive parameter.
+/// - `Polling`: Represents a request to switch the status polling and updates of a drive.
 /// - `None`: Indicates no specific function, used for uninitialized or default states.
 pub enum FunctionType {
     Run,
     Stop,
     Status,
+    Reset,
+    ReadParameter,
+    WriteParameter,
+    Polling,
     None,
 }
 
@@ -64,4 +79,109 @@ impl Into<u8> for ModbusId {
             ModbusId::Reserved => 255,
         }
     }
-}
\ No newline at end of file
+}
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// Represents a Modbus exception code, returned by a slave instead of a regular response
+/// when it cannot process a request.
+///
+/// An exception frame is 5 bytes long: `[MODBUS_ID, FUNCTION_CODE | 0x80, EXCEPTION_CODE, CRC, CRC]`.
+///
+/// ## Variants
+/// - `IllegalFunction`: The function code is not supported by the slave (1).
+/// - `IllegalDataAddress`: The register address is not valid for the slave (2).
+/// - `IllegalDataValue`: The value is not allowed by the slave (3).
+/// - `ServerDeviceFailure`: Unrecoverable error while processing the request (4).
+/// - `Acknowledge`: The request has been accepted but needs a long time to process (5).
+/// - `ServerDeviceBusy`: The slave is busy, the request should be retried later (6).
+/// - `MemoryParityError`: Parity error while reading extended memory (8).
+/// - `GatewayPathUnavailable`: Gateway misconfigured or overloaded (10).
+/// - `GatewayTargetDevice`: No response from the device behind a gateway (11).
+/// - `Unknown(u8)`: Any other exception code.
+pub enum ModbusException {
+    IllegalFunction,
+    IllegalDataAddress,
+    IllegalDataValue,
+    ServerDeviceFailure,
+    Acknowledge,
+    ServerDeviceBusy,
+    MemoryParityError,
+    GatewayPathUnavailable,
+    GatewayTargetDevice,
+    Unknown(u8),
+}
+
+impl ModbusException {
+    /// Try to parse an exception from a raw RTU frame (Modbus id and CRC included).
+    ///
+    /// Returns `None` if the frame is not a valid exception frame.
+    pub fn from_frame(frame: &[u8]) -> Option<Self> {
+        if frame.len() != 5 || (frame[1] & EXCEPTION_FLAG) == 0 {
+            return None;
+        }
+        let crc = crc16(&frame[..3]);
+        if frame[3..] != [((crc & 0xff00) >> 8) as u8, (crc & 0x00ff) as u8] {
+            log::error!("ModbusException::from_frame({:?}) wrong CRC!", frame);
+            return None;
+        }
+        Some(frame[2].into())
+    }
+
+    /// Return true if the request that triggered this exception can be sent again later,
+    /// the other exceptions will always fail for the same request.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ModbusException::Acknowledge
+                | ModbusException::ServerDeviceBusy
+                | ModbusException::GatewayPathUnavailable
+                | ModbusException::GatewayTargetDevice
+        )
+    }
+}
+
+impl From<u8> for ModbusException {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => ModbusException::IllegalFunction,
+            0x02 => ModbusException::IllegalDataAddress,
+            0x03 => ModbusException::IllegalDataValue,
+            0x04 => ModbusException::ServerDeviceFailure,
+            0x05 => ModbusException::Acknowledge,
+            0x06 => ModbusException::ServerDeviceBusy,
+            0x08 => ModbusException::MemoryParityError,
+            0x0a => ModbusException::GatewayPathUnavailable,
+            0x0b => ModbusException::GatewayTargetDevice,
+            code => ModbusException::Unknown(code),
+        }
+    }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<u8> for ModbusException {
+    fn into(self) -> u8 {
+        match self {
+            ModbusException::IllegalFunction => 0x01,
+            ModbusException::IllegalDataAddress => 0x02,
+            ModbusException::IllegalDataValue => 0x03,
+            ModbusException::ServerDeviceFailure => 0x04,
+            ModbusException::Acknowledge => 0x05,
+            ModbusException::ServerDeviceBusy => 0x06,
+            ModbusException::MemoryParityError => 0x08,
+            ModbusException::GatewayPathUnavailable => 0x0a,
+            ModbusException::GatewayTargetDevice => 0x0b,
+            ModbusException::Unknown(code) => code,
+        }
+    }
+}
+
+/// Build a RTU frame `[MODBUS_ID, PDU.., CRC, CRC]` from a Modbus request.
+pub fn rtu_frame(id: ModbusId, request: Request) -> Vec<u8> {
+    let mut frame: Vec<u8> = vec![id.into()];
+    let bytes = &mut [0; 256];
+    let len = request.encode(bytes).expect("frame fit in buffer");
+    frame.extend_from_slice(&bytes[..len]);
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&[((crc & 0xff00) >> 8) as u8, (crc & 0x00ff) as u8]);
+    frame
+}
@@ -19,7 +19,7 @@ pub struct ModbusPoller<DeviceRequest, DeviceResponse>
     serial_receiver: Receiver<SerialMessage>,
     receiver: Receiver<Batch<DeviceRequest, DeviceResponse>>,
     connector: Sender<Batch<DeviceRequest, DeviceResponse>>,
-    senders: HashMap<ModbusId, Sender<PollerMessage<DeviceResponse>>>,
+    senders: HashMap<ModbusId, Sender<PollerMessage<DeviceRequest, DeviceResponse>>>,
     frame_silence: Option<u64>,
     device_silence: Option<u64>,
     timeout: Option<u64>,
@@ -72,13 +72,35 @@ impl<DeviceRequest, DeviceResponse> ModbusPoller<DeviceRequest, DeviceResponse>
             .sender(serial_sender)
             .bauds(bauds);
 
+        let mut poller = Self::with_channels(
+            port, poller_sender, poller_receiver, frame_silence, device_silence, timeout);
+        poller.serial_port = Some(serial_port);
+        poller
+    }
+
+    /// Constructs a new `Poller` talking to an already running serial thread (or a simulated
+    /// bus) through the given channels.
+    ///
+    /// Parameters:
+    /// - `port`: The name of the serial port.
+    /// - `serial_sender`: Channel sender for sending `SerialMessage` to the serial thread.
+    /// - `serial_receiver`: Channel receiver for receiving `SerialMessage` from the serial thread.
+    /// - `frame_silence`: Optional duration of silence after each frame.
+    /// - `device_silence`: Optional duration of silence after each device communication.
+    /// - `timeout`: Optional timeout for the serial communication.
+    pub fn with_channels(port: &str,
+                         serial_sender: Sender<SerialMessage>,
+                         serial_receiver: Receiver<SerialMessage>,
+                         frame_silence: Option<u64>,
+                         device_silence: Option<u64>,
+                         timeout: Option<u64>) -> Self {
         let (connector, receiver) = channel();
 
         ModbusPoller {
             port: port.to_string(),
-            serial_port: Some(serial_port),
-            serial_sender: poller_sender,
-            serial_receiver: poller_receiver,
+            serial_port: None,
+            serial_sender,
+            serial_receiver,
             receiver,
             connector,
             senders: HashMap::new(),
@@ -168,7 +190,7 @@ impl<DeviceRequest, DeviceResponse> Polling<DeviceRequest, DeviceResponse> for M
         self.senders.clone().into_keys().collect()
     }
 
-    fn send_to_device(&mut self, id: ModbusId, msg: PollerMessage<DeviceResponse>) {
+    fn send_to_device(&mut self, id: ModbusId, msg: PollerMessage<DeviceRequest, DeviceResponse>) {
         if let Some(sender) = self.senders.get_mut(&id) {
             log::debug!("ModbusPoller.send_to_device() {:?} to device {} ", msg, {let i: u8 = id.into(); i});
             let _ = sender.send(msg);
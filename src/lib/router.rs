@@ -3,6 +3,7 @@ use std::fmt::Debug;
 use std::io::{Stdin, Stdout, Write};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use modbus_core::rtu::crc16;
+use crate::group::Groups;
 use crate::modbus::ModbusId;
 use crate::traits::request::{RequestFn, ResponseFn};
 use crate::traits::routing::{RouterConnector, Routing};
@@ -16,7 +17,8 @@ pub const FRAME_LENGTH: usize = 8;
 /// Manages routing of PLC requests and responses between external processes and modbus devices.
 ///
 /// `StdRouter` listens for incoming requests from an external process via `stdin` and dispatches
-/// these requests to the appropriate modbus devices based on their Modbus ID. It also handles
+/// these requests to the appropriate modbus devices based on their logical Modbus ID (that
+/// can differ from the slave id of the device on its bus, see `Device::connect_poller_as()`). It also handles
 /// broadcasting requests and dispatching responses back to the external process through `stdout`.
 pub struct StdRouter<Request, Response> {
     stdin: StdinChannel<u8>,
@@ -25,6 +27,7 @@ pub struct StdRouter<Request, Response> {
     receiver: Receiver<Response>,
     connector: Sender<Response>,
     senders: HashMap<ModbusId, Sender<Request>>,
+    groups: Groups,
 }
 
 impl<Request, Response> StdRouter<Request, Response>
@@ -49,6 +52,7 @@ impl<Request, Response> StdRouter<Request, Response>
             receiver,
             connector,
             senders: Default::default(),
+            groups: Default::default(),
         }
     }
 
@@ -108,8 +112,11 @@ impl<Request, Response> Routing<Request, Response> for StdRouter<Request, Respon
     ///
     /// # Returns
     /// * `Ok(RouterConnector)` if a new connector was created or retrieved successfully.
-    /// * `Err(Error)` if a connector for the specified ID already exists.
+    /// * `Err(Error)` if a connector for the specified ID (or a group) already exists.
     fn get_connector(&mut self, id: ModbusId) -> Option<RouterConnector<Request, Response>> {
+        if self.groups.contains(id) {
+            return None;
+        }
         if let std::collections::hash_map::Entry::Vacant(e) = self.senders.entry(id) {
             let (sender, receiver) = channel();
             e.insert(sender);
@@ -159,5 +166,9 @@ impl<Request, Response> Routing<Request, Response> for StdRouter<Request, Respon
     fn try_receive_response(&mut self) -> Option<Response> {
         self.receiver.try_recv().ok()
     }
+
+    fn groups(&mut self) -> &mut Groups {
+        &mut self.groups
+    }
     
 }
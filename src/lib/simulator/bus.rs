@@ -0,0 +1,95 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serial_thread::SerialMessage;
+use tokio::time::sleep;
+use crate::simulator::SharedSimulator;
+
+/// Default delay before a request without response is reported as `NoResponse`.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Request frames sent on a `SimulatedBus`, in sending order.
+pub type FrameLog = Arc<Mutex<Vec<Vec<u8>>>>;
+
+#[derive(Debug)]
+/// Serves a `Simulator` in memory, in place of the serial thread of a poller.
+///
+/// The poller is built with the channels returned by `SimulatedBus::channels()` (see
+/// `ModbusPoller::with_channels()`), every request frame it sends is logged.
+pub struct SimulatedBus {
+    simulator: SharedSimulator,
+    sender: Sender<SerialMessage>,
+    receiver: Receiver<SerialMessage>,
+    channels: Option<(Sender<SerialMessage>, Receiver<SerialMessage>)>,
+    frames: FrameLog,
+    timeout: Duration,
+}
+
+impl SimulatedBus {
+    pub fn new(simulator: SharedSimulator) -> Self {
+        let (poller_sender, receiver) = channel();
+        let (sender, poller_receiver) = channel();
+        SimulatedBus {
+            simulator,
+            sender,
+            receiver,
+            channels: Some((poller_sender, poller_receiver)),
+            frames: Default::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Take the channels the poller uses to talk to the bus, None if already taken.
+    pub fn channels(&mut self) -> Option<(Sender<SerialMessage>, Receiver<SerialMessage>)> {
+        self.channels.take()
+    }
+
+    /// Return the log of the request frames sent on the bus.
+    pub fn frames(&self) -> FrameLog {
+        self.frames.clone()
+    }
+
+    /// Starts the bus loop in a new thread.
+    pub fn start(mut self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    fn send(&self, msg: SerialMessage) {
+        if self.sender.send(msg).is_err() {
+            log::debug!("SimulatedBus: poller disconnected");
+        }
+    }
+
+    async fn run(&mut self) {
+        loop {
+            while let Ok(msg) = self.receiver.try_recv() {
+                match msg {
+                    SerialMessage::Connect => self.send(SerialMessage::Connected(true)),
+                    SerialMessage::SetTimeout(timeout) => self.timeout = timeout,
+                    SerialMessage::Send(frame) => {
+                        self.frames.lock().expect("frame log lock poisoned").push(frame.clone());
+                        let reply = self.simulator.lock()
+                            .expect("simulator lock poisoned")
+                            .handle_frame(&frame);
+                        match reply {
+                            Some(reply) if reply.delay.is_none_or(|d| d < self.timeout) => {
+                                if let Some(delay) = reply.delay {
+                                    sleep(delay).await;
+                                }
+                                self.send(SerialMessage::Receive(reply.frame));
+                            }
+                            _ => {
+                                sleep(self.timeout).await;
+                                self.send(SerialMessage::NoResponse);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            sleep(Duration::from_nanos(10)).await;
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+use crate::devices::joystick::device::JoystickType;
+use crate::modbus::ModbusException;
+use crate::simulator::Slave;
+
+/// First register of the joystick axes.
+pub const AXES_ADDRESS: u16 = 0x4001;
+
+/// Axis position at rest.
+pub const CENTER: u16 = 0x8000;
+
+#[derive(Debug, Clone, Copy)]
+/// A simulated joystick, with 4 axes (`Joystick`) or 4 axes and a thumb button
+/// (`JoystickWithThumb`) holding registers from `AXES_ADDRESS`.
+///
+/// If a sweep period is set, the X & Y axes move back and forth over their full range.
+pub struct JoystickSlave {
+    joystick_type: JoystickType,
+    positions: [u16; 5],
+    sweep: Option<Duration>,
+    elapsed: Duration,
+}
+
+impl JoystickSlave {
+    pub fn new(joystick_type: JoystickType) -> Self {
+        JoystickSlave {
+            joystick_type,
+            positions: [CENTER, CENTER, CENTER, CENTER, 0],
+            sweep: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Set the registers values.
+    pub fn positions(mut self, positions: [u16; 5]) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    /// Sweep the X & Y axes with the given period.
+    pub fn sweep(mut self, period: Duration) -> Self {
+        self.sweep = Some(period);
+        self
+    }
+
+    fn registers(&self) -> &[u16] {
+        match self.joystick_type {
+            JoystickType::Joystick => &self.positions[..4],
+            JoystickType::JoystickWithThumb => &self.positions,
+        }
+    }
+}
+
+impl Slave for JoystickSlave {
+    fn read_holding(&mut self, address: u16, quantity: u16) -> Result<Vec<u16>, ModbusException> {
+        let registers = self.registers();
+        let start = address.checked_sub(AXES_ADDRESS).ok_or(ModbusException::IllegalDataAddress)? as usize;
+        registers.get(start..start + quantity as usize)
+            .map(|r| r.to_vec())
+            .ok_or(ModbusException::IllegalDataAddress)
+    }
+
+    fn write(&mut self, _address: u16, _values: &[u16]) -> Result<(), ModbusException> {
+        Err(ModbusException::IllegalFunction)
+    }
+
+    fn tick(&mut self, elapsed: Duration) {
+        if let Some(period) = self.sweep.filter(|p| !p.is_zero()) {
+            let t = (self.elapsed + elapsed).as_secs_f32() % period.as_secs_f32();
+            self.elapsed = Duration::from_secs_f32(t);
+            // triangle wave over the full range
+            let phase = t / period.as_secs_f32();
+            let position = if phase < 0.5 { phase * 2.0 } else { 2.0 - phase * 2.0 };
+            let position = (position * u16::MAX as f32) as u16;
+            self.positions[0] = position;
+            self.positions[1] = u16::MAX - position;
+        }
+    }
+}
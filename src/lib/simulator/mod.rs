@@ -0,0 +1,213 @@
+//! Modbus RTU slaves simulator.
+//!
+//! The `Simulator` is transport agnostic: it decodes a request frame, dispatches it to the
+//! simulated slave and returns the response frame. A transport over a Linux pseudo-terminal
+//! is available in `pty`, and an in-memory transport (e.g. for tests) in `bus`.
+
+pub mod bus;
+pub mod joystick;
+pub mod pty;
+pub mod registers;
+pub mod vfd;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use modbus_core::codec::Encode;
+use modbus_core::rtu::crc16;
+use modbus_core::{Data, Request, Response};
+use tokio::time::Instant;
+use crate::modbus::{ModbusException, ModbusId, EXCEPTION_FLAG};
+
+/// A `Simulator` shared between its transport and the application (e.g. to inject faults).
+pub type SharedSimulator = Arc<Mutex<Simulator>>;
+
+/// A simulated Modbus slave.
+pub trait Slave: Send {
+    /// Read `quantity` holding registers from `address`.
+    fn read_holding(&mut self, address: u16, quantity: u16) -> Result<Vec<u16>, ModbusException>;
+
+    /// Read `quantity` input registers from `address`, default to `IllegalFunction`.
+    fn read_input(&mut self, _address: u16, _quantity: u16) -> Result<Vec<u16>, ModbusException> {
+        Err(ModbusException::IllegalFunction)
+    }
+
+    /// Write `values` to consecutive holding registers from `address`.
+    fn write(&mut self, address: u16, values: &[u16]) -> Result<(), ModbusException>;
+
+    /// Update the slave state, `elapsed` is the time since the last update.
+    fn tick(&mut self, _elapsed: Duration) {}
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Faults injected on a simulated slave.
+///
+/// Fields:
+/// - `no_response`: The slave never answers.
+/// - `drop_every`: Every nth request is not answered.
+/// - `exception`: The slave answers every request with this exception.
+/// - `exception_address`: If set, only the requests to this (first) register are answered with
+///   `exception`.
+/// - `delay`: Delay before the slave answers.
+/// - `bad_crc`: The slave answers with a wrong CRC.
+pub struct Faults {
+    pub no_response: bool,
+    pub drop_every: Option<u32>,
+    pub exception: Option<ModbusException>,
+    pub exception_address: Option<u16>,
+    pub delay: Option<Duration>,
+    pub bad_crc: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A response frame, and the delay to wait before sending it.
+pub struct Reply {
+    pub frame: Vec<u8>,
+    pub delay: Option<Duration>,
+}
+
+struct SimulatedSlave {
+    slave: Box<dyn Slave>,
+    faults: Faults,
+    requests: u32,
+}
+
+/// A set of simulated slaves sharing a bus.
+pub struct Simulator {
+    slaves: HashMap<u8, SimulatedSlave>,
+    last_tick: Instant,
+}
+
+impl std::fmt::Debug for Simulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut ids: Vec<_> = self.slaves.keys().collect();
+        ids.sort();
+        f.debug_struct("Simulator").field("slaves", &ids).finish()
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Simulator {
+            slaves: HashMap::new(),
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Add a slave with the given id, replacing the previous one if any.
+    pub fn slave(mut self, id: u8, slave: impl Slave + 'static) -> Self {
+        self.slaves.insert(id, SimulatedSlave {
+            slave: Box::new(slave),
+            faults: Faults::default(),
+            requests: 0,
+        });
+        self
+    }
+
+    /// Return the faults of the slave with the given id, to be modified.
+    pub fn faults(&mut self, id: u8) -> Option<&mut Faults> {
+        self.slaves.get_mut(&id).map(|s| &mut s.faults)
+    }
+
+    /// Wrap the simulator to be shared with its transport.
+    pub fn shared(self) -> SharedSimulator {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// Update the state of all slaves.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick);
+        self.last_tick = now;
+        for s in self.slaves.values_mut() {
+            s.slave.tick(elapsed);
+        }
+    }
+
+    /// Handle a request frame, return the response frame, or None if no slave answers (e.g. to a
+    /// broadcast).
+    pub fn handle_frame(&mut self, frame: &[u8]) -> Option<Reply> {
+        self.tick();
+        if frame.len() < 4 || !check_crc(frame) {
+            log::debug!("Simulator.handle_frame() invalid frame {:?}", frame);
+            return None;
+        }
+        let id = frame[0];
+        let function = frame[1];
+        if ModbusId::from(id) == ModbusId::Broadcast {
+            // every slave executes a broadcast request, none answers
+            for s in self.slaves.values_mut().filter(|s| !s.faults.no_response) {
+                let _ = Self::dispatch(s.slave.as_mut(), &frame[1..frame.len() - 2]);
+            }
+            return None;
+        }
+        let s = self.slaves.get_mut(&id)?;
+        s.requests = s.requests.wrapping_add(1);
+        let faults = s.faults;
+        if faults.no_response || faults.drop_every.is_some_and(|n| n > 0 && s.requests % n == 0) {
+            return None;
+        }
+
+        let address = (frame.len() >= 6).then(|| u16::from_be_bytes([frame[2], frame[3]]));
+        let result = match faults.exception {
+            Some(exception) if faults.exception_address.is_none_or(|a| Some(a) == address) => Err(exception),
+            _ => Self::dispatch(s.slave.as_mut(), &frame[1..frame.len() - 2]),
+        };
+        let mut out = vec![id];
+        match result {
+            Ok(pdu) => out.extend(pdu),
+            Err(exception) => out.extend([function | EXCEPTION_FLAG, exception.into()]),
+        }
+        let crc = crc16(&out);
+        out.extend([((crc & 0xff00) >> 8) as u8, (crc & 0x00ff) as u8]);
+        if faults.bad_crc {
+            let last = out.len() - 1;
+            out[last] ^= 0xff;
+        }
+        Some(Reply { frame: out, delay: faults.delay })
+    }
+
+    /// Execute a request PDU on a slave, return the response PDU.
+    fn dispatch(slave: &mut dyn Slave, pdu: &[u8]) -> Result<Vec<u8>, ModbusException> {
+        let request = Request::try_from(pdu).map_err(|_| ModbusException::IllegalDataValue)?;
+        let mut buffer = [0u8; 256];
+        let mut data_buffer = [0u8; 256];
+        let len = match request {
+            Request::ReadHoldingRegisters(address, quantity) => {
+                let words = slave.read_holding(address, quantity)?;
+                let data = Data::from_words(&words, &mut data_buffer)
+                    .map_err(|_| ModbusException::IllegalDataValue)?;
+                Response::ReadHoldingRegisters(data).encode(&mut buffer)
+            }
+            Request::ReadInputRegisters(address, quantity) => {
+                let words = slave.read_input(address, quantity)?;
+                let data = Data::from_words(&words, &mut data_buffer)
+                    .map_err(|_| ModbusException::IllegalDataValue)?;
+                Response::ReadInputRegisters(data).encode(&mut buffer)
+            }
+            Request::WriteSingleRegister(address, value) => {
+                slave.write(address, &[value])?;
+                Response::WriteSingleRegister(address, value).encode(&mut buffer)
+            }
+            Request::WriteMultipleRegisters(address, data) => {
+                let words: Vec<u16> = data.into_iter().collect();
+                slave.write(address, &words)?;
+                Response::WriteMultipleRegisters(address, words.len() as u16).encode(&mut buffer)
+            }
+            _ => return Err(ModbusException::IllegalFunction),
+        };
+        let len = len.map_err(|_| ModbusException::ServerDeviceFailure)?;
+        Ok(buffer[..len].to_vec())
+    }
+}
+
+fn check_crc(frame: &[u8]) -> bool {
+    let crc = crc16(&frame[..frame.len() - 2]);
+    frame[frame.len() - 2..] == [((crc & 0xff00) >> 8) as u8, (crc & 0x00ff) as u8]
+}
@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::pty::openpty;
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use nix::unistd::ttyname;
+use crate::simulator::SharedSimulator;
+
+/// Default silence marking the end of a request frame.
+pub const DEFAULT_FRAME_SILENCE: Duration = Duration::from_millis(2);
+
+#[derive(Debug)]
+/// Serves a `Simulator` on a Linux pseudo-terminal pair, the poller opens the slave side as a
+/// regular serial port.
+pub struct PtySimulator {
+    master: File,
+    // kept open, so the master side does not fail while no poller is connected
+    _slave: OwnedFd,
+    path: PathBuf,
+    simulator: SharedSimulator,
+    frame_silence: Duration,
+}
+
+impl PtySimulator {
+    /// Open a new pseudo-terminal pair in raw mode.
+    pub fn new(simulator: SharedSimulator) -> std::io::Result<Self> {
+        let pty = openpty(None, None)?;
+        let mut termios = tcgetattr(&pty.slave)?;
+        cfmakeraw(&mut termios);
+        tcsetattr(&pty.slave, SetArg::TCSANOW, &termios)?;
+        let path = ttyname(&pty.slave)?;
+        Ok(PtySimulator {
+            master: File::from(pty.master),
+            _slave: pty.slave,
+            path,
+            simulator,
+            frame_silence: DEFAULT_FRAME_SILENCE,
+        })
+    }
+
+    /// Set the silence marking the end of a request frame.
+    pub fn frame_silence(mut self, silence: Duration) -> Self {
+        self.frame_silence = silence;
+        self
+    }
+
+    /// Return the path of the serial port to connect the poller to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Starts the simulator loop in a new thread.
+    pub fn start(mut self) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            if let Err(e) = self.run() {
+                log::error!("PtySimulator {:?} stopped: {}", self.path, e);
+            }
+        })
+    }
+
+    /// Read request frames (delimited by `frame_silence`) and write back the responses.
+    fn run(&mut self) -> std::io::Result<()> {
+        let timeout = PollTimeout::try_from(self.frame_silence).unwrap_or(PollTimeout::MAX);
+        let mut frame = vec![];
+        let mut buffer = [0u8; 256];
+        loop {
+            let ready = {
+                let mut fds = [PollFd::new(self.master.as_fd(), PollFlags::POLLIN)];
+                poll(&mut fds, if frame.is_empty() { PollTimeout::NONE } else { timeout })?
+            };
+            if ready > 0 {
+                let len = self.master.read(&mut buffer)?;
+                frame.extend_from_slice(&buffer[..len]);
+            } else if !frame.is_empty() {
+                let reply = self.simulator.lock()
+                    .expect("simulator lock poisoned")
+                    .handle_frame(&frame);
+                frame.clear();
+                if let Some(reply) = reply {
+                    if let Some(delay) = reply.delay {
+                        std::thread::sleep(delay);
+                    }
+                    self.master.write_all(&reply.frame)?;
+                }
+            }
+        }
+    }
+}
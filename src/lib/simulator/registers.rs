@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use crate::modbus::ModbusException;
+use crate::simulator::Slave;
+
+#[derive(Debug, Clone, Default)]
+/// A generic simulated slave (e.g. for a `RegisterDevice`), with the holding and input
+/// registers added with `holding()` and `input()`. Holding registers can be written, other
+/// addresses answer `IllegalDataAddress`.
+pub struct RegisterSlave {
+    holding: BTreeMap<u16, u16>,
+    input: BTreeMap<u16, u16>,
+}
+
+impl RegisterSlave {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a holding register at `address`, holding `value`.
+    pub fn holding(mut self, address: u16, value: u16) -> Self {
+        self.holding.insert(address, value);
+        self
+    }
+
+    /// Add an input register at `address`, holding `value`.
+    pub fn input(mut self, address: u16, value: u16) -> Self {
+        self.input.insert(address, value);
+        self
+    }
+}
+
+/// Read `quantity` consecutive registers from `address`.
+fn read(registers: &BTreeMap<u16, u16>, address: u16, quantity: u16) -> Result<Vec<u16>, ModbusException> {
+    (address..address.saturating_add(quantity))
+        .map(|a| registers.get(&a).copied().ok_or(ModbusException::IllegalDataAddress))
+        .collect()
+}
+
+impl Slave for RegisterSlave {
+    fn read_holding(&mut self, address: u16, quantity: u16) -> Result<Vec<u16>, ModbusException> {
+        read(&self.holding, address, quantity)
+    }
+
+    fn read_input(&mut self, address: u16, quantity: u16) -> Result<Vec<u16>, ModbusException> {
+        read(&self.input, address, quantity)
+    }
+
+    fn write(&mut self, address: u16, values: &[u16]) -> Result<(), ModbusException> {
+        // check all addresses before writing
+        read(&self.holding, address, values.len() as u16)?;
+        for (a, value) in (address..).zip(values) {
+            self.holding.insert(a, *value);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use crate::devices::vfd::encoder::VfdCommands;
+use crate::modbus::ModbusException;
+use crate::simulator::Slave;
+
+/// Default acceleration of a simulated drive, in engineering units per second.
+pub const DEFAULT_ACCELERATION: f32 = 2500.0;
+
+/// DC bus voltage of a simulated drive.
+pub const DC_BUS_VOLTAGE: u16 = 540;
+
+#[derive(Debug, Clone)]
+/// A simulated drive, using the registers and command values of a `VfdCommands` profile
+/// (e.g. `FRECON` or `MEGMEET`).
+///
+/// The speed ramps toward the reference (negative in reverse, zero when stopped or tripped) at a
+/// constant acceleration, the reference and status registers are converted with the encoding of
+/// the profile (speed is an engineering value). Telemetry registers of the profile return the speed magnitude (frequency), a tenth
+/// of it (current), `DC_BUS_VOLTAGE` (voltage) and the fault code. Writing the reset value
+/// of the profile clears the fault. Parameter registers (see `parameter()`) can be read and
+/// written.
+pub struct VfdSlave {
+    commands: VfdCommands,
+    acceleration: f32,
+    cmd: u16,
+    reference: u16,
+    speed: f32,
+    fault: u16,
+    parameters: BTreeMap<u16, u16>,
+}
+
+impl VfdSlave {
+    pub fn new(commands: VfdCommands) -> Self {
+        VfdSlave {
+            commands,
+            acceleration: DEFAULT_ACCELERATION,
+            cmd: commands.stop_value,
+            reference: 0,
+            speed: 0.0,
+            fault: 0,
+            parameters: BTreeMap::new(),
+        }
+    }
+
+    /// Start the drive tripped, with the fault `code`.
+    pub fn fault(mut self, code: u16) -> Self {
+        self.fault = code;
+        self
+    }
+
+    /// Add a parameter register at `address`, holding `value`.
+    pub fn parameter(mut self, address: u16, value: u16) -> Self {
+        self.parameters.insert(address, value);
+        self
+    }
+
+    /// Set the acceleration, in engineering units per second.
+    pub fn acceleration(mut self, acceleration: f32) -> Self {
+        self.acceleration = acceleration;
+        self
+    }
+
+    /// Return the speed the drive ramps toward.
+    fn target(&self) -> f32 {
+        let reference = self.commands.encoding.decode(self.reference).abs();
+        if self.fault != 0 {
+            0.0
+        } else if self.cmd == self.commands.fw_value {
+            reference
+        } else if self.cmd == self.commands.rv_value {
+            -reference
+        } else {
+            0.0
+        }
+    }
+
+    fn status(&self) -> u16 {
+        let encoding = self.commands.encoding;
+        encoding.encode(self.speed)
+            .or_else(|| encoding.encode(0.0))
+            .unwrap_or(0)
+    }
+
+    fn write_register(&mut self, address: u16, value: u16) -> Result<(), ModbusException> {
+        let c = self.commands;
+        if c.reset_register() == Some((address, value)) {
+            self.fault = 0;
+            Ok(())
+        } else if address == c.cmd_address {
+            if value == c.fw_value || value == c.rv_value || value == c.stop_value {
+                self.cmd = value;
+                Ok(())
+            } else {
+                Err(ModbusException::IllegalDataValue)
+            }
+        } else if address == c.ref_address {
+            self.reference = value;
+            Ok(())
+        } else if let Some(parameter) = self.parameters.get_mut(&address) {
+            *parameter = value;
+            Ok(())
+        } else {
+            Err(ModbusException::IllegalDataAddress)
+        }
+    }
+}
+
+impl Slave for VfdSlave {
+    fn read_holding(&mut self, address: u16, quantity: u16) -> Result<Vec<u16>, ModbusException> {
+        (address..address.saturating_add(quantity))
+            .map(|a| {
+                let c = self.commands;
+                let magnitude = self.speed.abs().round().min(u16::MAX as f32) as u16;
+                if a == c.status_address {
+                    Ok(self.status())
+                } else if Some(a) == c.frequency_address {
+                    Ok(magnitude)
+                } else if Some(a) == c.current_address {
+                    Ok(magnitude / 10)
+                } else if Some(a) == c.voltage_address {
+                    Ok(DC_BUS_VOLTAGE)
+                } else if Some(a) == c.fault_address {
+                    Ok(self.fault)
+                } else if a == c.cmd_address {
+                    Ok(self.cmd)
+                } else if a == c.ref_address {
+                    Ok(self.reference)
+                } else if let Some(value) = self.parameters.get(&a) {
+                    Ok(*value)
+                } else {
+                    Err(ModbusException::IllegalDataAddress)
+                }
+            })
+            .collect()
+    }
+
+    fn write(&mut self, address: u16, values: &[u16]) -> Result<(), ModbusException> {
+        // check all addresses before writing
+        let c = self.commands;
+        let reset_address = c.reset_register().map(|(a, _)| a);
+        let valid = |a: u16| {
+            a == c.cmd_address || a == c.ref_address || Some(a) == reset_address || self.parameters.contains_key(&a)
+        };
+        if !(0..values.len() as u16).all(|i| valid(address.wrapping_add(i))) {
+            return Err(ModbusException::IllegalDataAddress);
+        }
+        for (i, value) in values.iter().enumerate() {
+            self.write_register(address.wrapping_add(i as u16), *value)?;
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self, elapsed: Duration) {
+        let target = self.target();
+        let step = self.acceleration * elapsed.as_secs_f32();
+        if (target - self.speed).abs() <= step {
+            self.speed = target;
+        } else if target > self.speed {
+            self.speed += step;
+        } else {
+            self.speed -= step;
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use modbus_core::rtu::crc16;
-use crate::devices::vfd::requests::VfdStatus;
-use crate::error::VfdError;
-use crate::modbus::{FrameType, FunctionType, ModbusId};
+use crate::batch::SyncGroup;
+use crate::devices::vfd::requests::{ParameterBlock, Telemetry, VfdStatus};
+use crate::error::Error;
+use crate::group::GroupReport;
+use crate::modbus::{FrameType, FunctionType, ModbusException, ModbusId};
 use crate::traits::request::{RequestFn, ResponseFn};
 
 
@@ -20,56 +22,137 @@ use crate::traits::request::{RequestFn, ResponseFn};
 ///
 /// - `MODBUS_ID`:
 ///   - `0` -> Broadcast
-///   - `1-247` -> Device ID
+///   - `1-247` -> Device ID, or group ID (see `DriveGroup`): `Run` and `Stop` requests to a
+///     group are sent to every member, the reference scaled by the member ratio. A `Run` whose
+///     scaled reference is above `i16::MAX` for a member is rejected (`OutOfRange`) and sent to
+///     no member, other requests to a group are rejected (`NotAvailable`)
 ///   - `248-255` -> Reserved
 /// - `TYPE`:
 ///   - `1` -> Vfd Request 
 ///   - `2` -> Vfd Response 
 ///   - `3` -> Joystick Request
 ///   - `4` -> Joystick response
-/// - `Vfd FUNCTION_CODE` and corresponding data layout:
-///   - `1` -> Run: DATA1 = SIGN, DATA2 = Reference MSB, DATA3 = Reference LSB (encoded as i16 without sign)
-///   - `2` -> Stop: DATA1, DATA2, DATA3 = `0`
+///   - `5` -> Register map Request
+///   - `6` -> Register map Response
+/// - `Vfd FUNCTION_CODE` and corresponding data layout, references and statuses are in the unit
+///   configured for each drive (see `Vfd::nameplate()`), the frames do not carry a unit:
+///   - `1` -> Run: DATA1 = SIGN, DATA2 = Reference MSB, DATA3 = Reference LSB (encoded as i16 without sign),
+///     answered with the same function code and DATA1, DATA2, DATA3 = `0` once accepted, or
+///     with a `Rejected` error (see `RejectReason`), also sent by the router if the reference
+///     is above `i16::MAX`
+///   - `2` -> Stop: DATA1, DATA2, DATA3 = `0`, answered with the same function code and DATA1,
+///     DATA2, DATA3 = `0`
 ///   - `3` -> Status: DATA1, DATA2, DATA3 = `0`
+///   - `4` -> Frequency, `5` -> Current, `6` -> Voltage, `7` -> Fault code: read a telemetry
+///     value (see `Telemetry`), DATA1, DATA2, DATA3 = `0`, answered with the same function
+///     code: DATA1 = `0`, DATA2 = value MSB, DATA3 = value LSB (raw register value), or with a
+///     `Rejected` error if the drive does not provide this value
+///   - `8` -> Reset: reset a drive fault, DATA1, DATA2, DATA3 = `0`, answered with the same
+///     function code and DATA1, DATA2, DATA3 = `0` once queued, or with a `Rejected` error if
+///     the drive cannot be reset remotely or if a run command is pending
+///   - `9` -> Read parameter: read a block of any drive registers, DATA1 = quantity (1 to
+///     `MAX_PARAMETER_BLOCK`), DATA2 = address MSB, DATA3 = address LSB, answered with one
+///     frame per register, with the same function code: DATA1 = offset in the block, DATA2 =
+///     value MSB, DATA3 = value LSB. The address of the last block read is kept for the next
+///     writes.
+///   - `10` -> Write parameter: DATA1 = offset from the address of the last block read, DATA2 =
+///     value MSB, DATA3 = value LSB, answered with the same function code and data once
+///     written, or with a `Rejected` error if no parameter was read or if the register is not
+///     writable (see `Vfd::writable_parameters()`)
+///   - `11` -> Group done (responses only): every member of a group applied (or failed to
+///     apply) the last `Run` or `Stop` request of the group, DATA1 = count of members that did
+///     not apply it (`0` on success), DATA2 = count of members, DATA3 = `0`
+///   - `12` -> Polling: switch the status polling and the pushed status updates of the drive,
+///     DATA1 = poll status (`0` = off, `1` = on), DATA2 = push updates (`0` = off, `1` = on),
+///     DATA3 = `0`, answered with the same function code and the settings in use: DATA1 = poll
+///     status, DATA2 = push updates, DATA3 = `0`. Telemetry is read with the status.
 /// - `Joystick FUNCTION_CODE` and corresponding data layout:
 ///   - `1` -> X Position: DATA1 = SIGN, DATA2 = X Position MSB, DATA3 = X Position LSB (encoded 
 ///     as u16 without sign) 
 ///   - `2` -> Y Position: DATA1 = SIGN, DATA2 = Y Position MSB, DATA3 = Y Position LSB (encoded 
 ///     as u16 without sign) 
 ///   - `3` -> Button state: DATA1 = Button # , DATA2 = Button state ( `0` = released, `1` = pressed)
-///         state mask ( if mask bit is 1 => the relevant button bit should be updated ) DATA3 = `0`
+///     state mask ( if mask bit is 1 => the relevant button bit should be updated ) DATA3 = `0`
 ///   - `4` -> X Thumb Position: DATA1 = SIGN, DATA2 = X Thumb Position MSB, DATA3 = X Thumb 
 ///     Position LSB (encoded as u16 without sign) DATA3 = `0`
 ///   - `5` -> Y Thumb Position: DATA1 = SIGN, DATA2 = Y Thumb Position MSB, DATA3 = Y Thumb 
 ///     Position LSB (encoded as u16 without sign) DATA3 = `0`
+/// - `Register map FUNCTION_CODE` and corresponding data layout, points are addressed by their
+///   index in the register map, values are encoded as SIGN + u16 magnitude:
+///   - `1` -> Read point: DATA1 = point index, DATA2, DATA3 = `0`, answered by a `Read point`
+///     response: DATA1 = point index | SIGN << 7, DATA2 = value MSB, DATA3 = value LSB
+///   - `2` -> Write point: DATA1 = point index | SIGN << 7, DATA2 = value MSB, DATA3 = value LSB,
+///     answered by a `Read point` response once written
+/// - `Error FUNCTION_CODE` (responses only, all device types):
+///   - `0x80` -> Error: DATA1 = error kind, DATA2 = error detail, DATA3 = extra detail
+///     - kind `1` -> Modbus exception: DATA2 = exception code
+///     - kind `2` -> Communication failure, the request failed after all retry attempts: DATA2 = `0`
+///     - kind `3` -> Request rejected: DATA2 = reason (see `RejectReason`), DATA3 = id of the
+///       interlocking device for reason `8` (`0` otherwise)
+/// - `Event FUNCTION_CODE` (unsolicited responses only, all device types):
+///   - `0x81` -> Event: DATA1 = event kind, DATA2 = detail MSB, DATA3 = detail LSB
+///     - kind `1` -> Online, the device answers (again): detail = `0`
+///     - kind `2` -> Offline, a request failed after all retry attempts: detail = `0`
+///     - kind `3` -> Communication error burst: detail = count of consecutive failed attempts
+///     - kind `4` -> Port down, the serial port of the device is lost: detail = `0`
+///     - kind `5` -> Port up, the serial port of the device is (re)connected: detail = `0`
+///     - kind `6` -> Configured, the device (re)started with its configuration: detail = `0`
+///     - kind `7` -> Fault, the device reports a fault: detail = fault code (`0` if unknown)
+///     - kind `8` -> Not following, the drive does not follow its command after all rewrite
+///       attempts: detail = `0`
+///     - kind `9` -> Following, the drive follows its command again: detail = `0`
 ///
 /// ## Variants
-/// - `Run`: Contains a `ModbusId` and a reference as `i16`.
+/// - `Run`: Contains a `ModbusId` and a reference as `i16`, in the unit of the drive.
 /// - `Stop`: Contains a `ModbusId`.
 /// - `Status`: Contains a `ModbusId`.
+/// - `ReadPoint`: Contains a `ModbusId` and a point index, the position of the point in the
+///   `RegisterMap` of the device (see `RegisterMap::index_of()` to find it from its name).
+/// - `WritePoint`: Contains a `ModbusId`, a point index (as for `ReadPoint`) and a scaled value.
+/// - `Telemetry`: Contains a `ModbusId` and the `Telemetry` value to read.
+/// - `Reset`: Contains a `ModbusId`.
+/// - `ReadParameter`: Contains a `ModbusId`, a register address and a quantity of registers.
+/// - `WriteParameter`: Contains a `ModbusId`, an offset from the last parameter read and a value.
+/// - `Polling`: Contains a `ModbusId`, whether the status is polled and whether status updates
+///   are pushed.
+/// - `GroupRun`: Contains the `ModbusId` of a group member, its `SyncGroup` and its reference,
+///   fanned out by the router from a `Run` request to the group (never decoded from a frame).
+/// - `GroupStop`: Contains the `ModbusId` of a group member and its `SyncGroup`, fanned out by
+///   the router from a `Stop` request to the group (never decoded from a frame).
 pub enum SoftRequest {
     Run(ModbusId, i16),
     Stop(ModbusId),
     Status(ModbusId),
+    ReadPoint(ModbusId, u8),
+    WritePoint(ModbusId, u8, i32),
+    Telemetry(ModbusId, Telemetry),
+    Reset(ModbusId),
+    ReadParameter(ModbusId, u16, u8),
+    WriteParameter(ModbusId, u8, u16),
+    Polling(ModbusId, bool, bool),
+    GroupRun(ModbusId, SyncGroup, i16),
+    GroupStop(ModbusId, SyncGroup),
 }
 
 impl RequestFn for SoftRequest {
-    fn from(raw: Vec<u8>) -> Option<Box<Self>> {
-        match SoftRequest::try_from(raw.as_slice()) {
-            Ok(r) => {
-                Some(Box::new(r))
-            }
-            Err(e) => {
-                log::error!("Request.from<u8>({:?}) fail: {:?}", raw, e);
-                None
-            }
-        }
-        
+    fn from(raw: Vec<u8>) -> Result<Box<Self>, Error> {
+        SoftRequest::try_from(raw.as_slice()).map(Box::new)
     }
 
     fn id(&self) -> ModbusId {
         match self {
-            SoftRequest::Run(id, _) | SoftRequest::Stop(id) | SoftRequest::Status(id) => *id,
+            SoftRequest::Run(id, _)
+            | SoftRequest::Stop(id)
+            | SoftRequest::Status(id)
+            | SoftRequest::Reset(id)
+            | SoftRequest::ReadPoint(id, _)
+            | SoftRequest::WritePoint(id, _, _)
+            | SoftRequest::Telemetry(id, _)
+            | SoftRequest::ReadParameter(id, _, _)
+            | SoftRequest::WriteParameter(id, _, _)
+            | SoftRequest::Polling(id, _, _)
+            | SoftRequest::GroupRun(id, _, _)
+            | SoftRequest::GroupStop(id, _) => *id,
         }
     }
 
@@ -78,16 +161,42 @@ impl RequestFn for SoftRequest {
             SoftRequest::Run(_, r) => SoftRequest::Run(id, *r),
             SoftRequest::Stop(_) => SoftRequest::Stop(id),
             SoftRequest::Status(_) => SoftRequest::Status(id),
+            SoftRequest::ReadPoint(_, p) => SoftRequest::ReadPoint(id, *p),
+            SoftRequest::WritePoint(_, p, v) => SoftRequest::WritePoint(id, *p, *v),
+            SoftRequest::Telemetry(_, t) => SoftRequest::Telemetry(id, *t),
+            SoftRequest::Reset(_) => SoftRequest::Reset(id),
+            SoftRequest::ReadParameter(_, a, q) => SoftRequest::ReadParameter(id, *a, *q),
+            SoftRequest::WriteParameter(_, o, v) => SoftRequest::WriteParameter(id, *o, *v),
+            SoftRequest::Polling(_, s, u) => SoftRequest::Polling(id, *s, *u),
+            SoftRequest::GroupRun(_, g, r) => SoftRequest::GroupRun(id, *g, *r),
+            SoftRequest::GroupStop(_, g) => SoftRequest::GroupStop(id, *g),
         };
         Box::new(out)
     }
+
+    /// `Run` and `Stop` requests are sent to the members of a group, a `Run` fails with
+    /// `WrongRefValue` if the scaled reference does not fit an `i16` (as a `Run` frame).
+    fn for_member(&self, id: ModbusId, group: SyncGroup, ratio: f32) -> Option<Result<Box<Self>, Error>> {
+        let out = match self {
+            SoftRequest::Run(_, r) => {
+                let reference = (*r as f32 * ratio).round();
+                if reference.abs() > i16::MAX as f32 {
+                    return Some(Err(Error::WrongRefValue));
+                }
+                SoftRequest::GroupRun(id, group, reference as i16)
+            }
+            SoftRequest::Stop(_) => SoftRequest::GroupStop(id, group),
+            _ => return None,
+        };
+        Some(Ok(Box::new(out)))
+    }
 }
 
 impl TryFrom<&[u8]> for SoftRequest {
-    type Error = VfdError;
+    type Error = Error;
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
         if value.len() == 8 {
-            let frame: &[u8; 8] = value.try_into().map_err(|_| VfdError::WrongFrameLength)?;
+            let frame: &[u8; 8] = value.try_into().map_err(|_| Error::WrongFrameLength)?;
 
             // check for crc
             let crc = crc16(&frame[..frame.len()-2]);
@@ -98,14 +207,18 @@ impl TryFrom<&[u8]> for SoftRequest {
                 .try_into()
                 .expect("cannot fail");
             if crc != frame_crc {
-                return Err(VfdError::WrongCrc);
+                return Err(Error::WrongCrc);
             }
 
             // deserialize
             let id: ModbusId = frame[0].into();
 
             if id == ModbusId::Reserved {
-                return Err(VfdError::WrongModbusId);
+                return Err(Error::WrongModbusId);
+            }
+
+            if frame[1] == 0x05 {
+                return Self::point_request(id, frame);
             }
 
             let frame_type = match &frame[1] {
@@ -114,30 +227,38 @@ impl TryFrom<&[u8]> for SoftRequest {
                 _ => FrameType::None,
             };
             if (frame_type == FrameType::None) || (frame_type == FrameType::Response) {
-                return Err(VfdError::WrongFrameType);
+                return Err(Error::WrongFrameType);
+            }
+
+            if let Some(telemetry) = Telemetry::from_fn_code(frame[2]) {
+                return Ok(SoftRequest::Telemetry(id, telemetry));
             }
 
             let fn_type = match &frame[2] {
                 1 => FunctionType::Run,
                 2 => FunctionType::Stop,
                 3 => FunctionType::Status,
+                8 => FunctionType::Reset,
+                9 => FunctionType::ReadParameter,
+                10 => FunctionType::WriteParameter,
+                12 => FunctionType::Polling,
                 _ => FunctionType::None,
             };
 
+            let word = ((frame[4] as u16) << 8) | (frame[5] as u16);
             let mut run_ref = 0i16;
             if fn_type == FunctionType::Run {
-                let reference = ((frame[4] as u16) << 8) | (frame[5] as u16);
-                if reference > (i16::MAX as u16) {
-                    return Err(VfdError::WrongRefValue);
+                if word > (i16::MAX as u16) {
+                    return Err(Error::WrongRefValue);
                 }
-                let mut reference = reference as i16;
+                let mut reference = word as i16;
                 match frame[3] {
                     0 => {}
                     1 => {
                         reference = -reference;
                     }
                     _ => {
-                        return Err(VfdError::WrongRefSign);
+                        return Err(Error::WrongRefSign);
                     }
                 }
                 run_ref = reference;
@@ -147,16 +268,184 @@ impl TryFrom<&[u8]> for SoftRequest {
                 FunctionType::Run => Ok(SoftRequest::Run(id, run_ref)),
                 FunctionType::Status => Ok(SoftRequest::Status(id)),
                 FunctionType::Stop => Ok(SoftRequest::Stop(id)),
-                FunctionType::None => Err(VfdError::WrongFunctionType),
+                FunctionType::Reset => Ok(SoftRequest::Reset(id)),
+                FunctionType::ReadParameter => Ok(SoftRequest::ReadParameter(id, word, frame[3])),
+                FunctionType::WriteParameter => Ok(SoftRequest::WriteParameter(id, frame[3], word)),
+                FunctionType::Polling => match (frame[3], frame[4]) {
+                    (status @ (0 | 1), updates @ (0 | 1)) => {
+                        Ok(SoftRequest::Polling(id, status == 1, updates == 1))
+                    }
+                    _ => Err(Error::WrongFlag),
+                },
+                FunctionType::None => Err(Error::WrongFunctionType),
             }
         } else {
-            Err(VfdError::WrongFrameLength)
+            Err(Error::WrongFrameLength)
+        }
+    }
+}
+
+impl SoftRequest {
+    /// Deserialize a register map request frame.
+    fn point_request(id: ModbusId, frame: &[u8; 8]) -> Result<Self, Error> {
+        let index = frame[3] & 0x7f;
+        match frame[2] {
+            1 => Ok(SoftRequest::ReadPoint(id, index)),
+            2 => {
+                let mut value = (((frame[4] as u16) << 8) | (frame[5] as u16)) as i32;
+                if (frame[3] & 0x80) != 0 {
+                    value = -value;
+                }
+                Ok(SoftRequest::WritePoint(id, index, value))
+            }
+            _ => Err(Error::WrongFunctionType),
         }
     }
 }
 
 #[allow(unused)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Type of the device a response is sent from, select the `TYPE` byte of the frame.
+///
+/// ## Variants
+/// - `Vfd`: Response is sent with `TYPE` = `2`.
+/// - `Joystick`: Response is sent with `TYPE` = `4`.
+/// - `RegisterMap`: Response is sent with `TYPE` = `6`.
+pub enum DeviceType {
+    Vfd,
+    Joystick,
+    RegisterMap,
+}
+
+impl DeviceType {
+    pub fn response_type(&self) -> u8 {
+        match self {
+            DeviceType::Vfd => 2,
+            DeviceType::Joystick => 4,
+            DeviceType::RegisterMap => 6,
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error reported to the PLC Controller in an `Error` frame.
+///
+/// ## Variants
+/// - `Exception(ModbusException)`: The device answered with a Modbus exception (kind `1`).
+/// - `CommFailure`: The device did not answer (or with an invalid frame) after all retry
+///   attempts (kind `2`).
+/// - `Rejected(RejectReason)`: The request have been rejected by the device (kind `3`).
+pub enum SoftError {
+    Exception(ModbusException),
+    CommFailure,
+    Rejected(RejectReason),
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Reason of a rejected request, sent as detail of a `Rejected` error frame.
+///
+/// ## Variants
+/// - `UnknownPoint`: The point index does not exist in the register map (1).
+/// - `NotWritable`: The point is read only (2).
+/// - `OutOfRange`: The value is out of the allowed range (3).
+/// - `NotAvailable`: The value is not provided by the device, or not read yet (4).
+/// - `RunPending`: A run command is pending, the drive must be stopped first (5).
+/// - `DirectionForbidden`: The drive must never run in this direction (6).
+/// - `StopRequired`: The direction changes, the drive must be stopped for its dwell time
+///   first (7).
+/// - `Interlocked(ModbusId)`: The interlocking device is not running (8), also sent when a
+///   running drive is stopped because its interlocking device stopped.
+pub enum RejectReason {
+    UnknownPoint,
+    NotWritable,
+    OutOfRange,
+    NotAvailable,
+    RunPending,
+    DirectionForbidden,
+    StopRequired,
+    Interlocked(ModbusId),
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<u8> for RejectReason {
+    fn into(self) -> u8 {
+        match self {
+            RejectReason::UnknownPoint => 1,
+            RejectReason::NotWritable => 2,
+            RejectReason::OutOfRange => 3,
+            RejectReason::NotAvailable => 4,
+            RejectReason::RunPending => 5,
+            RejectReason::DirectionForbidden => 6,
+            RejectReason::StopRequired => 7,
+            RejectReason::Interlocked(_) => 8,
+        }
+    }
+}
+
+impl SoftError {
+    /// Return the (kind, detail, extra detail) of the error frame.
+    fn to_data(self) -> (u8, u8, u8) {
+        match self {
+            SoftError::Exception(e) => (1, e.into(), 0),
+            SoftError::CommFailure => (2, 0, 0),
+            SoftError::Rejected(RejectReason::Interlocked(id)) => {
+                (3, RejectReason::Interlocked(id).into(), id.into())
+            }
+            SoftError::Rejected(reason) => (3, reason.into(), 0),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Lifecycle event of a device, sent unsolicited to the PLC Controller in an `Event` frame.
+///
+/// ## Variants
+/// - `Online`: The device answers, for the first time or after being offline (kind `1`).
+/// - `Offline`: A request to the device failed after all retry attempts (kind `2`).
+/// - `CommErrorBurst(u16)`: Consecutive requests attempts failed, the device is not (yet)
+///   offline (kind `3`).
+/// - `PortDown`: The serial port of the device is lost (kind `4`).
+/// - `PortUp`: The serial port of the device is (re)connected (kind `5`).
+/// - `Configured`: The device (re)started with its configuration (kind `6`).
+/// - `Fault(u16)`: The device reports a fault, with its fault code or `0` if the device does
+///   not report one (kind `7`).
+/// - `NotFollowing`: The drive status does not converge to its command after all rewrite
+///   attempts (kind `8`).
+/// - `Following`: The drive follows its command again, after a `NotFollowing` (kind `9`).
+pub enum DeviceEvent {
+    Online,
+    Offline,
+    CommErrorBurst(u16),
+    PortDown,
+    PortUp,
+    Configured,
+    Fault(u16),
+    NotFollowing,
+    Following,
+}
+
+impl DeviceEvent {
+    /// Return the (kind, detail) pair of the event frame.
+    fn to_data(self) -> (u8, u16) {
+        match self {
+            DeviceEvent::Online => (1, 0),
+            DeviceEvent::Offline => (2, 0),
+            DeviceEvent::CommErrorBurst(count) => (3, count),
+            DeviceEvent::PortDown => (4, 0),
+            DeviceEvent::PortUp => (5, 0),
+            DeviceEvent::Configured => (6, 0),
+            DeviceEvent::Fault(code) => (7, code),
+            DeviceEvent::NotFollowing => (8, 0),
+            DeviceEvent::Following => (9, 0),
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 /// # `SoftResponse`
 ///
 /// This enum represents the response to the PLC Controller.
@@ -165,45 +454,162 @@ impl TryFrom<&[u8]> for SoftRequest {
 ///
 /// ## Variants
 /// - `Status`: Contains a `ModbusId` and a `VfdStatus`, representing the status response.
+/// - `Error`: Contains a `ModbusId`, the `DeviceType` and a `SoftError`, reports a device error.
+/// - `Point`: Contains a `ModbusId`, a point index and its value, from a register map device.
+/// - `Event`: Contains a `ModbusId`, the `DeviceType` and a `DeviceEvent`, sent unsolicited.
+/// - `Telemetry`: Contains a `ModbusId`, the `Telemetry` value read and its raw value.
+/// - `Parameters`: Contains a `ModbusId` and the `ParameterBlock` read, sent as one frame per
+///   register.
+/// - `ParameterWritten`: Contains a `ModbusId`, the address of the parameter register written,
+///   its offset from the block address of the request and its value.
+/// - `Polling`: Contains a `ModbusId`, whether the status is polled and whether status updates
+///   are pushed.
+/// - `Accepted`: Contains a `ModbusId` and the `FunctionType` of the `Run`, `Stop` or `Reset`
+///   request accepted by the device.
+/// - `GroupApplied`: Contains the `ModbusId` of a group, the sequence number of a group command,
+///   the `ModbusId` of a member, and whether the member applied the command. Gathered by the
+///   router, never sent to the client.
+/// - `GroupDone`: Contains the `ModbusId` of a group, the count of members that did not apply
+///   the last group command and the count of members.
 /// - `None`: Represents an empty or uninitialized response.
 pub enum SoftResponse {
     Status(ModbusId, VfdStatus),
+    Error(ModbusId, DeviceType, SoftError),
+    Point(ModbusId, u8, i32),
+    Event(ModbusId, DeviceType, DeviceEvent),
+    Telemetry(ModbusId, Telemetry, u16),
+    Parameters(ModbusId, ParameterBlock),
+    ParameterWritten(ModbusId, u16, u8, u16),
+    Polling(ModbusId, bool, bool),
+    Accepted(ModbusId, FunctionType),
+    GroupApplied(ModbusId, u16, ModbusId, bool),
+    GroupDone(ModbusId, u8, u8),
     None,
 }
 
+/// Append the CRC16 to the 6 bytes payload of a frame.
+fn with_crc(mut response: [u8; 8]) -> [u8; 8] {
+    let crc = crc16(&response[..6]);
+    response[6] = ((crc & 0xff00) >> 8) as u8;
+    response[7] = (crc & 0x00ff) as u8;
+    response
+}
+
 impl TryInto<[u8; 8]> for SoftResponse {
     type Error = ();
-    fn try_into(self) -> Result<[u8; 8], Self::Error> {
-        if let SoftResponse::Status(id, status) = self {
-            let mut response = [id.into(), 2, 3, 0, 0, 0, 0, 0];
-            match status {
-                VfdStatus::Run(r) => {
-                    response[4] = ((r & 0x7f00) >> 8) as u8;
-                    response[5] = (r & 0x00ff) as u8;
-                    if r < 0 {
-                        response[3] = 1;
+    fn try_into(self) -> Result<[u8; 8], ()> {
+        match self {
+            SoftResponse::Status(id, status) => {
+                let mut response = [id.into(), 2, 3, 0, 0, 0, 0, 0];
+                match status {
+                    VfdStatus::Run(r) => {
+                        let magnitude = r.unsigned_abs();
+                        response[4] = ((magnitude & 0xff00) >> 8) as u8;
+                        response[5] = (magnitude & 0x00ff) as u8;
+                        if r < 0 {
+                            response[3] = 1;
+                        }
+                        Ok(with_crc(response))
                     }
-                    let crc = crc16(&response[..6]);
-                    response[6] = ((crc & 0xff00) >> 8) as u8;
-                    response[7] = (crc & 0x00ff) as u8;
-                    Ok(response)
+                    // TODO: implement STOP status response if speed < mini
+                    _ => {
+                        log::error!("SoftResponse.try_into<[u8]>() status {:?} not yet implemented", self);
+                        Err(())
+                    },
                 }
-                // TODO: implement STOP status response if speed < mini
-                _ => {
-                    log::error!("SoftResponse.try_into<[u8]>() status {:?} not yet implemented", self);
-                    Err(()) 
-                },
             }
-        } else {
-            log::error!("SoftResponse.try_into<[u8]>() Response type conversion not yet implemented: {:?}", self);
-            Err(())
+            SoftResponse::Error(id, device, error) => {
+                let (kind, detail, extra) = error.to_data();
+                Ok(with_crc([id.into(), device.response_type(), 0x80, kind, detail, extra, 0, 0]))
+            }
+            SoftResponse::Point(id, index, value) => {
+                let magnitude = value.unsigned_abs().min(u16::MAX as u32);
+                let sign = if value < 0 { 0x80 } else { 0 };
+                let data1 = (index & 0x7f) | sign;
+                Ok(with_crc([id.into(), DeviceType::RegisterMap.response_type(), 1, data1,
+                    ((magnitude & 0xff00) >> 8) as u8, (magnitude & 0x00ff) as u8, 0, 0]))
+            }
+            SoftResponse::Event(id, device, event) => {
+                let (kind, detail) = event.to_data();
+                Ok(with_crc([id.into(), device.response_type(), 0x81, kind,
+                    ((detail & 0xff00) >> 8) as u8, (detail & 0x00ff) as u8, 0, 0]))
+            }
+            SoftResponse::Telemetry(id, telemetry, value) => {
+                Ok(with_crc([id.into(), DeviceType::Vfd.response_type(), telemetry.fn_code(), 0,
+                    ((value & 0xff00) >> 8) as u8, (value & 0x00ff) as u8, 0, 0]))
+            }
+            SoftResponse::ParameterWritten(id, _, offset, value) => {
+                Ok(with_crc([id.into(), DeviceType::Vfd.response_type(), 10, offset,
+                    ((value & 0xff00) >> 8) as u8, (value & 0x00ff) as u8, 0, 0]))
+            }
+            SoftResponse::Polling(id, status, updates) => {
+                Ok(with_crc([id.into(), DeviceType::Vfd.response_type(), 12, status as u8, updates as u8, 0, 0, 0]))
+            }
+            SoftResponse::Accepted(id, fn_type) => {
+                let fn_code = match fn_type {
+                    FunctionType::Run => 1,
+                    FunctionType::Stop => 2,
+                    FunctionType::Reset => 8,
+                    _ => {
+                        log::error!("SoftResponse.try_into<[u8]>() {:?} is not acknowledged", fn_type);
+                        return Err(());
+                    }
+                };
+                Ok(with_crc([id.into(), DeviceType::Vfd.response_type(), fn_code, 0, 0, 0, 0, 0]))
+            }
+            SoftResponse::GroupDone(id, failed, members) => {
+                Ok(with_crc([id.into(), DeviceType::Vfd.response_type(), 11, failed, members, 0, 0, 0]))
+            }
+            SoftResponse::Parameters(_, _) | SoftResponse::GroupApplied(_, _, _, _) | SoftResponse::None => {
+                log::error!("SoftResponse.try_into<[u8]>() Response type conversion not yet implemented: {:?}", self);
+                Err(())
+            }
         }
     }
 }
 
 impl ResponseFn for SoftResponse {
     fn to_raw(self) -> Option<Vec<u8>> {
+        if let SoftResponse::Parameters(id, block) = self {
+            // one frame per register
+            let frames = block.values().iter().enumerate().flat_map(|(offset, value)| {
+                with_crc([id.into(), DeviceType::Vfd.response_type(), 9, offset as u8,
+                    ((value & 0xff00) >> 8) as u8, (value & 0x00ff) as u8, 0, 0])
+            });
+            return Some(frames.collect());
+        }
         let out: Result<[u8;8], ()> = self.try_into();
         out.ok().map(Vec::from)
     }
+
+    fn group_report(&self) -> Option<GroupReport> {
+        match self {
+            SoftResponse::GroupApplied(group, seq, member, applied) => Some(GroupReport {
+                group: *group,
+                seq: *seq,
+                member: *member,
+                applied: *applied,
+            }),
+            _ => None,
+        }
+    }
+
+    fn group_done(group: ModbusId, failed: u8, members: u8) -> Option<Self> {
+        Some(SoftResponse::GroupDone(group, failed, members))
+    }
+
+    /// A reference out of range and a request a group does not support are rejected, other
+    /// invalid frames are not answered.
+    fn request_error(raw: &[u8], error: &Error) -> Option<Self> {
+        let reason = match error {
+            Error::WrongRefValue => RejectReason::OutOfRange,
+            Error::WrongFunctionType => RejectReason::NotAvailable,
+            _ => return None,
+        };
+        let device = match raw.get(1) {
+            Some(0x05) => DeviceType::RegisterMap,
+            _ => DeviceType::Vfd,
+        };
+        Some(SoftResponse::Error((*raw.first()?).into(), device, SoftError::Rejected(reason)))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use tokio::sync::watch;
+use tokio::time::Instant;
+use crate::soft_request::{DeviceEvent, SoftError};
+
+/// Default count of consecutive failed attempts reported as a `CommErrorBurst`.
+pub const DEFAULT_BURST: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Communication health of a device.
+///
+/// ## Variants
+/// - `Unknown`: The device has not answered yet.
+/// - `Ok`: The last request to the device succeed.
+/// - `Error(SoftError)`: The last request to the device failed.
+pub enum CommHealth {
+    Unknown,
+    Ok,
+    Error(SoftError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Snapshot of a device state.
+///
+/// Fields:
+/// - `status`: The last status read from the device.
+/// - `updated`: When `status` was read from the bus, None if never read.
+/// - `health`: The current communication health of the device.
+pub struct DeviceState<Status> {
+    pub status: Status,
+    pub updated: Option<Instant>,
+    pub health: CommHealth,
+}
+
+#[derive(Debug)]
+/// Publish the state of a device to `watch` subscribers.
+pub struct StatePublisher<Status> {
+    sender: watch::Sender<DeviceState<Status>>,
+}
+
+impl<Status> StatePublisher<Status> {
+    pub fn new(status: Status) -> Self {
+        let (sender, _) = watch::channel(DeviceState {
+            status,
+            updated: None,
+            health: CommHealth::Unknown,
+        });
+        StatePublisher { sender }
+    }
+
+    /// Return a receiver of the device state snapshots.
+    pub fn subscribe(&self) -> watch::Receiver<DeviceState<Status>> {
+        self.sender.subscribe()
+    }
+
+    /// Update the status just read from the bus, the device is healthy.
+    pub fn update(&self, status: Status) {
+        self.sender.send_modify(|s| {
+            s.status = status;
+            s.updated = Some(Instant::now());
+            s.health = CommHealth::Ok;
+        });
+    }
+
+    /// Update the status computed by the device (not read from the bus), subscribers are only
+    /// notified if it changes.
+    pub fn set_status(&self, status: Status)
+    where
+        Status: PartialEq,
+    {
+        self.sender.send_if_modified(|s| {
+            let modified = s.status != status;
+            s.status = status;
+            modified
+        });
+    }
+
+    /// Update the communication health, subscribers are only notified if it changes.
+    pub fn set_health(&self, health: CommHealth) {
+        self.sender.send_if_modified(|s| {
+            let modified = s.health != health;
+            s.health = health;
+            modified
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Track the link to a device, and derive its lifecycle events from the outcome of its requests.
+///
+/// A device is `Online` once it answers (even with an exception), and `Offline` once a request
+/// failed after all its attempts. While not offline, `burst` consecutive failed attempts are
+/// reported once as a `CommErrorBurst`.
+pub struct LinkMonitor {
+    online: Option<bool>,
+    failures: u32,
+    burst: u32,
+}
+
+impl Default for LinkMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkMonitor {
+    pub fn new() -> Self {
+        LinkMonitor {
+            online: None,
+            failures: 0,
+            burst: DEFAULT_BURST,
+        }
+    }
+
+    /// Set the count of consecutive failed attempts reported as a `CommErrorBurst`.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// A request succeed, return `Online` if the device was offline (or never answered).
+    pub fn success(&mut self) -> Option<DeviceEvent> {
+        self.failures = 0;
+        self.answered()
+    }
+
+    /// The device answered with an exception, return `Online` if it was offline (or never
+    /// answered). The failed attempts are still counted, as the request failed.
+    pub fn answered(&mut self) -> Option<DeviceEvent> {
+        (self.online.replace(true) != Some(true)).then_some(DeviceEvent::Online)
+    }
+
+    /// A request failed and will be sent again, return `CommErrorBurst` if the consecutive
+    /// failures reach the burst count.
+    pub fn retry(&mut self) -> Option<DeviceEvent> {
+        self.failures = self.failures.saturating_add(1);
+        (self.online != Some(false) && self.failures == self.burst)
+            .then(|| DeviceEvent::CommErrorBurst(self.failures.min(u16::MAX as u32) as u16))
+    }
+
+    /// A request failed after all its attempts, return `Offline` if the device was online
+    /// (or never answered).
+    pub fn failure(&mut self) -> Option<DeviceEvent> {
+        self.failures = self.failures.saturating_add(1);
+        (self.online.replace(false) != Some(false)).then_some(DeviceEvent::Offline)
+    }
+}
@@ -1,13 +1,49 @@
 use std::fmt::Debug;
 use std::time::Duration;
 use tokio::time::sleep;
+use crate::batch::Attempt;
+use crate::error::Error;
 use crate::modbus::ModbusId;
+use crate::soft_request::DeviceEvent;
 use crate::traits::device_encoder::DeviceEncoder;
 use crate::traits::request::{RequestFn, ResponseFn};
 use crate::traits::polling::{PollerConnector, PollerMessage, Polling};
 use crate::traits::routing::{RouterConnector, Routing};
 
+pub use modbus_router_derive::DeviceConnectors;
+
+/// Router & poller plumbing of a `Device`.
+///
+/// Should be implemented with `#[derive(DeviceConnectors)]`, see the `modbus_router_derive` crate
+/// for the expected fields.
+pub trait DeviceConnectors<Request, Response, DeviceRequest, DeviceResponse> {
+    fn set_poller(&mut self, connector: PollerConnector<DeviceRequest, DeviceResponse>);
+
+    fn set_router(&mut self, connector: RouterConnector<Request, Response>);
+
+    /// Return the logical id of the device, used by the router.
+    fn id(&self) -> ModbusId;
+
+    /// Return the physical Modbus id of the device on the poller bus.
+    fn slave_id(&self) -> ModbusId;
+
+    /// Return true if connected to router
+    fn is_external_connected(&self) -> bool;
+    
+    /// Return true if connected to poller
+    fn is_device_connected(&self) -> bool;
+    
+    /// Return an error if not connected to router.
+    fn read_external_request(&mut self) -> Result<Option<Request>, Error>;
+    
+    fn send_external_response(&mut self, response: Response);
+
+    /// Return an error if not connected to poller.
+    fn read_device_response(&mut self) -> Result<Option<PollerMessage<DeviceRequest, DeviceResponse>>, Error>;
+}
+
 pub trait Device<Request, Response, DeviceRequest, DeviceResponse>
+    : DeviceConnectors<Request, Response, DeviceRequest, DeviceResponse>
     where
         Request: RequestFn,
         Response: ResponseFn,
@@ -16,105 +52,127 @@ pub trait Device<Request, Response, DeviceRequest, DeviceResponse>
 {
     type Encoder: DeviceEncoder<DeviceRequest, DeviceResponse>;
 
-    /// Connects Device to a given Poller.
+    /// Connects Device to a given Poller, the device is addressed on the bus by its current
+    /// slave id (its `id()` if not set).
     ///
-    /// Panics if there is already a poller connected.
+    /// Return an error if there is already a poller connected, or if the poller already have
+    /// a device with the same slave id.
     ///
     /// # Arguments
     /// * `poller` - A `Poller` to be connected to the Device.
-    fn connect_poller(&mut self, poller: &mut impl Polling<DeviceRequest, DeviceResponse>) 
+    fn connect_poller(&mut self, poller: &mut impl Polling<DeviceRequest, DeviceResponse>) -> Result<(), Error>
     where
         DeviceRequest: Debug + Clone + Copy + Send,
         DeviceResponse: Debug + Clone + Copy + Send,
     {
-        if !self.is_device_connected() {
-            if let Some(conn) = poller.get_connector(self.id()) {
-                self.set_poller(conn);
-            } else {
-                panic!("Poller already connected!");
-            }
-        } else {
-            panic!("Poller already connected!");
+        let slave_id = self.slave_id();
+        self.connect_poller_as(poller, slave_id)
+    }
+
+    /// Connects Device to a given Poller, the device is addressed on the bus by `slave_id`,
+    /// while the router keeps addressing it by its `id()`.
+    ///
+    /// Slave ids are only unique per poller, devices with the same slave id can be connected
+    /// to different pollers.
+    ///
+    /// Return an error if there is already a poller connected, or if the poller already have
+    /// a device with the same slave id.
+    ///
+    /// # Arguments
+    /// * `poller` - A `Poller` to be connected to the Device.
+    /// * `slave_id` - The physical Modbus id of the device on the poller bus.
+    fn connect_poller_as(&mut self, poller: &mut impl Polling<DeviceRequest, DeviceResponse>, slave_id: ModbusId) -> Result<(), Error>
+    where
+        DeviceRequest: Debug + Clone + Copy + Send,
+        DeviceResponse: Debug + Clone + Copy + Send,
+    {
+        if self.is_device_connected() {
+            return Err(Error::PollerAlreadyConnected);
         }
+        let conn = poller.get_connector(slave_id).ok_or(Error::IdAlreadyRegistered(slave_id))?;
+        self.set_slave_id(slave_id);
+        self.set_poller(conn);
+        Ok(())
     }
 
     /// Connects Device to a given Router.
     ///
-    /// Panics if there is already a router connected.
+    /// Return an error if there is already a router connected, or if the router already have
+    /// a device with the same id.
     ///
     /// # Arguments
     /// * `router` - A `Router` to be connected to the Device.
-    fn connect_router(&mut self, router: &mut impl Routing<Request, Response>) {
-        if !self.is_external_connected() {
-            if let Some(conn) = router.get_connector(self.id()) {
-                self.set_router(conn);
-            } else {
-                panic!("Router already connected!");
-            }
-        } else {
-            panic!("Router already connected!");
+    fn connect_router(&mut self, router: &mut impl Routing<Request, Response>) -> Result<(), Error> {
+        if self.is_external_connected() {
+            return Err(Error::RouterAlreadyConnected);
         }
+        let conn = router.get_connector(self.id()).ok_or(Error::IdAlreadyRegistered(self.id()))?;
+        self.set_router(conn);
+        Ok(())
     }
     
-    fn set_poller(&mut self, connector: PollerConnector<DeviceRequest, DeviceResponse>);
-    // self.poller = Some(conn);
-
-    fn set_router(&mut self, connector: RouterConnector<Request, Response>);
-    // self.router = Some(conn);
-
     fn send_batch(&mut self);
     
-    fn id(&self) -> ModbusId;
-    
-    /// Return true if connected to router
-    fn is_external_connected(&self) -> bool;
-    
-    /// Return true if connected to poller
-    fn is_device_connected(&self) -> bool;
-    
-    fn read_external_request(&mut self) -> Option<Request>;
-    
-    fn send_external_response(&mut self, response: Response);
-
-    fn read_device_response(&mut self) -> Option<PollerMessage<DeviceResponse>>;
-    // self.poller.as_mut().unwrap().receiver.try_recv()
+    /// Set the physical Modbus id of the device on the poller bus.
+    fn set_slave_id(&mut self, slave_id: ModbusId);
     
     fn handle_external_request(&mut self, request: Request);
     fn handle_device_response(&mut self, response: DeviceResponse);
+
+    /// Handle a failed request the poller hands back to be sent in the next batch,
+    /// default to drop it.
+    fn handle_retry(&mut self, attempt: Attempt<DeviceRequest>) {
+        log::debug!("Device::handle_retry() drop request after {} attempts", attempt.attempts);
+    }
+
+    /// Handle a request that have not been sent because a request it depends on failed,
+    /// default to drop it.
+    fn handle_aborted(&mut self, _request: DeviceRequest) {
+        log::debug!("Device::handle_aborted() drop request");
+    }
+
+    /// Report a lifecycle event to the client, default to log it.
+    fn report_event(&mut self, event: DeviceEvent) {
+        log::info!("Device {} event: {:?}", {let id: u8 = self.id().into(); id}, event);
+    }
     
     /// Function that continually handles external requests and device responses.
     ///
     /// It processes incoming requests and responses, updates the state, and handles communication
     /// with external and poller.
+    ///
+    /// Return an error if the device is not connected to both a router and a poller.
     #[allow(async_fn_in_trait)]
-    async fn run(&mut self) {
+    async fn run(&mut self) -> Result<(), Error> {
         log::info!("Device with id {} Started.", {
         let id: u8 = self.id().into();
         id
     });
+        self.report_event(DeviceEvent::Configured);
         loop {
-            if self.is_external_connected() {
-                while let Some(request) =  self.read_external_request(){
-                    log::debug!("Device::get external request: {:?}", request);
-                    self.handle_external_request(request);
-                }
-            } else {
-                panic!("No router!");
+            while let Some(request) =  self.read_external_request()? {
+                log::debug!("Device::get external request: {:?}", request);
+                self.handle_external_request(request);
             }
 
-            if self.is_device_connected() {
-                while let Some(response) =  self.read_device_response(){
-                    match response {
-                        PollerMessage::Poll => {
-                            self.send_batch();
-                        }
-                        PollerMessage::Response(r) => {
-                            self.handle_device_response(r);
-                        }
+            while let Some(response) =  self.read_device_response()? {
+                match response {
+                    PollerMessage::Poll => {
+                        self.send_batch();
+                    }
+                    PollerMessage::Response(r) => {
+                        self.handle_device_response(r);
+                    }
+                    PollerMessage::Retry(attempt) => {
+                        self.handle_retry(attempt);
+                    }
+                    PollerMessage::Aborted(r) => {
+                        self.handle_aborted(r);
+                    }
+                    PollerMessage::PortStatus(up) => {
+                        self.report_event(if up { DeviceEvent::PortUp } else { DeviceEvent::PortDown });
                     }
                 }
-            } else {
-                panic!("No Poller!");
             }
 
             sleep(Duration::from_nanos(10)).await;
@@ -1,18 +1,40 @@
 use std::fmt::Debug;
 use serial_thread::SerialMessage;
-use crate::modbus::ModbusId;
+use crate::batch::RetryPolicy;
+use crate::modbus::{ModbusException, ModbusId};
 
 pub trait DeviceEncoder<DeviceRequest, DeviceResponse>: Debug + Send
 {
     fn request_to_serial(&self, request: DeviceRequest) -> Option<SerialMessage>;
     fn serial_to_response(&self, msg: SerialMessage, request: DeviceRequest, id: ModbusId) -> DeviceResponse;
 
+    /// Retry policy applied by the `Batch` on failed requests, default to no retry.
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Return true if the response is a failure, requests depending on it will not be sent.
+    fn is_failure(&self, response: &DeviceResponse) -> bool {
+        self.should_retry(response)
+    }
+
+    /// Return true if the response is a failure and its request can be sent again.
+    fn should_retry(&self, _response: &DeviceResponse) -> bool {
+        false
+    }
+
+    /// Return the response to a request sent in a broadcast frame (that is not answered),
+    /// `None` if the request cannot be broadcast. Default to `None`.
+    fn broadcast_response(&self, _request: DeviceRequest) -> Option<DeviceResponse> {
+        None
+    }
+
     fn filter_response(&self, msg: SerialMessage) -> Option<SerialMessage> {
         // filtering: we handle only receive/no response, drop other messages
         match &msg {
             SerialMessage::Receive(data) => {
-                
-                if data.len() > 6 {Some(msg)} else {
+                // exception frames are only 5 bytes long
+                if data.len() > 6 || ModbusException::from_frame(data).is_some() {Some(msg)} else {
                     log::error!("Receive incomplete response: {:?}", data);
                     Some(SerialMessage::NoResponse)
                 }
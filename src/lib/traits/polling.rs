@@ -3,13 +3,27 @@ use std::sync::mpsc::{Receiver, Sender};
 use std::time::Duration;
 use serial_thread::{Mode, SerialInterface, SerialMessage};
 use tokio::time::sleep;
-use crate::batch::Batch;
+use crate::batch::{Attempt, Batch, SyncGroup};
 use crate::modbus::ModbusId;
 
+/// Delay between two attempts to connect the serial port.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
-pub enum PollerMessage<DeviceResponse> {
+/// Message sent from a poller to a device.
+///
+/// ## Variants
+/// - `Poll`: The device should send its next `Batch`.
+/// - `Response(DeviceResponse)`: Response to a request of the last `Batch`.
+/// - `Retry(Attempt)`: A failed request the device should push again in its next `Batch`.
+/// - `Aborted(DeviceRequest)`: A request not sent because a request it depends on failed.
+/// - `PortStatus(bool)`: The serial port of the poller is connected (`true`) or lost (`false`).
+pub enum PollerMessage<DeviceRequest, DeviceResponse> {
     Poll,
-    Response(DeviceResponse)
+    Response(DeviceResponse),
+    Retry(Attempt<DeviceRequest>),
+    Aborted(DeviceRequest),
+    PortStatus(bool),
 }
 
 #[allow(unused)]
@@ -21,7 +35,7 @@ pub struct PollerConnector<DeviceRequest, DeviceResponse>
 
 {
     pub sender: Sender<Batch<DeviceRequest, DeviceResponse>>,
-    pub receiver: Receiver<PollerMessage<DeviceResponse>>,
+    pub receiver: Receiver<PollerMessage<DeviceRequest, DeviceResponse>>,
 }
 
 pub trait Polling<DeviceRequest, DeviceResponse>
@@ -50,7 +64,7 @@ where
 
     fn devices_ids(&self) -> Vec<ModbusId>;
 
-    fn send_to_device(&mut self, id: ModbusId, msg: PollerMessage<DeviceResponse>);
+    fn send_to_device(&mut self, id: ModbusId, msg: PollerMessage<DeviceRequest, DeviceResponse>);
 
     // log::debug!("polling device {}", {let i: u8 = (*axis_id).into(); i});
     // self.send_to_device(self.poll_message);
@@ -58,6 +72,43 @@ where
         self.send_to_device(id, PollerMessage::Poll)
     }
 
+    /// Notify every device that the serial port is connected (`up`) or lost.
+    fn notify_port_status(&mut self, up: bool) {
+        for device_id in self.devices_ids() {
+            self.send_to_device(device_id, PollerMessage::PortStatus(up));
+        }
+    }
+
+    /// Connect the serial port, attempts are repeated every `RECONNECT_DELAY` until it
+    /// succeed. Devices are notified of the port status after each attempt.
+    #[allow(async_fn_in_trait)]
+    async fn connect(&mut self) {
+        loop {
+            self.send_msg(SerialMessage::Connect);
+
+            // wait for serial port to connect
+            let connected = loop {
+                match self.receive_msg() {
+                    Some(SerialMessage::Connected(connected)) => {
+                        log::debug!("{:?}", SerialMessage::Connected(connected));
+                        break connected;
+                    }
+                    Some(SerialMessage::Error(e)) => {
+                        log::error!("{} => {}", self.port_name(), e);
+                        break false;
+                    }
+                    _ => sleep(Duration::from_nanos(10)).await,
+                }
+            };
+            self.notify_port_status(connected);
+            if connected {
+                return;
+            }
+            log::error!("Cannot connect to serial port {}", self.port_name());
+            sleep(RECONNECT_DELAY).await;
+        }
+    }
+
 
     /// Runs the device polling logic.
     ///
@@ -81,7 +132,7 @@ where
                 serial.start().await;
             });
         } else {
-            log::error!("SerialInterface({}) missing",self.port_name())
+            log::info!("{} => no SerialInterface, using external serial channels", self.port_name())
         }
 
         if let Some(timeout) = self.get_timeout() {
@@ -89,23 +140,7 @@ where
             self.send_msg(SerialMessage::SetTimeout(timeout));
         }
 
-        self.send_msg(SerialMessage::Connect);
-
-        // wait for serial port to connect 
-        loop {
-            if let Some(SerialMessage::Connected(connected)) = self.receive_msg() {
-                log::debug!("{:?}", SerialMessage::Connected(connected));
-                if connected {
-                    break;
-                } else {
-                    log::error!("Cannot connect to serial port {}", self.port_name());
-                    return;
-                }
-            } else {
-                sleep(Duration::from_nanos(10)).await;
-            }
-        }
-
+        self.connect().await;
         log::info!("{} connected!", self.port_name());
 
         self.send_msg(SerialMessage::SetMode(Mode::MasterStream));
@@ -114,48 +149,158 @@ where
         log::info!("Poller => Start polling {} for {} devices", self.port_name(), self.devices_ids().len());
         loop {
             // poll each device
+            let mut batches = vec![];
             for device_id in self.devices_ids() {
                 self.poll(device_id);
+                batches.push(self.wait_batch().await);
+            }
 
-                //  wait for batch
-                let mut batch: Batch<DeviceRequest, DeviceResponse>;
-                loop {
-                    if let Some(b) = self.rcv_batch() {
-                        batch = b;
+            // synchronized requests are sent first
+            let mut port_lost = false;
+            let mut groups: Vec<SyncGroup> = vec![];
+            for group in batches.iter().flat_map(|b| b.sync_groups()) {
+                if !groups.contains(&group) {
+                    groups.push(group);
+                }
+            }
+            for group in groups {
+                port_lost = self.send_sync(&mut batches, group, port_lost).await;
+            }
+
+            for batch in batches.iter_mut() {
+                port_lost = self.send_requests(batch, port_lost, |_| true).await;
+                sleep(Duration::from_nanos(10)).await;
+            }
+
+            if port_lost {
+                self.notify_port_status(false);
+                self.connect().await;
+                self.send_msg(SerialMessage::SetMode(Mode::MasterStream));
+            }
+        }
+    }
+
+    /// Wait for the `Batch` of the device polled.
+    #[allow(async_fn_in_trait)]
+    async fn wait_batch(&mut self) -> Batch<DeviceRequest, DeviceResponse> {
+        loop {
+            if let Some(batch) = self.rcv_batch() {
+                return batch;
+            }
+            sleep(Duration::from_nanos(10)).await;
+        }
+    }
+
+    /// Send the requests of the batches synchronized with `group`, back-to-back.
+    ///
+    /// If the group allows it and every device of the port sends the same request, the request
+    /// is sent once, in a broadcast frame. Return true if the port is lost.
+    #[allow(async_fn_in_trait)]
+    async fn send_sync(
+        &mut self,
+        batches: &mut [Batch<DeviceRequest, DeviceResponse>],
+        group: SyncGroup,
+        mut port_lost: bool,
+    ) -> bool {
+        // the requests preceding the synchronized ones are sent first
+        for batch in batches.iter_mut().filter(|b| b.has_sync(group)) {
+            port_lost = self.send_requests(batch, port_lost, |b| b.has_sync(group) && !b.next_is_sync(group)).await;
+        }
+
+        // a broadcast reaches every slave of the port, the whole port must be in the group
+        while group.broadcast && !port_lost && batches.iter().all(|b| b.next_is_sync(group)) {
+            let frames: Vec<Option<Vec<u8>>> = batches.iter().map(|b| b.broadcast_frame()).collect();
+            let frame = match frames.first() {
+                Some(Some(frame)) if frames.iter().all(|f| f.as_ref() == Some(frame)) => frame.clone(),
+                _ => break,
+            };
+            log::debug!("{} => broadcast {:?} to group {:?}", self.port_name(), frame, group.id);
+            self.send_msg(SerialMessage::Send(frame));
+            // no slave answers, the serial timeout is the turnaround delay
+            loop {
+                match self.receive_msg() {
+                    Some(SerialMessage::Connected(false)) | Some(SerialMessage::Error(_)) => {
+                        log::error!("{} => port lost during broadcast", self.port_name());
+                        port_lost = true;
                         break;
-                    } else {
-                        sleep(Duration::from_nanos(10)).await;
                     }
+                    Some(_) => break,
+                    None => sleep(Duration::from_nanos(10)).await,
                 }
+            }
+            if port_lost {
+                break;
+            }
+            for batch in batches.iter_mut() {
+                for send in batch.handle_broadcast() {
+                    self.send_to_device(batch.id, send);
+                }
+            }
+        }
+
+        for batch in batches.iter_mut().filter(|b| b.has_sync(group)) {
+            port_lost = self.send_requests(batch, port_lost, |b| b.has_sync(group)).await;
+        }
+        port_lost
+    }
 
-                while !batch.is_empty() {
-                    let next_request = batch.next();
-                    if let Some(request) = next_request {
-                        self.send_msg(request);
-                        while !batch.is_complete() {
-                            let serial_response = self.receive_msg();
-                            if let Some(r) = serial_response {
-                                let to_send = batch.handle_response(r);
-                                if let Some(send) = to_send {
-                                    self.send_to_device(batch.id, PollerMessage::Response(send));
-                                    if let Some(silence) = self.get_frame_silence() {
-                                        sleep(silence).await;
-                                    }
-                                    break
-                                }
-                            } else {
-                                sleep(Duration::from_nanos(10)).await;
+    /// Send the requests of `batch` while `more` returns true, the responses are sent to the
+    /// device. If the port is lost, the remaining requests fail without being sent. Return
+    /// true if the port is lost.
+    #[allow(async_fn_in_trait)]
+    async fn send_requests(
+        &mut self,
+        batch: &mut Batch<DeviceRequest, DeviceResponse>,
+        mut port_lost: bool,
+        more: impl Fn(&Batch<DeviceRequest, DeviceResponse>) -> bool,
+    ) -> bool {
+        while !batch.is_empty() && more(batch) {
+            // immediate retry backoff
+            if let Some(delay) = batch.backoff() {
+                sleep(delay).await;
+            }
+            let next_request = batch.next();
+            if let Some(request) = next_request {
+                // the port is lost, remaining requests fail without being sent
+                if port_lost {
+                    for send in batch.handle_response(SerialMessage::NoResponse) {
+                        self.send_to_device(batch.id, send);
+                    }
+                    continue;
+                }
+                self.send_msg(request);
+                while !batch.is_complete() {
+                    let serial_response = self.receive_msg();
+                    if let Some(r) = serial_response {
+                        // the current request fails
+                        let r = match r {
+                            SerialMessage::Connected(false) | SerialMessage::Error(_) => {
+                                log::error!("{} => port lost: {:?}", self.port_name(), r);
+                                port_lost = true;
+                                SerialMessage::NoResponse
                             }
+                            r => r,
+                        };
+                        for send in batch.handle_response(r) {
+                            self.send_to_device(batch.id, send);
                         }
-                    } else {
-                        if let Some(silence) = self.get_device_silence() {
-                            sleep(silence).await;
+                        if batch.is_complete() {
+                            if let Some(silence) = self.get_frame_silence() {
+                                sleep(silence).await;
+                            }
+                            break
                         }
-                        break;
+                    } else {
+                        sleep(Duration::from_nanos(10)).await;
                     }
                 }
-                sleep(Duration::from_nanos(10)).await;
+            } else {
+                if let Some(silence) = self.get_device_silence() {
+                    sleep(silence).await;
+                }
+                break;
             }
         }
+        port_lost
     }
 }
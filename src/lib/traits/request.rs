@@ -1,13 +1,42 @@
 use std::fmt::Debug;
+use crate::batch::SyncGroup;
+use crate::error::Error;
+use crate::group::GroupReport;
 use crate::modbus::ModbusId;
 
 
 pub trait RequestFn: Debug + Clone + Copy + Send{
-    fn from(raw: Vec<u8>) -> Option<Box<Self>>;
+    /// Decode a request frame.
+    fn from(raw: Vec<u8>) -> Result<Box<Self>, Error>;
     fn id(&self) -> ModbusId;
     fn new_id(&self, id: ModbusId) -> Box<Self>;
+
+    /// Return the request sent to the member `id` of `group` for this group request, its
+    /// reference scaled by `ratio`, an error if the scaled reference is out of range. `None` if
+    /// the request is not supported by groups (default).
+    fn for_member(&self, _id: ModbusId, _group: SyncGroup, _ratio: f32) -> Option<Result<Box<Self>, Error>> {
+        None
+    }
 }
 
 pub trait ResponseFn: Debug + Clone + Copy + Send {
     fn to_raw(self) -> Option<Vec<u8>>;
-}
\ No newline at end of file
+
+    /// Return the report of a group member, `None` (default) if the response is not a member
+    /// report.
+    fn group_report(&self) -> Option<GroupReport> {
+        None
+    }
+
+    /// Return the completion response of a group command, `failed` of its `members` did not
+    /// apply it. Default to `None`: completion is not reported.
+    fn group_done(_group: ModbusId, _failed: u8, _members: u8) -> Option<Self> {
+        None
+    }
+
+    /// Return the response to the request frame `raw`, that cannot be routed because of
+    /// `error`. Default to `None`: the request is not answered.
+    fn request_error(_raw: &[u8], _error: &Error) -> Option<Self> {
+        None
+    }
+}
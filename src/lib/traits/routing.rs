@@ -1,6 +1,8 @@
 use std::sync::mpsc::{Receiver, Sender};
 use std::time::Duration;
 use tokio::time::sleep;
+use crate::error::Error;
+use crate::group::{DriveGroup, Groups};
 use crate::modbus::ModbusId;
 use crate::traits::request::{RequestFn, ResponseFn};
 
@@ -27,6 +29,13 @@ pub trait Routing<Request, Response>
     fn devices_ids(&self) -> Vec<ModbusId>;
     fn try_receive_request(&mut self) -> Option<Vec<u8>>;
     fn try_receive_response(&mut self) -> Option<Response>;
+    fn groups(&mut self) -> &mut Groups;
+
+    /// Add a group of drives, its members must be connected first.
+    fn add_group(&mut self, group: DriveGroup) -> Result<(), Error> {
+        let devices = self.devices_ids();
+        self.groups().add(group, &devices)
+    }
 
     /// Runs the Router loop, handling incoming requests and responses.
     ///
@@ -49,35 +58,67 @@ pub trait Routing<Request, Response>
     }
     /// Handles an incoming request.
     ///
-    /// Decodes the request and routes it to the appropriate axis, or handles broadcasting.
+    /// Decodes the request and routes it to the appropriate axis, or handles broadcasting and
+    /// groups.
     ///
     /// # Arguments
     /// * `request` - A Vec<u8> request.
     fn handle_raw_request(&mut self, raw_request: Vec<u8>) {
         log::debug!("Routing.handle_raw_request({:?})", raw_request);
-        let r = Request::from(raw_request.clone());
-        if let Some(request) = r {
-            match request.id() {
-                ModbusId::Id(_) => {
-                    self.transmit_request(*request);
+        let request = match Request::from(raw_request.clone()) {
+            Ok(request) => request,
+            Err(e) => {
+                log::error!("Routing.handle_raw_request({:?}) fail: {}", raw_request, e);
+                self.reply_error(&raw_request, &e);
+                return;
+            }
+        };
+        match self.groups().fan_out(&*request) {
+            Some(Ok(requests)) => {
+                for request in requests {
+                    self.transmit_request(request);
                 }
-                ModbusId::Broadcast => {
-                    let ids = self.devices_ids();
-                    for id in ids {
-                        self.transmit_request(*request.new_id(id));
-                    }
+                return;
+            }
+            Some(Err(e)) => {
+                log::error!("Routing.handle_raw_request({:?}) fail: {}", raw_request, e);
+                self.reply_error(&raw_request, &e);
+                return;
+            }
+            None => {}
+        }
+        match request.id() {
+            ModbusId::Id(_) => {
+                self.transmit_request(*request);
+            }
+            ModbusId::Broadcast => {
+                let ids = self.devices_ids();
+                for id in ids {
+                    self.transmit_request(*request.new_id(id));
                 }
-                ModbusId::Reserved => {}
             }
+            ModbusId::Reserved => {}
+        }
+    }
+
+    /// Answer the request frame `raw` that cannot be routed because of `error`, if the
+    /// response type has an answer for it (see `ResponseFn::request_error()`).
+    fn reply_error(&mut self, raw: &[u8], error: &Error) {
+        if let Some(raw) = Response::request_error(raw, error).and_then(|r| r.to_raw()) {
+            self.transmit_response(raw);
         }
     }
     /// Handles a PLC response.
     ///
-    /// Converts the `PlcResponse` into a byte array and writes it to `stdout`.
+    /// Converts the `PlcResponse` into a byte array and writes it to `stdout`, group members
+    /// reports are gathered into a group completion.
     ///
     /// # Arguments
     /// * `response` - A `PlcResponse` to handle.
     fn handle_response(&mut self, response: Response) {
+        let Some(response) = self.groups().filter_response(response) else {
+            return;
+        };
         if let Some(raw) = response.to_raw() {
             log::debug!("Routing.handle_response({:?}) ", response);
             self.transmit_response(raw);
@@ -0,0 +1,128 @@
+use lib::devices::joystick::device::JoystickType;
+use lib::devices::vfd::profile::VfdProfiles;
+use lib::modbus::ModbusException;
+use lib::simulator::joystick::JoystickSlave;
+use lib::simulator::pty::PtySimulator;
+use lib::simulator::vfd::{VfdSlave, DEFAULT_ACCELERATION};
+use lib::simulator::{Faults, Simulator};
+use std::env;
+use std::process::exit;
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: simulator [OPTIONS] <KIND:ID>...
+
+Simulate Modbus slaves on a pseudo-terminal, the path of the serial port to connect to is
+printed on stdout.
+
+Slaves:
+    <profile>:<id>      Drive using a Vfd profile (bundled: frecon, megmeet)
+    joystick:<id>       Joystick
+    thumb:<id>          Joystick with thumb
+
+Options:
+    --profiles <dir>        Load the Vfd profiles of a directory
+    --accel <units/s>       Drives acceleration
+    --sweep <ms>            Joysticks sweep period
+    --delay <id>:<ms>       Slave answers after a delay
+    --drop <id>:<n>         Slave does not answer every nth request
+    --exception <id>:<code> Slave answers with a Modbus exception
+    --silent <id>           Slave never answers
+    --bad-crc <id>          Slave answers with a wrong CRC";
+
+/// A fault to set on a slave.
+type SetFault = Box<dyn Fn(&mut Faults)>;
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    exit(1)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> T {
+    value.parse().unwrap_or_else(|_| fail(&format!("invalid value: {}", value)))
+}
+
+/// Split a `<a>:<b>` argument.
+fn pair(arg: &str) -> (&str, &str) {
+    arg.split_once(':').unwrap_or_else(|| fail(&format!("invalid argument: {}", arg)))
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut acceleration = DEFAULT_ACCELERATION;
+    let mut profiles = VfdProfiles::bundled();
+    let mut sweep = None;
+    let mut slaves = vec![];
+    let mut faults: Vec<(u8, SetFault)> = vec![];
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("missing value for {}", arg)));
+        match arg.as_str() {
+            "--accel" => acceleration = parse(&value()),
+            "--profiles" => {
+                profiles = VfdProfiles::load(value()).unwrap_or_else(|e| fail(&e.to_string()));
+            }
+            "--sweep" => sweep = Some(Duration::from_millis(parse(&value()))),
+            "--delay" => {
+                let v = value();
+                let (id, ms) = pair(&v);
+                let delay = Duration::from_millis(parse(ms));
+                faults.push((parse(id), Box::new(move |f| f.delay = Some(delay))));
+            }
+            "--drop" => {
+                let v = value();
+                let (id, n) = pair(&v);
+                let n: u32 = parse(n);
+                faults.push((parse(id), Box::new(move |f| f.drop_every = Some(n))));
+            }
+            "--exception" => {
+                let v = value();
+                let (id, code) = pair(&v);
+                let exception = ModbusException::from(parse::<u8>(code));
+                faults.push((parse(id), Box::new(move |f| f.exception = Some(exception))));
+            }
+            "--silent" => faults.push((parse(&value()), Box::new(|f| f.no_response = true))),
+            "--bad-crc" => faults.push((parse(&value()), Box::new(|f| f.bad_crc = true))),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => {
+                let (kind, id) = pair(&arg);
+                slaves.push((kind.to_string(), parse::<u8>(id)));
+            }
+        }
+    }
+    if slaves.is_empty() {
+        fail("no slave to simulate");
+    }
+
+    let mut simulator = Simulator::new();
+    for (kind, id) in slaves {
+        let joystick = |t| {
+            let j = JoystickSlave::new(t);
+            sweep.map_or(j, |s| j.sweep(s))
+        };
+        simulator = match kind.as_str() {
+            "joystick" => simulator.slave(id, joystick(JoystickType::Joystick)),
+            "thumb" => simulator.slave(id, joystick(JoystickType::JoystickWithThumb)),
+            profile => match profiles.get(profile) {
+                Ok(commands) => simulator.slave(id, VfdSlave::new(commands).acceleration(acceleration)),
+                Err(_) => fail(&format!("unknown slave kind: {}", kind)),
+            },
+        };
+    }
+    for (id, fault) in faults {
+        match simulator.faults(id) {
+            Some(f) => fault(f),
+            None => fail(&format!("no slave with id {}", id)),
+        }
+    }
+
+    let pty = PtySimulator::new(simulator.shared())
+        .unwrap_or_else(|e| fail(&format!("cannot open pseudo-terminal: {}", e)));
+    println!("{}", pty.path().display());
+    if pty.start().join().is_err() {
+        exit(1);
+    }
+}
@@ -0,0 +1,106 @@
+//! `Batch` retries, driven by hand on tokio's paused clock: the poller is played by the test,
+//! that sends the requests and answers them with `SerialMessage::NoResponse`.
+
+use std::time::Duration;
+use serial_thread::SerialMessage;
+use tokio::time::advance;
+use lib::batch::{Attempt, Batch, RetryMode, RetryPolicy};
+use lib::devices::vfd::encoder::{VfdEncoder, FRECON};
+use lib::devices::vfd::requests::{Dir, VfdRequest, VfdResponse};
+use lib::modbus::ModbusId;
+use lib::traits::polling::PollerMessage;
+
+const REFERENCE: VfdRequest = VfdRequest::Ref(ModbusId::Id(10), 500);
+const RUN: VfdRequest = VfdRequest::Cmd(ModbusId::Id(10), Dir::Fw);
+
+/// A batch writing the reference, then the run command if the reference write succeed.
+fn batch(policy: RetryPolicy, reference: Attempt<VfdRequest>, run: Attempt<VfdRequest>) -> Batch<VfdRequest, VfdResponse> {
+    let mut batch = Batch::new(10.into(), Box::new(VfdEncoder::new(FRECON, policy)));
+    batch.push_retry(reference);
+    batch.then(run);
+    batch
+}
+
+/// Send the next request of the batch, and let the slave not answer it.
+fn fail_next(batch: &mut Batch<VfdRequest, VfdResponse>) -> Vec<PollerMessage<VfdRequest, VfdResponse>> {
+    assert!(matches!(batch.next(), Some(SerialMessage::Send(_))));
+    batch.handle_response(SerialMessage::NoResponse)
+}
+
+/// Assert the messages report the reference write failure and the run command aborted.
+fn assert_aborted(messages: &[PollerMessage<VfdRequest, VfdResponse>]) {
+    assert_eq!(messages.len(), 2, "{messages:?}");
+    assert!(matches!(messages[0], PollerMessage::Response(VfdResponse::Fail(REFERENCE))));
+    assert!(matches!(messages[1], PollerMessage::Aborted(RUN)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn immediate_retry_waits_the_backoff_then_aborts() {
+    let policy = RetryPolicy::new(3, RetryMode::Immediate, Some(Duration::from_millis(100)));
+    let mut batch = batch(policy, Attempt::new(REFERENCE), Attempt::new(RUN));
+
+    // first retry after the backoff
+    assert!(fail_next(&mut batch).is_empty());
+    assert_eq!(batch.backoff(), Some(Duration::from_millis(100)));
+    advance(Duration::from_millis(60)).await;
+    assert_eq!(batch.backoff(), Some(Duration::from_millis(40)));
+    advance(Duration::from_millis(40)).await;
+    assert_eq!(batch.backoff(), Some(Duration::ZERO));
+
+    // the backoff doubles
+    assert!(fail_next(&mut batch).is_empty());
+    assert_eq!(batch.backoff(), Some(Duration::from_millis(200)));
+    advance(Duration::from_millis(200)).await;
+
+    // the third attempt is the last one
+    assert_aborted(&fail_next(&mut batch));
+    assert!(batch.next().is_none());
+    assert!(batch.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn immediate_retry_without_backoff_is_sent_at_once() {
+    let policy = RetryPolicy::new(2, RetryMode::Immediate, None);
+    let mut batch = batch(policy, Attempt::new(REFERENCE), Attempt::new(RUN));
+    assert!(fail_next(&mut batch).is_empty());
+    assert_eq!(batch.backoff(), None);
+    assert_aborted(&fail_next(&mut batch));
+    assert!(batch.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn next_cycle_retry_is_handed_back_then_aborts() {
+    let policy = RetryPolicy::new(3, RetryMode::NextCycle, Some(Duration::from_millis(100)));
+    let mut reference = Attempt::new(REFERENCE);
+    let mut run = Attempt::new(RUN);
+    for (attempts, backoff) in [(1, 100), (2, 200)] {
+        let mut batch = batch(policy, reference, run);
+        let messages = fail_next(&mut batch);
+        // the failed request and the one depending on it are handed back to the device
+        assert!(batch.is_empty());
+        let [PollerMessage::Retry(r), PollerMessage::Retry(c)] = messages.as_slice() else {
+            panic!("{messages:?}");
+        };
+        assert_eq!((r.request, r.attempts), (REFERENCE, attempts));
+        assert_eq!((c.request, c.not_before), (RUN, r.not_before));
+
+        // not due before the backoff
+        advance(Duration::from_millis(backoff - 1)).await;
+        assert!(!r.is_due());
+        advance(Duration::from_millis(1)).await;
+        assert!(r.is_due() && c.is_due());
+        (reference, run) = (*r, *c);
+    }
+
+    // the third attempt is the last one
+    let mut batch = batch(policy, reference, run);
+    assert_aborted(&fail_next(&mut batch));
+    assert!(batch.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn default_policy_does_not_retry() {
+    let mut batch = batch(RetryPolicy::default(), Attempt::new(REFERENCE), Attempt::new(RUN));
+    assert_aborted(&fail_next(&mut batch));
+    assert!(batch.is_empty());
+}
@@ -0,0 +1,165 @@
+//! End-to-end harness: a `ChannelRouter` and its in-memory client, real devices, and pollers
+//! attached to simulated slaves through `SimulatedBus`es.
+//!
+//! Tests run on tokio's paused clock (`#[tokio::test(start_paused = true)]`), so ramps, delays
+//! and timeouts are deterministic and do not slow the test suite down.
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use lib::channel_router::{ChannelRouter, RouterHandle};
+use lib::devices::joystick::device::Joystick;
+use lib::devices::register_map::device::RegisterDevice;
+use lib::devices::vfd::device::Vfd;
+use lib::devices::vfd::encoder::{VfdCommands, FRECON};
+use lib::error::Error;
+use lib::group::DriveGroup;
+use lib::poller::ModbusPoller;
+use lib::simulator::bus::{FrameLog, SimulatedBus};
+use lib::simulator::{Faults, SharedSimulator, Simulator};
+use lib::soft_request::SoftResponse;
+use lib::traits::device::Device;
+use lib::traits::routing::Routing;
+use modbus_core::rtu::crc16;
+
+/// Poller timeout, in ms.
+pub const TIMEOUT: u64 = 50;
+
+/// FRECON registers, with the command and reference written in a single Write Multiple
+/// Registers frame.
+pub const FRECON_MULTIPLE: VfdCommands = VfdCommands { write_multiple: true, ..FRECON };
+
+/// Append the CRC to a request frame.
+pub fn frame(bytes: &[u8]) -> Vec<u8> {
+    let crc = crc16(bytes);
+    let mut frame = bytes.to_vec();
+    frame.extend_from_slice(&[(crc >> 8) as u8, (crc & 0xff) as u8]);
+    frame
+}
+
+/// Advance the (paused) clock, letting every task run meanwhile.
+pub async fn wait(ms: u64) {
+    tokio::time::sleep(Duration::from_millis(ms)).await;
+}
+
+/// Return the responses received so far by an updates subscriber.
+pub fn received(updates: &mut broadcast::Receiver<SoftResponse>) -> Vec<SoftResponse> {
+    std::iter::from_fn(|| updates.try_recv().ok()).collect()
+}
+
+/// A simulated serial bus, served by its own poller.
+pub struct Bus {
+    simulator: SharedSimulator,
+    frames: FrameLog,
+}
+
+impl Bus {
+    /// Return the request frames sent on the bus so far.
+    pub fn frames(&self) -> Vec<Vec<u8>> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Return the request frames sent on the bus so far, except the ones matching `skip`.
+    pub fn frames_except(&self, skip: &[u8]) -> Vec<Vec<u8>> {
+        self.frames().into_iter().filter(|f| f != skip).collect()
+    }
+
+    /// Forget the request frames sent so far.
+    pub fn clear(&self) {
+        self.frames.lock().unwrap().clear();
+    }
+
+    /// Send a request frame to the slaves besides the poller (e.g. a local operator), the
+    /// frame is not logged.
+    pub fn inject(&self, bytes: &[u8]) {
+        self.simulator.lock().unwrap().handle_frame(&frame(bytes));
+    }
+
+    /// Change the faults injected by the slave `id`.
+    pub fn faults(&self, id: u8, set: impl FnOnce(&mut Faults)) {
+        let mut simulator = self.simulator.lock().unwrap();
+        set(simulator.faults(id).expect("unknown slave"));
+    }
+}
+
+pub struct Harness {
+    router: ChannelRouter,
+    handle: RouterHandle,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let router = ChannelRouter::new();
+        let handle = router.handle();
+        Harness { router, handle }
+    }
+
+    /// Return a client of the router.
+    pub fn handle(&self) -> RouterHandle {
+        self.handle.clone()
+    }
+
+    fn poller<Req, Resp>(&self, simulator: Simulator) -> (ModbusPoller<Req, Resp>, Bus)
+    where
+        Req: Debug + Clone + Copy + Send + 'static,
+        Resp: Debug + Clone + Copy + Send + 'static,
+    {
+        let simulator = simulator.shared();
+        let mut bus = SimulatedBus::new(simulator.clone());
+        let (sender, receiver) = bus.channels().unwrap();
+        let frames = bus.frames();
+        bus.start();
+        let poller = ModbusPoller::with_channels(
+            "sim", sender, receiver, None, None, Some(TIMEOUT));
+        (poller, Bus { simulator, frames })
+    }
+
+    /// Attach `vfds` to the router and to a new bus serving `simulator`, then start them.
+    pub fn vfd_bus(&mut self, simulator: Simulator, vfds: Vec<Vfd>) -> Bus {
+        let (mut poller, bus) = self.poller(simulator);
+        for mut vfd in vfds {
+            vfd.connect_poller(&mut poller).unwrap();
+            vfd.connect_router(&mut self.router).unwrap();
+            vfd.start();
+        }
+        poller.start();
+        bus
+    }
+
+    /// Attach `joysticks` to the router and to a new bus serving `simulator`, then start them.
+    pub fn joystick_bus(&mut self, simulator: Simulator, joysticks: Vec<Joystick>) -> Bus {
+        let (mut poller, bus) = self.poller(simulator);
+        for mut joystick in joysticks {
+            joystick.connect_poller(&mut poller).unwrap();
+            joystick.connect_router(&mut self.router).unwrap();
+            joystick.start();
+        }
+        poller.start();
+        bus
+    }
+
+    /// Attach register map `devices` to the router and to a new bus serving `simulator`, then
+    /// start them.
+    pub fn register_bus(&mut self, simulator: Simulator, devices: Vec<RegisterDevice>) -> Bus {
+        let (mut poller, bus) = self.poller(simulator);
+        for mut device in devices {
+            device.connect_poller(&mut poller).unwrap();
+            device.connect_router(&mut self.router).unwrap();
+            device.start();
+        }
+        poller.start();
+        bus
+    }
+
+    /// Add a group of the devices already attached.
+    pub fn group(&mut self, group: DriveGroup) -> Result<(), Error> {
+        self.router.add_group(group)
+    }
+
+    /// Start the router, return its client.
+    pub fn start(self) -> RouterHandle {
+        self.router.start();
+        self.handle
+    }
+}
@@ -0,0 +1,9 @@
+//! Compile tests of `#[derive(DeviceConnectors)]`: the cases in `tests/derive/` either compile
+//! (`pass_*`) or fail with the error recorded next to them (`fail_*`).
+
+#[test]
+fn derive_device_connectors() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/derive/pass_*.rs");
+    cases.compile_fail("tests/derive/fail_*.rs");
+}
@@ -0,0 +1,13 @@
+use lib::devices::joystick::requests::{JoystickRequest, JoystickResponse};
+use lib::soft_request::{SoftRequest, SoftResponse};
+use lib::traits::device::DeviceConnectors;
+use lib::traits::polling::PollerConnector;
+use lib::traits::routing::RouterConnector;
+
+#[derive(DeviceConnectors)]
+struct Device {
+    router: Option<RouterConnector<SoftRequest, SoftResponse>>,
+    poller: Option<PollerConnector<JoystickRequest, JoystickResponse>>,
+}
+
+fn main() {}
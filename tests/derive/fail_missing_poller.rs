@@ -0,0 +1,12 @@
+use lib::modbus::ModbusId;
+use lib::soft_request::{SoftRequest, SoftResponse};
+use lib::traits::device::DeviceConnectors;
+use lib::traits::routing::RouterConnector;
+
+#[derive(DeviceConnectors)]
+struct Device {
+    id: ModbusId,
+    router: Option<RouterConnector<SoftRequest, SoftResponse>>,
+}
+
+fn main() {}
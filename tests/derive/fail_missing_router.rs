@@ -0,0 +1,12 @@
+use lib::devices::joystick::requests::{JoystickRequest, JoystickResponse};
+use lib::modbus::ModbusId;
+use lib::traits::device::DeviceConnectors;
+use lib::traits::polling::PollerConnector;
+
+#[derive(DeviceConnectors)]
+struct Device {
+    id: ModbusId,
+    poller: Option<PollerConnector<JoystickRequest, JoystickResponse>>,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use lib::devices::joystick::requests::{JoystickRequest, JoystickResponse};
+use lib::modbus::ModbusId;
+use lib::soft_request::{SoftRequest, SoftResponse};
+use lib::traits::device::DeviceConnectors;
+use lib::traits::polling::PollerConnector;
+use lib::traits::routing::RouterConnector;
+
+#[derive(DeviceConnectors)]
+struct Device {
+    #[device(identifier)]
+    id: ModbusId,
+    router: Option<RouterConnector<SoftRequest, SoftResponse>>,
+    poller: Option<PollerConnector<JoystickRequest, JoystickResponse>>,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use lib::devices::joystick::requests::{JoystickRequest, JoystickResponse};
+use lib::modbus::ModbusId;
+use lib::soft_request::{SoftRequest, SoftResponse};
+use lib::traits::device::DeviceConnectors;
+use lib::traits::polling::PollerConnector;
+use lib::traits::routing::RouterConnector;
+
+/// The library re-exported under another name.
+mod reexport {
+    pub use lib as router;
+}
+
+#[derive(DeviceConnectors)]
+#[device(crate = crate::reexport::router)]
+struct Device {
+    id: ModbusId,
+    router: Option<RouterConnector<SoftRequest, SoftResponse>>,
+    poller: Option<PollerConnector<JoystickRequest, JoystickResponse>>,
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use lib::devices::joystick::requests::{JoystickRequest, JoystickResponse};
+use lib::modbus::ModbusId;
+use lib::soft_request::{SoftRequest, SoftResponse};
+use lib::traits::device::DeviceConnectors;
+use lib::traits::polling::PollerConnector;
+use lib::traits::routing::RouterConnector;
+
+#[derive(DeviceConnectors)]
+struct Device {
+    #[device(id)]
+    logical: ModbusId,
+    #[device(slave_id)]
+    physical: ModbusId,
+    #[device(router)]
+    external: Option<RouterConnector<SoftRequest, SoftResponse>>,
+    #[device(poller)]
+    bus: Option<PollerConnector<JoystickRequest, JoystickResponse>>,
+}
+
+fn main() {}
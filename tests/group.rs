@@ -0,0 +1,220 @@
+mod common;
+
+use common::{frame, received, wait, Harness, FRECON_MULTIPLE};
+use lib::devices::vfd::device::Vfd;
+use lib::devices::vfd::limits::VfdLimits;
+use lib::devices::vfd::requests::VfdStatus;
+use lib::error::Error;
+use lib::group::DriveGroup;
+use lib::simulator::vfd::VfdSlave;
+use lib::simulator::Simulator;
+use lib::soft_request::{DeviceType, RejectReason, SoftError, SoftRequest, SoftResponse};
+use lib::traits::request::ResponseFn;
+
+/// FRECON_MULTIPLE command and reference write of `id`.
+fn cmd_ref_frame(id: u8, cmd: u8, reference: u16) -> Vec<u8> {
+    let [msb, lsb] = reference.to_be_bytes();
+    frame(&[id, 0x10, 0x20, 0x00, 0x00, 0x02, 0x04, 0x00, cmd, msb, lsb])
+}
+
+/// Return the command and reference writes, sorted (the devices of a port are polled in any
+/// order).
+fn writes(frames: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let mut writes: Vec<Vec<u8>> = frames.into_iter().filter(|f| f[1] == 0x10).collect();
+    writes.sort();
+    writes
+}
+
+#[tokio::test(start_paused = true)]
+async fn group_setpoints_are_written_back_to_back() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new()
+            .slave(10, VfdSlave::new(FRECON_MULTIPLE))
+            .slave(11, VfdSlave::new(FRECON_MULTIPLE))
+            .slave(12, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![
+            Vfd::new(10.into(), FRECON_MULTIPLE, true),
+            Vfd::new(11.into(), FRECON_MULTIPLE, true),
+            Vfd::new(12.into(), FRECON_MULTIPLE, true),
+        ],
+    );
+    harness.group(DriveGroup::new(100.into(), "line").member(11.into(), 0.5).member(10.into(), 1.0)).unwrap();
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+    bus.clear();
+
+    let response = client.request(SoftRequest::Run(100.into(), 1000)).await.unwrap();
+    assert_eq!(response, SoftResponse::None);
+    wait(200).await;
+    // no read between the writes of the members
+    let frames = bus.frames();
+    let first = frames.iter().position(|f| f[1] == 0x10).unwrap();
+    assert_eq!(writes(frames[first..first + 2].to_vec()), vec![cmd_ref_frame(10, 1, 1000), cmd_ref_frame(11, 1, 500)]);
+    assert_eq!(writes(frames).len(), 2);
+    assert!(received(&mut updates).contains(&SoftResponse::GroupDone(100.into(), 0, 2)));
+
+    wait(1000).await;
+    let response = client.request(SoftRequest::Status(11.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(11.into(), VfdStatus::Run(500)));
+    let response = client.request(SoftRequest::Status(12.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(12.into(), VfdStatus::Stop));
+
+    bus.clear();
+    client.request(SoftRequest::Stop(100.into())).await.unwrap();
+    wait(200).await;
+    assert_eq!(writes(bus.frames()), vec![cmd_ref_frame(10, 5, 0), cmd_ref_frame(11, 5, 0)]);
+    let done = SoftResponse::GroupDone(100.into(), 0, 2);
+    assert!(received(&mut updates).contains(&done));
+    assert_eq!(done.to_raw().unwrap()[..6], [100, 2, 11, 0, 2, 0]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn same_setpoints_are_broadcast() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)).slave(11, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, true), Vfd::new(11.into(), FRECON_MULTIPLE, true)],
+    );
+    harness.group(
+        DriveGroup::new(100.into(), "line").member(10.into(), 1.0).member(11.into(), 1.0).broadcast(true),
+    ).unwrap();
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+    bus.clear();
+
+    client.request(SoftRequest::Run(100.into(), 1000)).await.unwrap();
+    wait(1000).await;
+    assert_eq!(writes(bus.frames()), vec![cmd_ref_frame(0, 1, 1000)]);
+    assert!(received(&mut updates).contains(&SoftResponse::GroupDone(100.into(), 0, 2)));
+    for id in [10, 11] {
+        let response = client.request(SoftRequest::Status(id.into())).await.unwrap();
+        assert_eq!(response, SoftResponse::Status(id.into(), VfdStatus::Run(1000)));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn broadcast_needs_the_whole_port() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new()
+            .slave(10, VfdSlave::new(FRECON_MULTIPLE))
+            .slave(11, VfdSlave::new(FRECON_MULTIPLE))
+            .slave(12, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![
+            Vfd::new(10.into(), FRECON_MULTIPLE, false),
+            Vfd::new(11.into(), FRECON_MULTIPLE, false),
+            Vfd::new(12.into(), FRECON_MULTIPLE, false),
+        ],
+    );
+    harness.group(
+        DriveGroup::new(100.into(), "line").member(10.into(), 1.0).member(11.into(), 1.0).broadcast(true),
+    ).unwrap();
+    let client = harness.start();
+    client.request(SoftRequest::Run(100.into(), 1000)).await.unwrap();
+    wait(200).await;
+    // the drive 12 is not a member
+    assert_eq!(bus.frames().len(), 2);
+    assert_eq!(writes(bus.frames()), vec![cmd_ref_frame(10, 1, 1000), cmd_ref_frame(11, 1, 1000)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn group_spans_ports_and_reports_members_not_applied() {
+    let mut harness = Harness::new();
+    let left = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, false)],
+    );
+    let right = harness.vfd_bus(
+        Simulator::new().slave(20, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(20.into(), FRECON_MULTIPLE, false).limits(VfdLimits::new().reference(0, 800))],
+    );
+    harness.group(DriveGroup::new(100.into(), "line").member(10.into(), 1.0).member(20.into(), 1.0)).unwrap();
+    let client = harness.start();
+    let mut updates = client.updates();
+
+    client.request(SoftRequest::Run(100.into(), 1000)).await.unwrap();
+    wait(200).await;
+    assert_eq!(left.frames(), vec![cmd_ref_frame(10, 1, 1000)]);
+    assert!(right.frames().is_empty());
+    let updates = received(&mut updates);
+    let rejected = SoftResponse::Error(20.into(), DeviceType::Vfd, SoftError::Rejected(RejectReason::OutOfRange));
+    assert!(updates.contains(&rejected));
+    assert!(updates.contains(&SoftResponse::GroupDone(100.into(), 1, 2)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn out_of_range_member_reference_is_rejected() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)).slave(11, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, false), Vfd::new(11.into(), FRECON_MULTIPLE, false)],
+    );
+    harness.group(DriveGroup::new(100.into(), "line").member(10.into(), 1.0).member(11.into(), 2.0)).unwrap();
+    let client = harness.start();
+
+    let response = client.request(SoftRequest::Run(100.into(), 20000)).await;
+    assert!(matches!(response, Err(Error::WrongRefValue)));
+    wait(200).await;
+    assert!(writes(bus.frames()).is_empty());
+
+    client.request(SoftRequest::Run(100.into(), 10000)).await.unwrap();
+    wait(200).await;
+    assert_eq!(writes(bus.frames()), vec![cmd_ref_frame(10, 1, 10000), cmd_ref_frame(11, 1, 20000)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn invalid_groups_are_rejected() {
+    let mut harness = Harness::new();
+    harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, false)],
+    );
+    let invalid = |r: Result<(), Error>| matches!(r, Err(Error::InvalidGroup(_)));
+    assert!(invalid(harness.group(DriveGroup::new(100.into(), "empty"))));
+    assert!(invalid(harness.group(DriveGroup::new(100.into(), "unknown").member(11.into(), 1.0))));
+    assert!(invalid(harness.group(DriveGroup::new(100.into(), "twice").member(10.into(), 1.0).member(10.into(), 2.0))));
+    assert!(invalid(harness.group(DriveGroup::new(100.into(), "ratio").member(10.into(), f32::NAN))));
+    assert_eq!(
+        harness.group(DriveGroup::new(10.into(), "clash").member(10.into(), 1.0)),
+        Err(Error::IdAlreadyRegistered(10.into())),
+    );
+    assert_eq!(harness.group(DriveGroup::new(100.into(), "line").member(10.into(), 1.0)), Ok(()));
+    assert_eq!(
+        harness.group(DriveGroup::new(100.into(), "again").member(10.into(), 1.0)),
+        Err(Error::IdAlreadyRegistered(100.into())),
+    );
+
+    // requests other than run and stop are not supported by groups
+    let client = harness.start();
+    assert_eq!(client.request(SoftRequest::Status(100.into())).await, Err(Error::WrongFunctionType));
+}
+
+#[tokio::test(start_paused = true)]
+async fn superseded_group_commands_are_not_reported() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)).slave(11, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, true), Vfd::new(11.into(), FRECON_MULTIPLE, true)],
+    );
+    harness.group(DriveGroup::new(100.into(), "line").member(10.into(), 1.0).member(11.into(), 1.0)).unwrap();
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+    bus.clear();
+
+    client.request(SoftRequest::Run(100.into(), 1000)).await.unwrap();
+    client.request(SoftRequest::Run(100.into(), 500)).await.unwrap();
+    wait(1000).await;
+    let done: Vec<SoftResponse> = received(&mut updates)
+        .into_iter()
+        .filter(|r| matches!(r, SoftResponse::GroupDone(_, _, _)))
+        .collect();
+    assert_eq!(done, vec![SoftResponse::GroupDone(100.into(), 0, 2)]);
+    for id in [10, 11] {
+        let response = client.request(SoftRequest::Status(id.into())).await.unwrap();
+        assert_eq!(response, SoftResponse::Status(id.into(), VfdStatus::Run(500)));
+    }
+}
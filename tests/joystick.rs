@@ -0,0 +1,25 @@
+mod common;
+
+use common::{frame, wait, Harness};
+use lib::devices::joystick::device::{Joystick, JoystickType};
+use lib::devices::joystick::requests::JoystickStatus;
+use lib::simulator::joystick::JoystickSlave;
+use lib::simulator::Simulator;
+use lib::state::CommHealth;
+
+#[tokio::test(start_paused = true)]
+async fn positions_are_published() {
+    let mut harness = Harness::new();
+    let joystick = Joystick::new(3.into(), JoystickType::JoystickWithThumb);
+    let mut state = joystick.subscribe();
+    let slave = JoystickSlave::new(JoystickType::JoystickWithThumb)
+        .positions([0x1000, 0x2000, 0x3000, 0x4000, 1]);
+    let bus = harness.joystick_bus(Simulator::new().slave(3, slave), vec![joystick]);
+    harness.start();
+
+    wait(100).await;
+    assert_eq!(bus.frames().first(), Some(&frame(&[3, 0x03, 0x40, 0x01, 0x00, 0x05])));
+    let state = state.borrow_and_update();
+    assert_eq!(state.status, JoystickStatus::JoystickWithThumb([0x1000, 0x2000, 0x3000, 0x4000, 1]));
+    assert_eq!(state.health, CommHealth::Ok);
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::PathBuf;
+use lib::devices::vfd::encoder::{VfdCommands, FRECON, MEGMEET};
+use lib::devices::vfd::encoding::{SignScheme, Unit};
+use lib::devices::vfd::profile::VfdProfiles;
+use lib::error::Error;
+
+/// Create an empty profile directory for the test `name`.
+fn profile_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vfd_profiles_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const DRIVE: &str = r#"
+cmd_address = 0x1000
+ref_address = 0x1001
+status_address = 0x1100
+fw_value = 1
+rv_value = 2
+stop_value = 3
+fault_address = 0x1101
+"#;
+
+#[test]
+fn bundled_profiles_match_builtin_commands() {
+    let profiles = VfdProfiles::bundled();
+    assert_eq!(profiles.names(), vec!["frecon", "megmeet"]);
+    assert_eq!(profiles.get("frecon"), Ok(FRECON));
+    assert_eq!(profiles.get("megmeet"), Ok(MEGMEET));
+    assert_eq!(profiles.get("abb"), Err(Error::UnknownVfdProfile("abb".to_string())));
+}
+
+#[test]
+fn profiles_are_loaded_from_directory() {
+    let dir = profile_dir("load");
+    fs::write(dir.join("drive.toml"), DRIVE).unwrap();
+    fs::write(
+        dir.join("frecon.json"),
+        r#"{"cmd_address": 8192, "ref_address": 8193, "status_address": 12288,
+            "fw_value": 1, "rv_value": 2, "stop_value": 5, "fault_address": 32768}"#,
+    ).unwrap();
+    fs::write(dir.join("README"), "not a profile").unwrap();
+
+    let profiles = VfdProfiles::load(&dir).unwrap();
+    assert_eq!(profiles.names(), vec!["drive", "frecon", "megmeet"]);
+    let drive = profiles.get("drive").unwrap();
+    assert_eq!(drive.status_address, 0x1100);
+    assert_eq!(drive.fault_address, Some(0x1101));
+    assert!(!drive.write_multiple);
+    // replaces the bundled profile
+    let frecon = profiles.get("frecon").unwrap();
+    assert_eq!(frecon.fault_address, Some(0x8000));
+    assert_eq!(frecon.reset_value, None);
+}
+
+#[test]
+fn invalid_profiles_are_rejected() {
+    let invalid = |description: &str| matches!(VfdCommands::from_toml(description), Err(Error::InvalidVfdProfile(_)));
+    assert!(invalid("cmd_address = 0x1000"));
+    assert!(invalid(&format!("{}\nunknown_register = 1", DRIVE)));
+    assert!(invalid(&DRIVE.replace("stop_value = 3", "stop_value = 2")));
+    assert!(invalid(&DRIVE.replace("ref_address = 0x1001", "ref_address = 0x1000")));
+    assert!(invalid(&DRIVE.replace("fault_address = 0x1101", "fault_address = 0x1100")));
+    assert!(invalid(&format!("{}\nreset_value = 1", DRIVE)));
+    assert!(invalid(&format!("{}\nreset_address = 0x1200", DRIVE)));
+    assert!(!invalid(&format!("{}\nreset_address = 0x1200\nreset_value = 1", DRIVE)));
+
+    let dir = profile_dir("invalid");
+    fs::write(dir.join("drive.toml"), DRIVE.replace("rv_value = 2", "rv_value = 1")).unwrap();
+    match VfdProfiles::load(&dir) {
+        Err(Error::InvalidVfdProfile(e)) => assert!(e.contains("drive.toml"), "{}", e),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn duplicate_profile_names_are_rejected() {
+    let dir = profile_dir("duplicate");
+    fs::write(dir.join("drive.toml"), DRIVE).unwrap();
+    fs::write(
+        dir.join("drive.json"),
+        r#"{"cmd_address": 1, "ref_address": 2, "status_address": 3, "fw_value": 1, "rv_value": 2, "stop_value": 3}"#,
+    ).unwrap();
+    assert!(matches!(VfdProfiles::load(&dir), Err(Error::InvalidVfdProfile(_))));
+}
+
+#[test]
+fn encoding_is_read_from_profile() {
+    let description = format!("{}\n[encoding]\nsign = {{ direction_bit = 14 }}\nscale = 0.1\nunit = \"hz\"", DRIVE);
+    let encoding = VfdCommands::from_toml(&description).unwrap().encoding;
+    assert_eq!(encoding.sign, SignScheme::DirectionBit(14));
+    assert_eq!(encoding.unit, Unit::Hz);
+    assert_eq!(encoding.decode(0x4000 | 500), -50.0);
+    assert_eq!(encoding.encode(-50.0), Some(0x4000 | 500));
+    // the magnitude overlaps the direction bit
+    assert_eq!(encoding.encode(1700.0), None);
+
+    assert_eq!(FRECON.encoding.decode(0x8000 | 1500), -1500.0);
+    assert_eq!(FRECON.encoding.encode(-1500.0), Some(0x8000 | 1500));
+
+    let invalid = |encoding: &str| {
+        let description = format!("{}\n[encoding]\n{}", DRIVE, encoding);
+        matches!(VfdCommands::from_toml(&description), Err(Error::InvalidVfdProfile(_)))
+    };
+    assert!(invalid("scale = 0.0"));
+    assert!(invalid("sign = { direction_bit = 16 }"));
+    assert!(invalid("sign = \"ones_complement\""));
+}
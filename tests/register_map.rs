@@ -0,0 +1,212 @@
+mod common;
+
+use common::{frame, wait, Harness};
+use lib::devices::register_map::device::RegisterDevice;
+use lib::devices::register_map::map::{DataType, Point, RegisterKind, RegisterMap};
+use lib::error::Error;
+use lib::simulator::registers::RegisterSlave;
+use lib::simulator::Simulator;
+use lib::soft_request::{DeviceType, RejectReason, SoftError, SoftRequest, SoftResponse};
+use lib::traits::request::ResponseFn;
+
+const MAP: &str = r#"
+[[points]]
+name = "temperature"
+address = 0x0010
+kind = "input"
+data_type = "i16"
+scale = 0.1
+offset = -40
+period_ms = 1000
+
+[[points]]
+name = "setpoint"
+address = 0x0020
+writable = true
+
+[[points]]
+name = "status"
+address = 0x0030
+
+[[points]]
+name = "counter"
+address = 0x0040
+data_type = "u32"
+writable = true
+"#;
+
+/// Return a point named `name` at `address`, with the default settings.
+fn point(name: &str, address: u16) -> Point {
+    Point {
+        name: name.to_string(),
+        address,
+        kind: RegisterKind::Holding,
+        data_type: DataType::U16,
+        scale: 1.0,
+        offset: 0.0,
+        period_ms: None,
+        writable: false,
+    }
+}
+
+/// Return the rejection of a request to the device `id`.
+fn rejected(id: u8, reason: RejectReason) -> SoftResponse {
+    SoftResponse::Error(id.into(), DeviceType::RegisterMap, SoftError::Rejected(reason))
+}
+
+/// Return a simulated slave holding the registers of `MAP`.
+fn slave() -> RegisterSlave {
+    RegisterSlave::new()
+        .input(0x0010, 650)
+        .holding(0x0020, 7)
+        .holding(0x0030, 1)
+        .holding(0x0040, 0)
+        .holding(0x0041, 0)
+}
+
+#[test]
+fn map_is_parsed_from_toml() {
+    let map = RegisterMap::from_toml(MAP).unwrap();
+    assert_eq!(map.points.len(), 4);
+    assert_eq!(map.index_of("temperature"), Some(0));
+    assert_eq!(map.index_of("counter"), Some(3));
+    assert_eq!(map.index_of("missing"), None);
+
+    let temperature = map.point(0).unwrap();
+    assert_eq!(temperature.kind, RegisterKind::Input);
+    assert_eq!(temperature.data_type, DataType::I16);
+    assert_eq!(temperature.period_ms, Some(1000));
+    assert!(!temperature.writable);
+
+    // defaults
+    let setpoint = map.point(1).unwrap();
+    assert_eq!(setpoint.kind, RegisterKind::Holding);
+    assert_eq!(setpoint.data_type, DataType::U16);
+    assert_eq!((setpoint.scale, setpoint.offset, setpoint.period_ms), (1.0, 0.0, None));
+    assert!(map.point(4).is_none());
+}
+
+#[test]
+fn invalid_maps_are_rejected() {
+    let invalid = |points: Vec<Point>| matches!(RegisterMap::new(points), Err(Error::InvalidRegisterMap(_)));
+
+    assert!(invalid(vec![]));
+    assert!(invalid((0..129).map(|i| point(&format!("p{}", i), i)).collect()));
+    assert!(RegisterMap::new((0..128).map(|i| point(&format!("p{}", i), i)).collect()).is_ok());
+    assert!(invalid(vec![point("a", 0), point("a", 1)]));
+    assert!(invalid(vec![Point { kind: RegisterKind::Input, writable: true, ..point("a", 0) }]));
+    assert!(invalid(vec![Point { scale: 0.0, ..point("a", 0) }]));
+    assert!(invalid(vec![Point { scale: f64::NAN, ..point("a", 0) }]));
+    assert!(invalid(vec![Point { offset: f64::INFINITY, ..point("a", 0) }]));
+    assert!(invalid(vec![Point { period_ms: Some(0), ..point("a", 0) }]));
+
+    // TOML errors and validation errors are reported alike
+    assert!(matches!(RegisterMap::from_toml("[[points]]\nname = \"a\""), Err(Error::InvalidRegisterMap(_))));
+    assert!(matches!(RegisterMap::from_toml("points = []"), Err(Error::InvalidRegisterMap(_))));
+    let input = "[[points]]\nname = \"a\"\naddress = 1\nkind = \"input\"\nwritable = true";
+    assert!(matches!(RegisterMap::from_toml(input), Err(Error::InvalidRegisterMap(_))));
+}
+
+#[test]
+fn values_are_scaled_both_ways() {
+    let map = RegisterMap::from_toml(MAP).unwrap();
+    let temperature = map.point(0).unwrap();
+    assert_eq!(temperature.to_soft(650), 25);
+    assert_eq!(temperature.to_soft(-100i16 as u16 as u32), -50);
+    assert_eq!(temperature.from_soft(25), Some(650));
+    assert_eq!(temperature.from_soft(-50), Some(-100i16 as u16 as u32));
+    // out of the i16 range
+    assert_eq!(temperature.from_soft(4000), None);
+
+    let counter = map.point(3).unwrap();
+    assert_eq!(counter.to_soft(70000), 65535);
+    assert_eq!(counter.from_soft(65535), Some(65535));
+    assert_eq!(counter.from_soft(-1), None);
+
+    let setpoint = map.point(1).unwrap();
+    for value in [0, 1, 1234, 65535] {
+        assert_eq!(setpoint.from_soft(value).map(|raw| setpoint.to_soft(raw)), Some(value));
+    }
+}
+
+#[test]
+fn point_requests_are_encoded_with_signed_values() {
+    let request = frame(&[20, 5, 1, 3, 0, 0]);
+    assert!(matches!(SoftRequest::try_from(request.as_slice()), Ok(SoftRequest::ReadPoint(_, 3))));
+    let request = frame(&[20, 5, 2, 0x80, 0x01, 0xF4]);
+    assert!(matches!(SoftRequest::try_from(request.as_slice()), Ok(SoftRequest::WritePoint(_, 0, -500))));
+    let request = frame(&[20, 5, 3, 0, 0, 0]);
+    assert!(matches!(SoftRequest::try_from(request.as_slice()), Err(Error::WrongFunctionType)));
+
+    assert_eq!(SoftResponse::Point(20.into(), 0, -125).to_raw().unwrap()[..6], [20, 6, 1, 0x80, 0, 125]);
+    assert_eq!(SoftResponse::Point(20.into(), 2, 300).to_raw().unwrap()[..6], [20, 6, 1, 2, 0x01, 0x2C]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn points_are_read_and_written() {
+    let mut harness = Harness::new();
+    let bus = harness.register_bus(
+        Simulator::new().slave(20, slave()),
+        vec![RegisterDevice::new(20.into(), RegisterMap::from_toml(MAP).unwrap())],
+    );
+    let client = harness.start();
+    wait(100).await;
+    let frames = bus.frames();
+    assert!(frames.contains(&frame(&[20, 0x04, 0x00, 0x10, 0x00, 0x01])));
+    assert!(frames.contains(&frame(&[20, 0x03, 0x00, 0x20, 0x00, 0x01])));
+    assert!(frames.contains(&frame(&[20, 0x03, 0x00, 0x40, 0x00, 0x02])));
+
+    let response = client.request(SoftRequest::ReadPoint(20.into(), 0)).await.unwrap();
+    assert_eq!(response, SoftResponse::Point(20.into(), 0, 25));
+    bus.clear();
+
+    let response = client.request(SoftRequest::WritePoint(20.into(), 1, 1234)).await.unwrap();
+    assert_eq!(response, SoftResponse::Point(20.into(), 1, 1234));
+    assert!(bus.frames().contains(&frame(&[20, 0x06, 0x00, 0x20, 0x04, 0xD2])));
+    let response = client.request(SoftRequest::ReadPoint(20.into(), 1)).await.unwrap();
+    assert_eq!(response, SoftResponse::Point(20.into(), 1, 1234));
+
+    // 32 bits points are written with a multiple registers write
+    let response = client.request(SoftRequest::WritePoint(20.into(), 3, 65535)).await.unwrap();
+    assert_eq!(response, SoftResponse::Point(20.into(), 3, 65535));
+    assert!(bus.frames().contains(&frame(&[20, 0x10, 0x00, 0x40, 0x00, 0x02, 0x04, 0x00, 0x00, 0xFF, 0xFF])));
+}
+
+#[tokio::test(start_paused = true)]
+async fn invalid_writes_are_rejected() {
+    let mut harness = Harness::new();
+    let bus = harness.register_bus(
+        Simulator::new().slave(20, slave()),
+        vec![RegisterDevice::new(20.into(), RegisterMap::from_toml(MAP).unwrap())],
+    );
+    let client = harness.start();
+    wait(100).await;
+    bus.clear();
+
+    let response = client.request(SoftRequest::WritePoint(20.into(), 2, 1)).await.unwrap();
+    assert_eq!(response, rejected(20, RejectReason::NotWritable));
+    let response = client.request(SoftRequest::WritePoint(20.into(), 4, 1)).await.unwrap();
+    assert_eq!(response, rejected(20, RejectReason::UnknownPoint));
+    let response = client.request(SoftRequest::ReadPoint(20.into(), 4)).await.unwrap();
+    assert_eq!(response, rejected(20, RejectReason::UnknownPoint));
+    let response = client.request(SoftRequest::WritePoint(20.into(), 1, -1)).await.unwrap();
+    assert_eq!(response, rejected(20, RejectReason::OutOfRange));
+    wait(100).await;
+    assert!(bus.frames().iter().all(|f| f[1] != 0x06 && f[1] != 0x10));
+}
+
+#[tokio::test(start_paused = true)]
+async fn points_are_read_at_their_period() {
+    let mut harness = Harness::new();
+    let bus = harness.register_bus(
+        Simulator::new().slave(20, slave()),
+        vec![RegisterDevice::new(20.into(), RegisterMap::from_toml(MAP).unwrap())],
+    );
+    let _client = harness.start();
+    wait(2500).await;
+    let frames = bus.frames();
+    let count = |f: Vec<u8>| frames.iter().filter(|r| **r == f).count();
+    // the temperature has a 1 s period, the status is read on every poll
+    assert_eq!(count(frame(&[20, 0x04, 0x00, 0x10, 0x00, 0x01])), 3);
+    assert!(count(frame(&[20, 0x03, 0x00, 0x30, 0x00, 0x01])) > 10);
+}
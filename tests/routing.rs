@@ -0,0 +1,113 @@
+mod common;
+
+use common::frame;
+use lib::group::{DriveGroup, Groups};
+use lib::modbus::ModbusId;
+use lib::soft_request::{DeviceType, RejectReason, SoftError, SoftRequest, SoftResponse};
+use lib::traits::request::ResponseFn;
+use lib::traits::routing::{RouterConnector, Routing};
+
+#[derive(Debug, Default)]
+/// A router fed with raw frames, that records the requests routed to its devices and the raw
+/// responses written back to the client.
+struct RawRouter {
+    devices: Vec<ModbusId>,
+    groups: Groups,
+    requests: Vec<SoftRequest>,
+    responses: Vec<Vec<u8>>,
+}
+
+impl RawRouter {
+    fn new(devices: &[u8]) -> Self {
+        RawRouter {
+            devices: devices.iter().map(|id| (*id).into()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Routing<SoftRequest, SoftResponse> for RawRouter {
+    fn get_connector(&mut self, _id: ModbusId) -> Option<RouterConnector<SoftRequest, SoftResponse>> {
+        None
+    }
+
+    fn transmit_request(&mut self, request: SoftRequest) {
+        self.requests.push(request);
+    }
+
+    fn transmit_response(&mut self, raw: Vec<u8>) {
+        self.responses.push(raw);
+    }
+
+    fn devices_count(&self) -> usize {
+        self.devices.len()
+    }
+
+    fn devices_ids(&self) -> Vec<ModbusId> {
+        self.devices.clone()
+    }
+
+    fn try_receive_request(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn try_receive_response(&mut self) -> Option<SoftResponse> {
+        None
+    }
+
+    fn groups(&mut self) -> &mut Groups {
+        &mut self.groups
+    }
+}
+
+/// Return the raw rejection of a request to the drive `id`.
+fn rejected(id: u8, reason: RejectReason) -> Vec<u8> {
+    SoftResponse::Error(id.into(), DeviceType::Vfd, SoftError::Rejected(reason)).to_raw().unwrap()
+}
+
+#[test]
+fn out_of_range_reference_is_rejected() {
+    let mut router = RawRouter::new(&[10]);
+    router.handle_raw_request(frame(&[10, 1, 1, 0, 0x80, 0x00]));
+    assert!(router.requests.is_empty());
+    assert_eq!(router.responses, vec![rejected(10, RejectReason::OutOfRange)]);
+    assert_eq!(router.responses[0][..6], [10, 2, 0x80, 3, 3, 0]);
+
+    router.responses.clear();
+    router.handle_raw_request(frame(&[10, 1, 1, 1, 0x7F, 0xFF]));
+    assert!(matches!(router.requests[..], [SoftRequest::Run(_, -32767)]));
+    assert!(router.responses.is_empty());
+}
+
+#[test]
+fn corrupted_frames_are_not_answered() {
+    let mut router = RawRouter::new(&[10]);
+    let mut request = frame(&[10, 1, 1, 0, 0x80, 0x00]);
+    request[7] ^= 0xff;
+    router.handle_raw_request(request);
+    // invalid sign
+    router.handle_raw_request(frame(&[10, 1, 1, 2, 0x00, 0x10]));
+    assert!(router.requests.is_empty());
+    assert!(router.responses.is_empty());
+}
+
+#[test]
+fn invalid_group_requests_are_rejected() {
+    let mut router = RawRouter::new(&[10, 11]);
+    router.add_group(DriveGroup::new(100.into(), "line").member(10.into(), 1.0).member(11.into(), 2.0)).unwrap();
+
+    // not supported by groups
+    router.handle_raw_request(frame(&[100, 1, 3, 0, 0, 0]));
+    assert_eq!(router.responses, vec![rejected(100, RejectReason::NotAvailable)]);
+
+    // out of range for the member 11
+    router.responses.clear();
+    router.handle_raw_request(frame(&[100, 1, 1, 1, 0x40, 0x00]));
+    assert_eq!(router.responses, vec![rejected(100, RejectReason::OutOfRange)]);
+    assert!(router.requests.is_empty());
+
+    router.responses.clear();
+    router.handle_raw_request(frame(&[100, 1, 1, 1, 0x3F, 0xFF]));
+    assert!(router.responses.is_empty());
+    assert!(matches!(router.requests[..], [SoftRequest::GroupRun(_, _, -16383), SoftRequest::GroupRun(_, _, -32766)]));
+}
@@ -0,0 +1,944 @@
+mod common;
+
+use std::time::Duration;
+use common::{frame, received, wait, Harness, FRECON_MULTIPLE, TIMEOUT};
+use lib::batch::{RetryMode, RetryPolicy};
+use lib::devices::vfd::device::Vfd;
+use lib::devices::vfd::encoder::{VfdCommands, FRECON};
+use lib::devices::vfd::encoding::{SignScheme, Unit, ValueEncoding};
+use lib::devices::vfd::limits::VfdLimits;
+use lib::devices::vfd::nameplate::Nameplate;
+use lib::devices::vfd::ramp::{Ramp, RampConfig, RampProgress};
+use lib::devices::vfd::reconcile::ReconcileConfig;
+use lib::devices::vfd::requests::{Dir, ParameterBlock, Telemetry, VfdStatus};
+use lib::error::Error;
+use lib::modbus::{FunctionType, ModbusException};
+use lib::simulator::vfd::VfdSlave;
+use lib::simulator::Simulator;
+use lib::soft_request::{DeviceEvent, DeviceType, RejectReason, SoftError, SoftRequest, SoftResponse};
+use lib::state::CommHealth;
+use lib::traits::device::Device;
+use lib::traits::request::ResponseFn;
+
+/// Read of the FRECON status register of slave 10.
+fn status_frame() -> Vec<u8> {
+    frame(&[10, 0x03, 0x30, 0x00, 0x00, 0x01])
+}
+
+/// Return the rejection of a request to the drive `id`.
+fn rejected(id: u8, reason: RejectReason) -> SoftResponse {
+    SoftResponse::Error(id.into(), DeviceType::Vfd, SoftError::Rejected(reason))
+}
+
+/// Return the references written to a FRECON drive with single register writes.
+fn written_references(frames: Vec<Vec<u8>>) -> Vec<u16> {
+    frames.into_iter()
+        .filter(|f| f[1..4] == [0x06, 0x20, 0x01])
+        .map(|f| u16::from_be_bytes([f[4], f[5]]))
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_writes_cmd_and_ref_then_reports_status() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, true)],
+    );
+    let client = harness.start();
+    wait(100).await;
+    assert_eq!(bus.frames().last(), Some(&status_frame()));
+    bus.clear();
+
+    let response = client.request(SoftRequest::Run(10.into(), 1500)).await.unwrap();
+    assert_eq!(response, SoftResponse::Accepted(10.into(), FunctionType::Run));
+    assert_eq!(response.to_raw().unwrap()[..6], [10, 2, 1, 0, 0, 0]);
+    wait(100).await;
+    assert_eq!(
+        bus.frames_except(&status_frame()),
+        vec![frame(&[10, 0x10, 0x20, 0x00, 0x00, 0x02, 0x04, 0x00, 0x01, 0x05, 0xDC])],
+    );
+
+    // default acceleration reaches the reference within 1s
+    wait(1000).await;
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Run(1500)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn ref_is_written_before_cmd_without_write_multiple() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), -200)).await.unwrap();
+    wait(100).await;
+    assert_eq!(
+        bus.frames(),
+        vec![
+            frame(&[10, 0x06, 0x20, 0x01, 0x00, 0xC8]),
+            frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x02]),
+        ],
+    );
+}
+
+/// Write of the FRECON command register of slave 10.
+fn cmd_frame(cmd: u8) -> Vec<u8> {
+    frame(&[10, 0x06, 0x20, 0x00, 0x00, cmd])
+}
+
+/// Write of the FRECON reference register of slave 10.
+fn ref_frame(reference: u16) -> Vec<u8> {
+    let [msb, lsb] = reference.to_be_bytes();
+    frame(&[10, 0x06, 0x20, 0x01, msb, lsb])
+}
+
+#[tokio::test(start_paused = true)]
+async fn newer_setpoint_replaces_the_pending_one() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false)],
+    );
+    let client = harness.start();
+
+    // within one poll cycle
+    let (first, second) = tokio::join!(
+        client.request(SoftRequest::Run(10.into(), 500)),
+        client.request(SoftRequest::Run(10.into(), 800)),
+    );
+    assert!(first.is_ok() && second.is_ok());
+    wait(200).await;
+    assert_eq!(bus.frames(), vec![ref_frame(800), cmd_frame(1)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn stop_overrides_the_pending_run() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false)],
+    );
+    let client = harness.start();
+
+    let (first, second, stop) = tokio::join!(
+        client.request(SoftRequest::Run(10.into(), 500)),
+        client.request(SoftRequest::Run(10.into(), 800)),
+        client.request(SoftRequest::Stop(10.into())),
+    );
+    assert!(first.is_ok() && second.is_ok() && stop.is_ok());
+    wait(200).await;
+    assert_eq!(bus.frames(), vec![cmd_frame(5), ref_frame(0)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_queued_behind_a_stop_is_sent_on_the_next_cycle() {
+    let policy = RetryPolicy::new(2, RetryMode::NextCycle, Some(Duration::from_millis(300)));
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false).retry_policy(policy)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    wait(200).await;
+    bus.faults(10, |f| {
+        f.exception = Some(ModbusException::ServerDeviceBusy);
+        f.exception_address = Some(0x2000);
+    });
+    bus.clear();
+
+    // the stop fails and waits for its retry, the run is queued behind it
+    client.request(SoftRequest::Stop(10.into())).await.unwrap();
+    wait(100).await;
+    client.request(SoftRequest::Run(10.into(), 300)).await.unwrap();
+    bus.faults(10, |f| f.exception = None);
+    wait(600).await;
+    assert_eq!(
+        bus.frames(),
+        vec![cmd_frame(5), ref_frame(0), cmd_frame(5), ref_frame(300), cmd_frame(1)],
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn unchanged_setpoint_is_not_written_again() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    wait(200).await;
+    bus.clear();
+
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    wait(200).await;
+    assert!(bus.frames().is_empty());
+
+    // only the reference changes
+    client.request(SoftRequest::Run(10.into(), 600)).await.unwrap();
+    wait(200).await;
+    assert_eq!(bus.frames(), vec![ref_frame(600)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_is_not_written_if_the_reference_write_fails() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, true)],
+    );
+    bus.faults(10, |f| {
+        f.exception = Some(ModbusException::IllegalDataValue);
+        f.exception_address = Some(0x2001);
+    });
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 200)).await.unwrap();
+    wait(500).await;
+    let frames = bus.frames();
+    assert!(frames.contains(&frame(&[10, 0x06, 0x20, 0x01, 0x00, 0xC8])));
+    assert!(frames.iter().all(|f| f[1..4] != [0x06, 0x20, 0x00]));
+
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Stop));
+}
+
+/// Let the reference write fail with a busy slave, and check it is retried after the backoff
+/// before the run command is dropped.
+async fn reference_write_is_retried(mode: RetryMode) {
+    let policy = RetryPolicy::new(3, mode, Some(Duration::from_millis(100)));
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false).retry_policy(policy)],
+    );
+    bus.faults(10, |f| {
+        f.exception = Some(ModbusException::ServerDeviceBusy);
+        f.exception_address = Some(0x2001);
+    });
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+    bus.clear();
+
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    // sent at 0, then retried after 100ms and 200ms
+    let mut sent = vec![];
+    for _ in 0..4 {
+        wait(50).await;
+        sent.push(bus.frames().len());
+        wait(50).await;
+    }
+    assert_eq!(sent, vec![1, 2, 2, 3]);
+    wait(1000).await;
+    assert_eq!(bus.frames(), vec![ref_frame(500); 3]);
+    let exception = SoftError::Exception(ModbusException::ServerDeviceBusy);
+    assert!(received(&mut updates).contains(&SoftResponse::Error(10.into(), DeviceType::Vfd, exception)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn immediate_retry_waits_the_backoff() {
+    reference_write_is_retried(RetryMode::Immediate).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn next_cycle_retry_waits_the_backoff() {
+    reference_write_is_retried(RetryMode::NextCycle).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn stop_writes_stop_cmd_and_drive_stops() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, true)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 1500)).await.unwrap();
+    wait(1000).await;
+    bus.clear();
+
+    client.request(SoftRequest::Stop(10.into())).await.unwrap();
+    wait(100).await;
+    assert_eq!(
+        bus.frames_except(&status_frame()),
+        vec![frame(&[10, 0x10, 0x20, 0x00, 0x00, 0x02, 0x04, 0x00, 0x05, 0x00, 0x00])],
+    );
+    wait(1000).await;
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Stop));
+}
+
+#[tokio::test(start_paused = true)]
+async fn silent_slave_reports_comm_failure() {
+    let mut harness = Harness::new();
+    let vfd = Vfd::new(10.into(), FRECON, true);
+    let mut state = vfd.subscribe();
+    let bus = harness.vfd_bus(Simulator::new().slave(10, VfdSlave::new(FRECON)), vec![vfd]);
+    bus.faults(10, |f| f.no_response = true);
+    let client = harness.start();
+    let mut updates = client.updates();
+
+    wait(10 * TIMEOUT).await;
+    let event = |e| SoftResponse::Event(10.into(), DeviceType::Vfd, e);
+    // reported once
+    assert_eq!(
+        received(&mut updates),
+        vec![
+            event(DeviceEvent::Configured),
+            event(DeviceEvent::PortUp),
+            event(DeviceEvent::CommErrorBurst(2)),
+            event(DeviceEvent::Offline),
+            SoftResponse::Error(10.into(), DeviceType::Vfd, SoftError::CommFailure),
+        ],
+    );
+    assert_eq!(state.borrow_and_update().health, CommHealth::Error(SoftError::CommFailure));
+
+    // recovers with the slave
+    bus.faults(10, |f| f.no_response = false);
+    wait(10 * TIMEOUT).await;
+    assert_eq!(received(&mut updates), vec![event(DeviceEvent::Online)]);
+    assert_eq!(state.borrow_and_update().health, CommHealth::Ok);
+}
+
+#[tokio::test(start_paused = true)]
+async fn exception_is_reported() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, true)],
+    );
+    bus.faults(10, |f| f.exception = Some(ModbusException::ServerDeviceBusy));
+    let client = harness.start();
+    let mut updates = client.updates();
+
+    wait(100).await;
+    let event = |e| SoftResponse::Event(10.into(), DeviceType::Vfd, e);
+    assert_eq!(
+        received(&mut updates),
+        vec![
+            event(DeviceEvent::Configured),
+            event(DeviceEvent::PortUp),
+            // busy exceptions are retried
+            event(DeviceEvent::CommErrorBurst(2)),
+            event(DeviceEvent::Online),
+            SoftResponse::Error(
+                10.into(),
+                DeviceType::Vfd,
+                SoftError::Exception(ModbusException::ServerDeviceBusy),
+            ),
+        ],
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn device_failure_is_reported_as_fault() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, true)],
+    );
+    bus.faults(10, |f| f.exception = Some(ModbusException::ServerDeviceFailure));
+    let client = harness.start();
+    let mut updates = client.updates();
+
+    wait(100).await;
+    let responses = received(&mut updates);
+    assert_eq!(
+        responses.last(),
+        Some(&SoftResponse::Event(10.into(), DeviceType::Vfd, DeviceEvent::Fault(0))),
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn same_slave_id_on_two_buses() {
+    let mut harness = Harness::new();
+    let mut left = Vfd::new(10.into(), FRECON_MULTIPLE, true);
+    let mut right = Vfd::new(11.into(), FRECON_MULTIPLE, true);
+    left.set_slave_id(1.into());
+    right.set_slave_id(1.into());
+    let left_bus = harness.vfd_bus(Simulator::new().slave(1, VfdSlave::new(FRECON_MULTIPLE)), vec![left]);
+    let right_bus = harness.vfd_bus(Simulator::new().slave(1, VfdSlave::new(FRECON_MULTIPLE)), vec![right]);
+    let client = harness.start();
+
+    client.request(SoftRequest::Run(11.into(), 100)).await.unwrap();
+    wait(1000).await;
+    let status = frame(&[1, 0x03, 0x30, 0x00, 0x00, 0x01]);
+    assert!(left_bus.frames_except(&status).is_empty());
+    assert_eq!(
+        right_bus.frames_except(&status),
+        vec![frame(&[1, 0x10, 0x20, 0x00, 0x00, 0x02, 0x04, 0x00, 0x01, 0x00, 0x64])],
+    );
+
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Stop));
+    let response = client.request(SoftRequest::Status(11.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(11.into(), VfdStatus::Run(100)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn contiguous_telemetry_is_read_with_status() {
+    let commands = VfdCommands {
+        frequency_address: Some(0x3001),
+        current_address: Some(0x3002),
+        voltage_address: Some(0x3003),
+        fault_address: Some(0x3004),
+        ..FRECON_MULTIPLE
+    };
+    let mut harness = Harness::new();
+    let vfd = Vfd::new(10.into(), commands, true);
+    let mut state = vfd.subscribe();
+    let bus = harness.vfd_bus(Simulator::new().slave(10, VfdSlave::new(commands)), vec![vfd]);
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 1000)).await.unwrap();
+    wait(1000).await;
+
+    let status = frame(&[10, 0x03, 0x30, 0x00, 0x00, 0x05]);
+    assert!(bus.frames().contains(&status));
+    assert_eq!(bus.frames_except(&status).len(), 1);
+
+    let telemetry = |t| SoftRequest::Telemetry(10.into(), t);
+    let expected = [
+        (Telemetry::Frequency, 1000),
+        (Telemetry::Current, 100),
+        (Telemetry::Voltage, 540),
+        (Telemetry::Fault, 0),
+    ];
+    for (t, value) in expected {
+        let response = client.request(telemetry(t)).await.unwrap();
+        assert_eq!(response, SoftResponse::Telemetry(10.into(), t, value));
+    }
+    let state = state.borrow_and_update();
+    assert_eq!(state.status.status, VfdStatus::Run(1000));
+    assert_eq!(state.status.telemetry.current, Some(100));
+}
+
+#[tokio::test(start_paused = true)]
+async fn drive_fault_code_is_reported() {
+    let commands = VfdCommands { fault_address: Some(0x5000), ..FRECON };
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(commands).fault(7)),
+        vec![Vfd::new(10.into(), commands, true)],
+    );
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+
+    assert_eq!(
+        bus.frames()[..2],
+        [frame(&[10, 0x03, 0x30, 0x00, 0x00, 0x01]), frame(&[10, 0x03, 0x50, 0x00, 0x00, 0x01])],
+    );
+    let fault = SoftResponse::Event(10.into(), DeviceType::Vfd, DeviceEvent::Fault(7));
+    // reported once
+    assert_eq!(received(&mut updates).iter().filter(|r| **r == fault).count(), 1);
+
+    let response = client.request(SoftRequest::Telemetry(10.into(), Telemetry::Fault)).await.unwrap();
+    assert_eq!(response, SoftResponse::Telemetry(10.into(), Telemetry::Fault, 7));
+    let response = client.request(SoftRequest::Telemetry(10.into(), Telemetry::Voltage)).await.unwrap();
+    assert_eq!(
+        response,
+        SoftResponse::Error(10.into(), DeviceType::Vfd, SoftError::Rejected(RejectReason::NotAvailable)),
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn reset_is_refused_until_the_drive_is_stopped() {
+    let commands = VfdCommands { fault_address: Some(0x5000), ..FRECON_MULTIPLE };
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(commands).fault(7)),
+        vec![Vfd::new(10.into(), commands, true)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    wait(100).await;
+    bus.clear();
+
+    let response = client.request(SoftRequest::Reset(10.into())).await.unwrap();
+    assert_eq!(response, rejected(10, RejectReason::RunPending));
+    wait(100).await;
+    assert!(!bus.frames().contains(&frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x07])));
+
+    // the reset is sent once the stop is written
+    client.request(SoftRequest::Stop(10.into())).await.unwrap();
+    client.request(SoftRequest::Reset(10.into())).await.unwrap();
+    wait(200).await;
+    assert_eq!(
+        bus.frames().into_iter().filter(|f| f[1] != 0x03).collect::<Vec<_>>(),
+        vec![
+            frame(&[10, 0x10, 0x20, 0x00, 0x00, 0x02, 0x04, 0x00, 0x05, 0x00, 0x00]),
+            frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x07]),
+        ],
+    );
+    let response = client.request(SoftRequest::Telemetry(10.into(), Telemetry::Fault)).await.unwrap();
+    assert_eq!(response, SoftResponse::Telemetry(10.into(), Telemetry::Fault, 0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_is_not_written_if_the_reset_fails() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON).fault(7)),
+        vec![Vfd::new(10.into(), FRECON, true)],
+    );
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+    bus.faults(10, |f| f.exception = Some(ModbusException::IllegalDataValue));
+    bus.clear();
+    received(&mut updates);
+
+    // run queued while the reset is sent
+    client.request(SoftRequest::Reset(10.into())).await.unwrap();
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    wait(200).await;
+    let writes = bus.frames().into_iter().filter(|f| f[1] != 0x03).collect::<Vec<_>>();
+    assert_eq!(writes, vec![frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x07])]);
+    let exception = SoftError::Exception(ModbusException::IllegalDataValue);
+    assert!(received(&mut updates).contains(&SoftResponse::Error(10.into(), DeviceType::Vfd, exception)));
+
+    // run queued in the same batch as the reset
+    bus.clear();
+    let (reset, run) = tokio::join!(
+        client.request(SoftRequest::Reset(10.into())),
+        client.request(SoftRequest::Run(10.into(), 500)),
+    );
+    assert!(reset.is_ok() && run.is_ok());
+    wait(200).await;
+    let writes = bus.frames().into_iter().filter(|f| f[1] != 0x03).collect::<Vec<_>>();
+    assert_eq!(writes, vec![frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x07])]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn reset_is_rejected_without_reset_value() {
+    let commands = VfdCommands { reset_value: None, ..FRECON };
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(commands)),
+        vec![Vfd::new(10.into(), commands, false)],
+    );
+    let client = harness.start();
+    let response = client.request(SoftRequest::Reset(10.into())).await.unwrap();
+    assert_eq!(response, rejected(10, RejectReason::NotAvailable));
+    wait(100).await;
+    assert!(bus.frames().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn reference_is_ramped_over_poll_cycles() {
+    let vfd = Vfd::new(10.into(), FRECON, true).ramp(RampConfig::new(1000.0, 2000.0));
+    let mut state = vfd.subscribe();
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(Simulator::new().slave(10, VfdSlave::new(FRECON)), vec![vfd]);
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+
+    // 1000 units/s: half way after 250ms
+    wait(250).await;
+    let progress = state.borrow_and_update().status.ramp.unwrap();
+    assert_eq!(progress.target, 500);
+    assert!((200..=300).contains(&progress.setpoint), "{:?}", progress);
+    assert!(!progress.is_done());
+
+    wait(500).await;
+    let references = written_references(bus.frames());
+    assert!(references.len() > 10, "{:?}", references);
+    assert!(references.windows(2).all(|w| w[0] < w[1]), "{:?}", references);
+    assert_eq!(references.last(), Some(&500));
+    assert_eq!(state.borrow_and_update().status.ramp, Some(RampProgress { setpoint: 500, target: 500 }));
+
+    // reversal: 2000 units/s down to zero, then 1000 units/s up to -500
+    bus.clear();
+    client.request(SoftRequest::Run(10.into(), -500)).await.unwrap();
+    wait(1000).await;
+    let writes: Vec<Vec<u8>> = bus.frames().into_iter().filter(|f| f[1] == 0x06).collect();
+    let reverse = frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x02]);
+    let at = writes.iter().position(|f| *f == reverse).expect("reverse command written");
+    let references = written_references(writes.clone());
+    let before = written_references(writes[..at].to_vec());
+    assert!(before.windows(2).all(|w| w[0] > w[1]), "{:?}", before);
+    assert_eq!(before.last(), Some(&0));
+    assert_eq!(references.last(), Some(&500));
+    assert_eq!(state.borrow_and_update().status.ramp, Some(RampProgress { setpoint: -500, target: -500 }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn stop_is_not_ramped_but_run_to_zero_is() {
+    let vfd = Vfd::new(10.into(), FRECON, false).ramp(RampConfig::new(1000.0, 100.0).s_curve(true));
+    let mut state = vfd.subscribe();
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(Simulator::new().slave(10, VfdSlave::new(FRECON)), vec![vfd]);
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    // the S-curve starts slowly: less than half of a linear ramp (100) after 100ms
+    wait(100).await;
+    let progress = state.borrow_and_update().status.ramp.unwrap();
+    assert!(progress.setpoint > 0 && progress.setpoint < 50, "{:?}", progress);
+
+    wait(1000).await;
+    bus.clear();
+    client.request(SoftRequest::Stop(10.into())).await.unwrap();
+    wait(100).await;
+    assert_eq!(
+        bus.frames(),
+        vec![frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x05]), frame(&[10, 0x06, 0x20, 0x01, 0x00, 0x00])],
+    );
+    assert_eq!(state.borrow_and_update().status.ramp, Some(RampProgress { setpoint: 0, target: 0 }));
+
+    // 100 units/s down to zero, the stop is written once zero is reached
+    client.request(SoftRequest::Run(10.into(), 500)).await.unwrap();
+    wait(1000).await;
+    bus.clear();
+    client.request(SoftRequest::Run(10.into(), 0)).await.unwrap();
+    wait(1000).await;
+    let progress = state.borrow_and_update().status.ramp.unwrap();
+    assert!(progress.setpoint > 400 && progress.target == 0, "{:?}", progress);
+    let stop = frame(&[10, 0x06, 0x20, 0x00, 0x00, 0x05]);
+    assert!(!bus.frames().contains(&stop));
+    wait(7000).await;
+    let frames = bus.frames();
+    let references = written_references(frames.clone());
+    assert!(references.windows(2).all(|w| w[0] > w[1]), "{:?}", references);
+    assert_eq!(references.last(), Some(&0));
+    let at = frames.iter().position(|f| *f == stop).expect("stop written");
+    assert!(written_references(frames[at..].to_vec()).iter().all(|r| *r == 0));
+    assert_eq!(state.borrow_and_update().status.ramp, Some(RampProgress { setpoint: 0, target: 0 }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn reverse_status_is_negative() {
+    let mut harness = Harness::new();
+    harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, true)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), -1500)).await.unwrap();
+    wait(1000).await;
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Run(-1500)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn reference_and_status_use_profile_encoding() {
+    // 0.01 Hz registers, two's complement
+    let encoding = ValueEncoding { sign: SignScheme::TwosComplement, scale: 0.01, offset: 0.0, unit: Unit::Hz };
+    let commands = VfdCommands { encoding, ..FRECON };
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(commands)),
+        vec![Vfd::new(10.into(), commands, true)],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), -30)).await.unwrap();
+    wait(1000).await;
+
+    assert_eq!(written_references(bus.frames()), vec![3000]);
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Run(-30)));
+
+    // 400 Hz does not fit a 16 bits register in 0.01 Hz
+    bus.clear();
+    let response = client.request(SoftRequest::Run(10.into(), 400)).await.unwrap();
+    assert_eq!(response, rejected(10, RejectReason::OutOfRange));
+    wait(100).await;
+    assert!(written_references(bus.frames()).is_empty());
+}
+
+#[test]
+fn ramp_returns_zero_before_reversing() {
+    let mut ramp = Ramp::new(RampConfig::new(1000.0, 1000.0));
+    let start = tokio::time::Instant::now();
+    ramp.set_target(500, start);
+    assert_eq!(ramp.advance(start + Duration::from_secs(1)), 500);
+
+    // the step crosses zero, zero is returned first
+    let reverse = start + Duration::from_secs(1);
+    ramp.set_target(-500, reverse);
+    assert_eq!(ramp.advance(reverse + Duration::from_millis(700)), 0);
+    assert_eq!(ramp.advance(reverse + Duration::from_millis(800)), -100);
+}
+
+#[tokio::test(start_paused = true)]
+async fn nameplate_converts_client_units() {
+    // 50 Hz motor with 2 pole pairs, drive values in 0.01 Hz
+    let nameplate = Nameplate::new(50.0, 2).full_scale(5000.0);
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)).slave(11, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![
+            Vfd::new(10.into(), FRECON_MULTIPLE, true).nameplate(nameplate, Unit::Rpm).unwrap(),
+            Vfd::new(11.into(), FRECON_MULTIPLE, true).nameplate(nameplate, Unit::Percent).unwrap(),
+        ],
+    );
+    let client = harness.start();
+    client.request(SoftRequest::Run(10.into(), -750)).await.unwrap();
+    client.request(SoftRequest::Run(11.into(), 5000)).await.unwrap();
+    wait(3000).await;
+
+    let references = |id: u8| bus.frames().into_iter()
+        .filter(|f| f[0] == id && f[1..4] == [0x10, 0x20, 0x00])
+        .map(|f| u16::from_be_bytes([f[9], f[10]]))
+        .collect::<Vec<_>>();
+    // 750 rpm and 50 % are 25 Hz
+    assert_eq!(references(10), vec![2500]);
+    assert_eq!(references(11), vec![2500]);
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Run(-750)));
+    let response = client.request(SoftRequest::Status(11.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(11.into(), VfdStatus::Run(5000)));
+
+    // 1600 rpm is above the maximum frequency
+    bus.clear();
+    let response = client.request(SoftRequest::Run(10.into(), 1600)).await.unwrap();
+    assert_eq!(response, rejected(10, RejectReason::OutOfRange));
+    wait(100).await;
+    assert!(references(10).is_empty());
+
+    assert!(matches!(
+        Vfd::new(12.into(), FRECON_MULTIPLE, true).nameplate(Nameplate::new(50.0, 0), Unit::Rpm),
+        Err(Error::InvalidNameplate(_)),
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn parameters_are_read_and_written_through() {
+    // acceleration time, deceleration time and max frequency
+    let slave = VfdSlave::new(FRECON).parameter(0xF00A, 100).parameter(0xF00B, 200).parameter(0xF00C, 5000);
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, slave),
+        vec![Vfd::new(10.into(), FRECON, false).writable_parameters([0xF00A..=0xF00B])],
+    );
+    let client = harness.start();
+    let mut updates = client.updates();
+    let rejected = |reason| SoftResponse::Error(10.into(), DeviceType::Vfd, SoftError::Rejected(reason));
+
+    // nothing read yet
+    let response = client.request(SoftRequest::WriteParameter(10.into(), 0, 150)).await.unwrap();
+    assert_eq!(response, rejected(RejectReason::NotAvailable));
+
+    let response = client.request(SoftRequest::ReadParameter(10.into(), 0xF00A, 3)).await.unwrap();
+    let block = ParameterBlock::new(0xF00A, &[100, 200, 5000]).unwrap();
+    assert_eq!(response, SoftResponse::Parameters(10.into(), block));
+    assert!(!received(&mut updates).iter().any(|r| matches!(r, SoftResponse::Parameters(..))));
+    assert_eq!(bus.frames(), vec![frame(&[10, 0x03, 0xF0, 0x0A, 0x00, 0x03])]);
+    // one frame per register
+    let raw = response.to_raw().unwrap();
+    assert_eq!(raw.len(), 24);
+    assert_eq!(raw[16..22], [10, 2, 9, 2, 0x13, 0x88]);
+    bus.clear();
+
+    let response = client.request(SoftRequest::WriteParameter(10.into(), 1, 150)).await.unwrap();
+    assert_eq!(response, SoftResponse::ParameterWritten(10.into(), 0xF00B, 1, 150));
+    assert_eq!(response.to_raw().unwrap()[..6], [10, 2, 10, 1, 0x00, 0x96]);
+    assert_eq!(bus.frames(), vec![frame(&[10, 0x06, 0xF0, 0x0B, 0x00, 0x96])]);
+    // the max frequency is not in the allowlist
+    let response = client.request(SoftRequest::WriteParameter(10.into(), 2, 6000)).await.unwrap();
+    assert_eq!(response, rejected(RejectReason::NotWritable));
+    let response = client.request(SoftRequest::ReadParameter(10.into(), 0xF00B, 1)).await.unwrap();
+    assert_eq!(response, SoftResponse::Parameters(10.into(), ParameterBlock::new(0xF00B, &[150]).unwrap()));
+
+    // answered even if the same exception was reported before
+    for _ in 0..2 {
+        let response = client.request(SoftRequest::ReadParameter(10.into(), 0xF100, 1)).await.unwrap();
+        assert_eq!(
+            response,
+            SoftResponse::Error(10.into(), DeviceType::Vfd, SoftError::Exception(ModbusException::IllegalDataAddress)),
+        );
+    }
+    // a failed read keeps the address of the last block read
+    let response = client.request(SoftRequest::WriteParameter(10.into(), 0, 120)).await.unwrap();
+    assert_eq!(response, SoftResponse::ParameterWritten(10.into(), 0xF00B, 0, 120));
+    let response = client.request(SoftRequest::ReadParameter(10.into(), 0xF00A, 9)).await.unwrap();
+    assert_eq!(response, rejected(RejectReason::OutOfRange));
+}
+
+#[tokio::test(start_paused = true)]
+async fn drive_is_reconciled_with_desired_state() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![Vfd::new(10.into(), FRECON_MULTIPLE, true).reconcile(ReconcileConfig::new(10, Duration::from_secs(2), 3))],
+    );
+    let client = harness.start();
+    let mut updates = client.updates();
+    client.request(SoftRequest::Run(10.into(), 1500)).await.unwrap();
+    wait(3000).await;
+    let run = frame(&[10, 0x10, 0x20, 0x00, 0x00, 0x02, 0x04, 0x00, 0x01, 0x05, 0xDC]);
+    assert_eq!(bus.frames_except(&status_frame()), vec![run.clone()]);
+    bus.clear();
+
+    // stopped locally, command and reference are written again once settled
+    bus.inject(&[10, 0x06, 0x20, 0x00, 0x00, 0x05]);
+    wait(1000).await;
+    assert!(bus.frames_except(&status_frame()).is_empty());
+    wait(2000).await;
+    assert_eq!(bus.frames_except(&status_frame()), vec![run]);
+    wait(1000).await;
+    let response = client.request(SoftRequest::Status(10.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(10.into(), VfdStatus::Run(1500)));
+    assert!(!received(&mut updates).contains(&SoftResponse::Event(10.into(), DeviceType::Vfd, DeviceEvent::NotFollowing)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn not_following_drive_is_reported() {
+    let commands = VfdCommands { fault_address: Some(0x5000), ..FRECON_MULTIPLE };
+    let mut harness = Harness::new();
+    let vfd = Vfd::new(10.into(), commands, true).reconcile(ReconcileConfig::new(10, Duration::from_secs(1), 2));
+    let mut state = vfd.subscribe();
+    let bus = harness.vfd_bus(Simulator::new().slave(10, VfdSlave::new(commands).fault(7)), vec![vfd]);
+    let client = harness.start();
+    let mut updates = client.updates();
+    client.request(SoftRequest::Run(10.into(), 1000)).await.unwrap();
+    wait(5000).await;
+
+    let writes = |frames: Vec<Vec<u8>>| frames.into_iter().filter(|f| f[1] == 0x10).count();
+    // the first write and 2 attempts
+    assert_eq!(writes(bus.frames()), 3);
+    let event = |e| SoftResponse::Event(10.into(), DeviceType::Vfd, e);
+    let events = received(&mut updates);
+    assert_eq!(events.iter().filter(|r| **r == event(DeviceEvent::NotFollowing)).count(), 1);
+    assert!(state.borrow_and_update().status.not_following);
+
+    // follows once the fault is reset
+    client.request(SoftRequest::Stop(10.into())).await.unwrap();
+    client.request(SoftRequest::Reset(10.into())).await.unwrap();
+    wait(100).await;
+    client.request(SoftRequest::Run(10.into(), 1000)).await.unwrap();
+    wait(1000).await;
+    assert!(received(&mut updates).contains(&event(DeviceEvent::Following)));
+    assert!(!state.borrow_and_update().status.not_following);
+}
+
+/// Return the requests rejections received so far by an updates subscriber.
+fn rejections(updates: &mut tokio::sync::broadcast::Receiver<SoftResponse>) -> Vec<(u8, RejectReason)> {
+    received(updates).into_iter()
+        .filter_map(|r| match r {
+            SoftResponse::Error(id, _, SoftError::Rejected(reason)) => Some((id.into(), reason)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_requests_are_checked_against_limits() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON_MULTIPLE)).slave(11, VfdSlave::new(FRECON_MULTIPLE)),
+        vec![
+            Vfd::new(10.into(), FRECON_MULTIPLE, true).limits(VfdLimits::new().reference(100, 1500).forbid(Dir::Rv)),
+            Vfd::new(11.into(), FRECON_MULTIPLE, true).limits(VfdLimits::new().stop_before_reversal(Duration::from_secs(1))),
+        ],
+    );
+    let client = harness.start();
+    let mut responses = vec![];
+    for r in [50, 2000, -500, 1000] {
+        responses.push(client.request(SoftRequest::Run(10.into(), r)).await.unwrap());
+    }
+    assert_eq!(
+        responses,
+        vec![
+            rejected(10, RejectReason::OutOfRange),
+            rejected(10, RejectReason::OutOfRange),
+            rejected(10, RejectReason::DirectionForbidden),
+            SoftResponse::Accepted(10.into(), FunctionType::Run),
+        ],
+    );
+    wait(100).await;
+    let references = bus.frames().into_iter()
+        .filter(|f| f[0] == 10 && f[1] == 0x10)
+        .map(|f| u16::from_be_bytes([f[9], f[10]]))
+        .collect::<Vec<_>>();
+    assert_eq!(references, vec![1000]);
+
+    // read stopped for the dwell time before the run command, the reversal needs a new stop
+    let stop_required = rejected(11, RejectReason::StopRequired);
+    let accepted = SoftResponse::Accepted(11.into(), FunctionType::Run);
+    wait(1000).await;
+    assert_eq!(client.request(SoftRequest::Run(11.into(), 1000)).await, Ok(accepted));
+    assert_eq!(client.request(SoftRequest::Run(11.into(), -1000)).await, Ok(stop_required));
+    wait(1000).await;
+    assert_eq!(client.request(SoftRequest::Run(11.into(), -1000)).await, Ok(stop_required));
+    let response = client.request(SoftRequest::Stop(11.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Accepted(11.into(), FunctionType::Stop));
+    // stopped, but not for the dwell time
+    wait(1000).await;
+    assert_eq!(client.request(SoftRequest::Run(11.into(), -1000)).await, Ok(stop_required));
+    wait(1000).await;
+    assert_eq!(client.request(SoftRequest::Run(11.into(), -1000)).await, Ok(accepted));
+    wait(1000).await;
+    let response = client.request(SoftRequest::Status(11.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(11.into(), VfdStatus::Run(-1000)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn pump_is_interlocked_with_fan() {
+    let fan = Vfd::new(12.into(), FRECON, true);
+    let pump = Vfd::new(20.into(), FRECON, true).interlock(12.into(), fan.subscribe());
+    let mut harness = Harness::new();
+    harness.vfd_bus(
+        Simulator::new().slave(12, VfdSlave::new(FRECON)).slave(20, VfdSlave::new(FRECON)),
+        vec![fan, pump],
+    );
+    let client = harness.start();
+    let mut updates = client.updates();
+    let interlocked = rejected(20, RejectReason::Interlocked(12.into()));
+    assert_eq!(client.request(SoftRequest::Run(20.into(), 1000)).await, Ok(interlocked));
+    // the interlocking device is sent in DATA3
+    assert_eq!(interlocked.to_raw().unwrap()[..6], [20, 2, 0x80, 3, 8, 12]);
+
+    client.request(SoftRequest::Run(12.into(), 500)).await.unwrap();
+    wait(1000).await;
+    client.request(SoftRequest::Run(20.into(), 1000)).await.unwrap();
+    wait(1000).await;
+    assert!(rejections(&mut updates).is_empty());
+    let response = client.request(SoftRequest::Status(20.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(20.into(), VfdStatus::Run(1000)));
+
+    // the pump stops with the fan
+    client.request(SoftRequest::Stop(12.into())).await.unwrap();
+    wait(1000).await;
+    assert_eq!(rejections(&mut updates), vec![(20, RejectReason::Interlocked(12.into()))]);
+    let response = client.request(SoftRequest::Status(20.into())).await.unwrap();
+    assert_eq!(response, SoftResponse::Status(20.into(), VfdStatus::Stop));
+}
+
+#[tokio::test(start_paused = true)]
+async fn status_polling_is_switched_at_runtime() {
+    let mut harness = Harness::new();
+    let bus = harness.vfd_bus(
+        Simulator::new().slave(10, VfdSlave::new(FRECON)),
+        vec![Vfd::new(10.into(), FRECON, false)],
+    );
+    let client = harness.start();
+    let mut updates = client.updates();
+    wait(100).await;
+    assert!(bus.frames().is_empty());
+
+    let response = client.request(SoftRequest::Polling(10.into(), true, true)).await.unwrap();
+    assert_eq!(response, SoftResponse::Polling(10.into(), true, true));
+    assert_eq!(response.to_raw().unwrap()[..6], [10, 2, 12, 1, 1, 0]);
+    wait(100).await;
+    assert_eq!(bus.frames().first(), Some(&status_frame()));
+    assert!(received(&mut updates).contains(&SoftResponse::Status(10.into(), VfdStatus::Stop)));
+
+    let response = client.request(SoftRequest::Polling(10.into(), false, false)).await.unwrap();
+    assert_eq!(response, SoftResponse::Polling(10.into(), false, false));
+    wait(10).await;
+    bus.clear();
+    received(&mut updates);
+    wait(100).await;
+    assert!(bus.frames().is_empty());
+    assert!(received(&mut updates).is_empty());
+
+    // polling flags are 0 or 1
+    let request = frame(&[10, 1, 12, 1, 2, 0]);
+    assert!(matches!(SoftRequest::try_from(request.as_slice()), Err(Error::WrongFlag)));
+    let request = frame(&[10, 1, 12, 1, 0, 0]);
+    assert!(matches!(SoftRequest::try_from(request.as_slice()), Ok(SoftRequest::Polling(_, true, false))));
+}